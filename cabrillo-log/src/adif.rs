@@ -0,0 +1,820 @@
+//! ADIF 3.x import and export.
+//!
+//! Both the tagged `.adi` format and the `.adx` XML format are supported. The
+//! fields that have a Cabrillo counterpart (FREQ/BAND, MODE/SUBMODE,
+//! QSO_DATE/TIME_ON, STATION_CALLSIGN, CALL, RST_SENT/RST_RCVD, STX/SRX and
+//! STX_STRING/SRX_STRING) are mapped onto a [`QSO`]. Everything else, and any
+//! mapped value the QSO cannot reproduce, is kept in [`AdifRecord::fields`] and
+//! written back unchanged, so a round trip through [`QSO`] loses nothing.
+
+use crate::{
+    Band, CabrilloError, CabrilloLog, Exchange, ExchangeField, FieldKind, Frequency, Header, Mode,
+    QSO,
+};
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use std::borrow::Cow;
+use std::fmt::Write as _;
+
+/// Fields that are mapped onto [`QSO`].
+const MAPPED_FIELDS: &[&str] = &[
+    "QSO_DATE",
+    "TIME_ON",
+    "STATION_CALLSIGN",
+    "CALL",
+    "FREQ",
+    "BAND",
+    "MODE",
+    "SUBMODE",
+    "RST_SENT",
+    "STX",
+    "STX_STRING",
+    "RST_RCVD",
+    "SRX",
+    "SRX_STRING",
+];
+
+/// An ADIF file: optional header and a list of records.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Adif {
+    /// Free text preceding the header fields of an `.adi` file.
+    pub preamble: String,
+    /// Header fields such as `ADIF_VER` and `PROGRAMID`.
+    pub header: Vec<(String, String)>,
+    pub records: Vec<AdifRecord>,
+}
+
+/// A single ADIF record mapped onto a [`QSO`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdifRecord {
+    pub qso: QSO,
+    /// Fields not represented in `qso`, in file order with upper-case names.
+    pub fields: Vec<(String, String)>,
+}
+
+impl Adif {
+    /// Parse ADIF content, detecting whether it is `.adi` or `.adx`.
+    pub fn parse(content: &str) -> Result<Self, CabrilloError> {
+        let start = content.trim_start();
+        if start.starts_with("<?xml")
+            || start
+                .get(..4)
+                .is_some_and(|s| s.eq_ignore_ascii_case("<ADX"))
+        {
+            Self::parse_adx(content)
+        } else {
+            Self::parse_adi(content)
+        }
+    }
+
+    /// Parse a file from disk, detecting the format from its content.
+    pub fn parse_from_file(path: &str) -> Result<Self, CabrilloError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| CabrilloError::ParseError(e.to_string()))?;
+        Self::parse(&content)
+    }
+
+    /// Parse the tagged `.adi` format.
+    pub fn parse_adi(content: &str) -> Result<Self, CabrilloError> {
+        let mut adif = Adif::default();
+        let mut fields = Vec::new();
+        let mut pos = 0;
+
+        if !content.trim_start().starts_with('<') {
+            pos = content.find('<').unwrap_or(content.len());
+            adif.preamble = content[..pos].trim().to_string();
+        }
+
+        while let Some(offset) = content[pos..].find('<') {
+            let start = pos + offset + 1;
+            let end = content[start..]
+                .find('>')
+                .map(|i| start + i)
+                .ok_or_else(|| CabrilloError::InvalidFormat("Unterminated ADIF tag".to_string()))?;
+            let tag = &content[start..end];
+            pos = end + 1;
+
+            let mut spec = tag.split(':');
+            let name = spec.next().unwrap_or("").trim().to_ascii_uppercase();
+            match name.as_str() {
+                "EOH" => {
+                    adif.header = std::mem::take(&mut fields);
+                    continue;
+                }
+                "EOR" => {
+                    adif.records
+                        .push(AdifRecord::from_fields(std::mem::take(&mut fields))?);
+                    continue;
+                }
+                _ => {}
+            }
+            // A tag without a length carries no data
+            let Some(length) = spec.next() else {
+                continue;
+            };
+            let length: usize = length.trim().parse().map_err(|_| {
+                CabrilloError::InvalidFormat(format!("Invalid ADIF field length: <{}>", tag))
+            })?;
+            let value_end = content[pos..]
+                .char_indices()
+                .nth(length)
+                .map_or(content.len(), |(i, _)| pos + i);
+            fields.push((name, content[pos..value_end].to_string()));
+            pos = value_end;
+        }
+
+        Ok(adif)
+    }
+
+    /// Parse the `.adx` XML format.
+    pub fn parse_adx(content: &str) -> Result<Self, CabrilloError> {
+        let mut adif = Adif::default();
+        let mut reader = XmlReader { rest: content };
+        let mut in_header = false;
+        let mut fields: Option<Vec<(String, String)>> = None; // Inside HEADER or RECORD
+        let mut field: Option<(String, String)> = None; // Inside a field element
+
+        while let Some(event) = reader.next_event()? {
+            match event {
+                XmlEvent::Start { name, attrs, empty } => {
+                    let name = name.to_ascii_uppercase();
+                    match (name.as_str(), fields.as_mut()) {
+                        ("HEADER", None) | ("RECORD", None) => {
+                            in_header = name == "HEADER";
+                            fields = Some(Vec::new());
+                        }
+                        (_, Some(list)) => {
+                            let name = adx_field_name(&name, &attrs, in_header);
+                            if empty {
+                                list.push((name, String::new()));
+                            } else {
+                                field = Some((name, String::new()));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                XmlEvent::Text(text) => {
+                    if let Some((_, value)) = field.as_mut() {
+                        value.push_str(&text);
+                    }
+                }
+                XmlEvent::End => {
+                    if let Some(done) = field.take() {
+                        if let Some(list) = fields.as_mut() {
+                            list.push(done);
+                        }
+                    } else if let Some(list) = fields.take() {
+                        if in_header {
+                            adif.header = list;
+                        } else {
+                            adif.records.push(AdifRecord::from_fields(list)?);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(adif)
+    }
+
+    /// Write the tagged `.adi` format.
+    pub fn to_adi(&self) -> String {
+        let mut out = String::new();
+        if !self.header.is_empty() || !self.preamble.is_empty() {
+            // A header must not start with '<'
+            if self.preamble.is_empty() {
+                out.push_str("Generated by cabrillo-log");
+            } else {
+                out.push_str(&self.preamble);
+            }
+            out.push('\n');
+            for (name, value) in &self.header {
+                write_adi_field(&mut out, name, value);
+                out.push('\n');
+            }
+            out.push_str("<EOH>\n\n");
+        }
+        for record in &self.records {
+            for (name, value) in record.to_fields() {
+                write_adi_field(&mut out, &name, &value);
+                out.push(' ');
+            }
+            out.push_str("<EOR>\n");
+        }
+        out
+    }
+
+    /// Write the `.adx` XML format. The `.adi` preamble has no ADX equivalent and is dropped.
+    pub fn to_adx(&self) -> String {
+        // Names of the user-defined fields, from `USERDEFn` header values such
+        // as `EPC` or `SWEATER,{S,M,L}`
+        let userdefs: Vec<&str> = self
+            .header
+            .iter()
+            .filter(|(name, _)| name.starts_with("USERDEF"))
+            .filter_map(|(_, value)| value.split([',', ' ']).next())
+            .collect();
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ADX>\n");
+        out.push_str("  <HEADER>\n");
+        for (name, value) in &self.header {
+            out.push_str("    ");
+            write_adx_field(&mut out, name, value, true);
+        }
+        out.push_str("  </HEADER>\n  <RECORDS>\n");
+        for record in &self.records {
+            out.push_str("    <RECORD>\n");
+            for (name, value) in record.to_fields() {
+                out.push_str("      ");
+                if userdefs.iter().any(|u| u.eq_ignore_ascii_case(&name)) {
+                    let _ = writeln!(
+                        out,
+                        "<USERDEF FIELDNAME=\"{}\">{}</USERDEF>",
+                        xml_escape(&name),
+                        xml_escape(&value)
+                    );
+                } else {
+                    write_adx_field(&mut out, &name, &value, false);
+                }
+            }
+            out.push_str("    </RECORD>\n");
+        }
+        out.push_str("  </RECORDS>\n</ADX>\n");
+        out
+    }
+
+    /// Convert to a Cabrillo log, taking `CALLSIGN:` from the first station callsign.
+    pub fn to_log(&self) -> CabrilloLog {
+        let mut log = CabrilloLog {
+            qsos: self.records.iter().map(|r| r.qso.clone()).collect(),
+            ..Default::default()
+        };
+        if let Some(call) = log
+            .qsos
+            .iter()
+            .map(|q| &q.sent_call)
+            .find(|c| !c.is_empty())
+        {
+            log.headers.push(Header::Callsign(call.clone()));
+        }
+        log
+    }
+}
+
+impl From<&CabrilloLog> for Adif {
+    fn from(log: &CabrilloLog) -> Self {
+        Adif {
+            preamble: String::new(),
+            header: vec![
+                ("ADIF_VER".to_string(), "3.1.4".to_string()),
+                ("PROGRAMID".to_string(), "cabrillo-log".to_string()),
+                (
+                    "PROGRAMVERSION".to_string(),
+                    env!("CARGO_PKG_VERSION").to_string(),
+                ),
+            ],
+            records: log.qsos.iter().cloned().map(AdifRecord::from).collect(),
+        }
+    }
+}
+
+impl From<QSO> for AdifRecord {
+    fn from(qso: QSO) -> Self {
+        AdifRecord {
+            qso,
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl AdifRecord {
+    /// Map the fields of one record onto a QSO, keeping everything else.
+    pub fn from_fields(fields: Vec<(String, String)>) -> Result<Self, CabrilloError> {
+        let get = |name: &str| {
+            fields
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.trim())
+        };
+
+        let date = get("QSO_DATE")
+            .ok_or_else(|| CabrilloError::MissingRequiredField("QSO_DATE".to_string()))?;
+        let date = NaiveDate::parse_from_str(date, "%Y%m%d")
+            .map_err(|_| CabrilloError::InvalidDate(date.to_string()))?;
+        let time = get("TIME_ON")
+            .ok_or_else(|| CabrilloError::MissingRequiredField("TIME_ON".to_string()))?;
+        let time = match time.len() {
+            4 => NaiveTime::parse_from_str(time, "%H%M"),
+            _ => NaiveTime::parse_from_str(time, "%H%M%S"),
+        }
+        .map_err(|_| CabrilloError::InvalidTime(time.to_string()))?;
+        let rcvd_call =
+            get("CALL").ok_or_else(|| CabrilloError::MissingRequiredField("CALL".to_string()))?;
+
+        let freq = match (get("FREQ"), get("BAND")) {
+            (Some(mhz), _) => shift_decimal(mhz, 3)
+                .and_then(|khz| khz.parse().ok())
+                .ok_or_else(|| CabrilloError::InvalidFormat(format!("Invalid FREQ: {}", mhz)))?,
+            (None, Some(band)) => band
+                .parse::<Band>()
+                .map(Frequency::from)
+                .map_err(|_| CabrilloError::InvalidFormat(format!("Invalid BAND: {}", band)))?,
+            (None, None) => {
+                return Err(CabrilloError::MissingRequiredField("FREQ".to_string()));
+            }
+        };
+
+        let adif_mode =
+            get("MODE").ok_or_else(|| CabrilloError::MissingRequiredField("MODE".to_string()))?;
+        let submode = get("SUBMODE");
+        let mode = Mode::from_adif(adif_mode, submode);
+        // Keep MODE/SUBMODE when the mode table cannot reproduce them, e.g. an unknown submode.
+        let (mapped_mode, mapped_submode) = mode.adif();
+        let keep_mode = !mapped_mode.eq_ignore_ascii_case(adif_mode)
+            || mapped_submode.map(str::to_ascii_uppercase) != submode.map(str::to_ascii_uppercase);
+        let keep_band = get("FREQ").is_none();
+
+        let qso = QSO {
+            freq,
+            mode,
+            date,
+            time,
+            sent_call: get("STATION_CALLSIGN").unwrap_or("").to_string(),
+            sent_exch: exchange(get("RST_SENT"), get("STX"), get("STX_STRING")),
+            rcvd_call: rcvd_call.to_string(),
+            rcvd_exch: exchange(get("RST_RCVD"), get("SRX"), get("SRX_STRING")),
+            tx: None,
+            excluded: false,
+            zero_point: false,
+        };
+        let fields = fields
+            .into_iter()
+            .filter(|(name, _)| match name.as_str() {
+                "MODE" | "SUBMODE" => keep_mode,
+                "BAND" => keep_band,
+                name => !MAPPED_FIELDS.contains(&name),
+            })
+            .collect();
+
+        Ok(AdifRecord { qso, fields })
+    }
+
+    /// Value of a kept field by name.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// All fields of the record: mapped QSO fields first, then the kept ones.
+    ///
+    /// Kept MODE/SUBMODE and BAND values are used only while they still agree
+    /// with the QSO; otherwise they are derived from it again.
+    pub fn to_fields(&self) -> Vec<(String, String)> {
+        let qso = &self.qso;
+        let mut out: Vec<(String, String)> = Vec::new();
+        let mut push = |name: &str, value: &str| out.push((name.to_string(), value.to_string()));
+
+        push("QSO_DATE", &qso.date.format("%Y%m%d").to_string());
+        let time = if qso.time.second() == 0 {
+            qso.time.format("%H%M")
+        } else {
+            qso.time.format("%H%M%S")
+        };
+        push("TIME_ON", &time.to_string());
+        if !qso.sent_call.is_empty() {
+            push("STATION_CALLSIGN", &qso.sent_call);
+        }
+        push("CALL", &qso.rcvd_call);
+
+        match self.field("BAND") {
+            Some(band) if band.parse::<Band>().ok().map(Frequency::from) == Some(qso.freq) => {
+                push("BAND", band)
+            }
+            _ => {
+                if let Some(mhz) = qso
+                    .freq
+                    .khz()
+                    .and_then(|k| shift_decimal(&k.to_string(), -3))
+                {
+                    push("FREQ", &mhz);
+                }
+                if let Some(band) = qso.freq.band() {
+                    push("BAND", band.name());
+                }
+            }
+        }
+
+        match self.field("MODE") {
+            Some(mode) if Mode::from_adif(mode, self.field("SUBMODE")) == qso.mode => {
+                push("MODE", mode);
+                if let Some(submode) = self.field("SUBMODE") {
+                    push("SUBMODE", submode);
+                }
+            }
+            _ => {
+                let (mode, submode) = qso.mode.adif();
+                push("MODE", mode);
+                if let Some(submode) = submode {
+                    push("SUBMODE", submode);
+                }
+            }
+        }
+
+        for (name, value) in exchange_fields(&qso.sent_exch, ["RST_SENT", "STX", "STX_STRING"]) {
+            push(name, &value);
+        }
+        for (name, value) in exchange_fields(&qso.rcvd_exch, ["RST_RCVD", "SRX", "SRX_STRING"]) {
+            push(name, &value);
+        }
+
+        out.extend(
+            self.fields
+                .iter()
+                .filter(|(name, _)| !MAPPED_FIELDS.contains(&name.as_str()))
+                .cloned(),
+        );
+        out
+    }
+}
+
+/// Build an exchange from the report, serial and free-text ADIF fields.
+fn exchange(rst: Option<&str>, serial: Option<&str>, text: Option<&str>) -> Exchange {
+    let mut fields = Vec::new();
+    if let Some(rst) = rst.filter(|v| !v.is_empty()) {
+        fields.push(ExchangeField::new("rst", FieldKind::Rst, rst));
+    }
+    if let Some(serial) = serial.filter(|v| !v.is_empty()) {
+        fields.push(ExchangeField::new("serial", FieldKind::Serial, serial));
+    }
+    for (i, token) in text.unwrap_or("").split_whitespace().enumerate() {
+        fields.push(ExchangeField::new(
+            format!("exch{}", i + 1),
+            FieldKind::Text,
+            token,
+        ));
+    }
+    Exchange::new(fields)
+}
+
+/// Split an exchange into report, serial and free-text ADIF fields.
+fn exchange_fields(exch: &Exchange, names: [&'static str; 3]) -> Vec<(&'static str, String)> {
+    let rst = exch.fields.iter().position(|f| f.kind == FieldKind::Rst);
+    let serial = exch.fields.iter().position(|f| f.kind == FieldKind::Serial);
+    let mut out = Vec::new();
+    if let Some(i) = rst {
+        out.push((names[0], exch.fields[i].value.clone()));
+    }
+    if let Some(i) = serial {
+        out.push((names[1], exch.fields[i].value.clone()));
+    }
+    let text: Vec<&str> = exch
+        .fields
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| Some(*i) != rst && Some(*i) != serial)
+        .map(|(_, f)| f.value.as_str())
+        .collect();
+    if !text.is_empty() {
+        out.push((names[2], text.join(" ")));
+    }
+    out
+}
+
+/// Move the decimal point of a non-negative decimal number, e.g. MHz to kHz.
+///
+/// Works on the digits rather than on `f64`, so no precision is lost.
+fn shift_decimal(value: &str, places: i32) -> Option<String> {
+    let (int, frac) = value.trim().split_once('.').unwrap_or((value.trim(), ""));
+    let digits = format!("{}{}", int, frac);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let point = int.len() as i32 + places;
+    let (int, frac) = if point <= 0 {
+        (String::new(), "0".repeat(-point as usize) + &digits)
+    } else if point as usize >= digits.len() {
+        (
+            digits.clone() + &"0".repeat(point as usize - digits.len()),
+            String::new(),
+        )
+    } else {
+        let (int, frac) = digits.split_at(point as usize);
+        (int.to_string(), frac.to_string())
+    };
+    let int = match int.trim_start_matches('0') {
+        "" => "0",
+        int => int,
+    };
+    Some(match frac.trim_end_matches('0') {
+        "" => int.to_string(),
+        frac => format!("{}.{}", int, frac),
+    })
+}
+
+fn write_adi_field(out: &mut String, name: &str, value: &str) {
+    let _ = write!(out, "<{}:{}>{}", name, value.chars().count(), value);
+}
+
+/// Write a field element; `USERDEFn` declarations only exist in the header.
+fn write_adx_field(out: &mut String, name: &str, value: &str, in_header: bool) {
+    let value = xml_escape(value);
+    if let Some((program, field)) = name.strip_prefix("APP_").and_then(|n| n.split_once('_')) {
+        let _ = writeln!(
+            out,
+            "<APP PROGRAMID=\"{}\" FIELDNAME=\"{}\">{}</APP>",
+            xml_escape(program),
+            xml_escape(field),
+            value
+        );
+    } else if let Some(id) = name
+        .strip_prefix("USERDEF")
+        .filter(|id| in_header && !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+    {
+        let _ = writeln!(out, "<USERDEF FIELDID=\"{}\">{}</USERDEF>", id, value);
+    } else {
+        let _ = writeln!(out, "<{}>{}</{}>", name, value, name);
+    }
+}
+
+/// ADI-style name of an ADX field element, folding `APP` and `USERDEF` attributes in.
+fn adx_field_name(name: &str, attrs: &[(&str, String)], in_header: bool) -> String {
+    let attr = |key: &str| {
+        attrs
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+            .unwrap_or("")
+    };
+    match name {
+        "APP" => format!("APP_{}_{}", attr("PROGRAMID"), attr("FIELDNAME")).to_ascii_uppercase(),
+        "USERDEF" if in_header => format!("USERDEF{}", attr("FIELDID")),
+        "USERDEF" => attr("FIELDNAME").to_ascii_uppercase(),
+        _ => name.to_string(),
+    }
+}
+
+fn xml_escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"', '\'']) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;"),
+    )
+}
+
+fn xml_unescape(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// Event produced by [`XmlReader`].
+enum XmlEvent<'a> {
+    Start {
+        name: &'a str,
+        attrs: Vec<(&'a str, String)>,
+        empty: bool,
+    },
+    End,
+    Text(Cow<'a, str>),
+}
+
+/// Minimal pull parser for the flat element structure of ADX files.
+struct XmlReader<'a> {
+    rest: &'a str,
+}
+
+impl<'a> XmlReader<'a> {
+    fn next_event(&mut self) -> Result<Option<XmlEvent<'a>>, CabrilloError> {
+        loop {
+            if self.rest.is_empty() {
+                return Ok(None);
+            }
+            if !self.rest.starts_with('<') {
+                let end = self.rest.find('<').unwrap_or(self.rest.len());
+                let (text, rest) = self.rest.split_at(end);
+                self.rest = rest;
+                return Ok(Some(XmlEvent::Text(xml_unescape(text))));
+            }
+            if let Some(rest) = self.rest.strip_prefix("<![CDATA[") {
+                let end = rest.find("]]>").ok_or_else(|| unterminated("CDATA"))?;
+                self.rest = &rest[end + 3..];
+                return Ok(Some(XmlEvent::Text(Cow::Borrowed(&rest[..end]))));
+            }
+            if let Some(rest) = self.rest.strip_prefix("<!--") {
+                let end = rest.find("-->").ok_or_else(|| unterminated("comment"))?;
+                self.rest = &rest[end + 3..];
+                continue;
+            }
+
+            let end = self.rest.find('>').ok_or_else(|| unterminated("tag"))?;
+            let tag = &self.rest[1..end];
+            self.rest = &self.rest[end + 1..];
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            if tag.starts_with('/') {
+                return Ok(Some(XmlEvent::End));
+            }
+
+            let empty = tag.ends_with('/');
+            let tag = tag.trim_end_matches('/').trim();
+            let (name, mut attrs_text) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+            let mut attrs = Vec::new();
+            while let Some(eq) = attrs_text.find('=') {
+                let key = attrs_text[..eq].trim();
+                let value = attrs_text[eq + 1..].trim_start();
+                let quote = value
+                    .chars()
+                    .next()
+                    .ok_or_else(|| unterminated("attribute"))?;
+                let value = &value[quote.len_utf8()..];
+                let close = value.find(quote).ok_or_else(|| unterminated("attribute"))?;
+                attrs.push((key, xml_unescape(&value[..close]).into_owned()));
+                attrs_text = &value[close + 1..];
+            }
+            return Ok(Some(XmlEvent::Start { name, attrs, empty }));
+        }
+    }
+}
+
+fn unterminated(what: &str) -> CabrilloError {
+    CabrilloError::InvalidFormat(format!("Unterminated XML {}", what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CabrilloMode;
+
+    const ADI: &str = "Exported by a general logger
+<ADIF_VER:5>3.1.4 <PROGRAMID:6>Logger
+<EOH>
+<QSO_DATE:8>20231001 <TIME_ON:6>120030 <STATION_CALLSIGN:4>N1MM <CALL:4>W1AW
+<FREQ:6>14.025 <BAND:3>20m <MODE:4>MFSK <SUBMODE:3>FT4 <RST_SENT:3>-10 <RST_RCVD:3>-12
+<GRIDSQUARE:4>FN31 <COMMENT:10>Nice <sig> <EOR>
+<QSO_DATE:8>20231001 <TIME_ON:4>1205 <CALL:5>SP5XX <BAND:3>40m <MODE:2>CW
+<RST_SENT:3>599 <STX:3>001 <RST_RCVD:3>599 <SRX:2>42 <SRX_STRING:5>ON 3A <EOR>
+";
+
+    #[test]
+    fn test_parse_adi_maps_and_keeps_fields() {
+        let adif = Adif::parse(ADI).unwrap();
+        assert_eq!(adif.preamble, "Exported by a general logger");
+        assert_eq!(adif.header.len(), 2);
+        assert_eq!(adif.records.len(), 2);
+
+        let first = &adif.records[0];
+        assert_eq!(first.qso.freq, Frequency::Khz(14025.0));
+        assert_eq!(first.qso.mode, Mode::precise("FT4"));
+        assert_eq!(first.qso.sent_call, "N1MM");
+        assert_eq!(first.qso.time, NaiveTime::from_hms_opt(12, 0, 30).unwrap());
+        assert_eq!(first.field("SUBMODE"), None);
+        assert!(adif.to_adi().contains("<MODE:4>MFSK <SUBMODE:3>FT4"));
+        assert_eq!(first.field("COMMENT"), Some("Nice <sig>"));
+
+        let second = &adif.records[1];
+        assert_eq!(second.qso.freq, Frequency::Khz(7000.0));
+        assert_eq!(second.qso.mode, CabrilloMode::Cw);
+        assert_eq!(second.qso.sent_exch.serial(), Some(1));
+        assert_eq!(second.qso.rcvd_exch.to_string(), "599 42 ON 3A");
+        assert_eq!(second.field("BAND"), Some("40m"));
+        assert_eq!(second.field("MODE"), None);
+    }
+
+    #[test]
+    fn test_adi_and_adx_round_trip() {
+        let adif = Adif::parse(ADI).unwrap();
+        assert_eq!(Adif::parse(&adif.to_adi()).unwrap(), adif);
+
+        let adx = adif.to_adx();
+        assert!(adx.contains("<COMMENT>Nice &lt;sig&gt;</COMMENT>"));
+        let mut from_adx = Adif::parse(&adx).unwrap();
+        from_adx.preamble = adif.preamble.clone();
+        assert_eq!(from_adx, adif);
+        assert_eq!(adif.records[1].to_fields(), from_adx.records[1].to_fields());
+    }
+
+    #[test]
+    fn test_parse_adx_app_fields() {
+        let adx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ADX>
+  <HEADER><ADIF_VER>3.1.4</ADIF_VER></HEADER>
+  <RECORDS>
+    <RECORD>
+      <QSO_DATE>20230624</QSO_DATE><TIME_ON>1800</TIME_ON>
+      <CALL>K1ABC</CALL><FREQ>144.2</FREQ><MODE>SSB</MODE>
+      <APP PROGRAMID="N1MM" FIELDNAME="RADIO_NR">1</APP>
+    </RECORD>
+  </RECORDS>
+</ADX>"#;
+        let adif = Adif::parse(adx).unwrap();
+        let record = &adif.records[0];
+        assert_eq!(record.qso.freq, Frequency::Khz(144200.0));
+        assert_eq!(record.qso.mode, CabrilloMode::Ph);
+        assert_eq!(
+            record.fields,
+            vec![("APP_N1MM_RADIO_NR".to_string(), "1".to_string())]
+        );
+        assert!(
+            adif.to_adx()
+                .contains(r#"<APP PROGRAMID="N1MM" FIELDNAME="RADIO_NR">1</APP>"#)
+        );
+    }
+
+    #[test]
+    fn test_adx_userdef_fields() {
+        let adx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ADX>
+  <HEADER><USERDEF FIELDID="1" TYPE="S">epc</USERDEF></HEADER>
+  <RECORDS>
+    <RECORD>
+      <QSO_DATE>20231001</QSO_DATE><TIME_ON>1200</TIME_ON><CALL>W1AW</CALL>
+      <BAND>20m</BAND><MODE>FT8</MODE><RST_SENT>-10</RST_SENT><RST_RCVD>+03</RST_RCVD>
+      <USERDEF FIELDNAME="epc">12345</USERDEF>
+    </RECORD>
+  </RECORDS>
+</ADX>"#;
+        let adif = Adif::parse(adx).unwrap();
+        assert_eq!(
+            adif.header,
+            vec![("USERDEF1".to_string(), "epc".to_string())]
+        );
+        assert_eq!(adif.records[0].field("EPC"), Some("12345"));
+        assert!(adif.records[0].qso.sent_exch.fields[0].is_valid());
+        assert!(adif.records[0].qso.rcvd_exch.fields[0].is_valid());
+
+        let written = adif.to_adx();
+        assert!(written.contains(r#"<USERDEF FIELDID="1">epc</USERDEF>"#));
+        assert!(written.contains(r#"<USERDEF FIELDNAME="EPC">12345</USERDEF>"#));
+        assert_eq!(Adif::parse(&written).unwrap(), adif);
+    }
+
+    #[test]
+    fn test_from_cabrillo_log() {
+        let log = CabrilloLog::parse(
+            "START-OF-LOG: 3.0\nCONTEST: ARRL-FD\nQSO: 50 PH 2023-06-24 1800 W1AW 3A CT K1ABC 12F EMA\nQSO: 7030 CW 2023-06-24 1801 W1AW 3A CT K2ABC 1D NNY\nEND-OF-LOG:\n",
+        )
+        .unwrap();
+        let adif = Adif::from(&log);
+        let adi = adif.to_adi();
+        assert!(adi.contains("<BAND:2>6m <MODE:3>SSB <STX_STRING:5>3A CT <SRX_STRING:7>12F EMA"));
+        assert!(adi.contains("<FREQ:4>7.03 <BAND:3>40m"));
+
+        let back = Adif::parse(&adi).unwrap().to_log();
+        assert_eq!(back.headers.callsign(), Some("W1AW"));
+        assert_eq!(back.qsos[0].freq, Frequency::Band(Band::B6M));
+        assert_eq!(back.qsos[1].rcvd_exch.to_string(), "1D NNY");
+    }
+
+    #[test]
+    fn test_shift_decimal() {
+        assert_eq!(shift_decimal("14.025", 3).as_deref(), Some("14025"));
+        assert_eq!(shift_decimal("7.0", 3).as_deref(), Some("7000"));
+        assert_eq!(shift_decimal("14025.5", -3).as_deref(), Some("14.0255"));
+        assert_eq!(shift_decimal("50", -3).as_deref(), Some("0.05"));
+        assert_eq!(shift_decimal("1.2G", -3), None);
+    }
+}
@@ -0,0 +1,552 @@
+//! Builders for creating logs programmatically.
+//!
+//! Logging programs generate a submission from their own database rather than
+//! from text. [`CabrilloLogBuilder`] sets the header with typed values, takes
+//! the operator's details from a [`Station`], and [`QsoBuilder`] lays out the
+//! exchange by the contest template. Both check the result in `build()`.
+//!
+//! ```rust
+//! use cabrillo_log::{CabrilloLogBuilder, CabrilloMode, CategoryOperator, Station};
+//! use chrono::NaiveDate;
+//!
+//! let station = Station::new("AA1ZZZ").grid_locator("FN42");
+//! let builder = CabrilloLogBuilder::from_station(&station)
+//!     .contest("CQ-WPX-CW")
+//!     .category_operator(CategoryOperator::SingleOp);
+//! let time = NaiveDate::from_ymd_opt(2009, 5, 30).unwrap().and_hms_opt(0, 2, 0).unwrap();
+//! let qso = builder
+//!     .qso_builder()
+//!     .khz(7005.0)
+//!     .mode(CabrilloMode::Cw)
+//!     .datetime(time)
+//!     .sent(["599", "1"])
+//!     .rcvd_call("S50A")
+//!     .rcvd(["599", "4"])
+//!     .build()
+//!     .unwrap();
+//! let log = builder.qso(qso).build().unwrap();
+//! assert_eq!(log.qsos[0].sent_call, "AA1ZZZ");
+//! assert_eq!(log.qsos[0].rcvd_exch.serial(), Some(4));
+//! ```
+
+use crate::header::{
+    CategoryAssisted, CategoryBand, CategoryMode, CategoryOperator, CategoryOverlay, CategoryPower,
+    CategoryStation, CategoryTime, CategoryTransmitter,
+};
+use crate::{
+    CabrilloError, CabrilloLog, ContestTemplate, Exchange, ExchangeField, FieldDef, Frequency,
+    Header, Headers, Mode, QSO, TemplateRegistry,
+};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+/// Details of the station and operator that go into every log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Station {
+    pub callsign: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub address: Vec<String>,
+    pub club: Option<String>,
+    pub location: Option<String>,
+    pub grid_locator: Option<String>,
+    pub operators: Vec<String>,
+    pub created_by: Option<String>,
+}
+
+impl Station {
+    /// Settings for a station callsign.
+    pub fn new(callsign: impl Into<String>) -> Self {
+        Station {
+            callsign: callsign.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Set the operator's name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the e-mail address.
+    pub fn email(mut self, email: impl Into<String>) -> Self {
+        self.email = Some(email.into());
+        self
+    }
+
+    /// Add an `ADDRESS:` line.
+    pub fn address(mut self, line: impl Into<String>) -> Self {
+        self.address.push(line.into());
+        self
+    }
+
+    /// Set the club.
+    pub fn club(mut self, club: impl Into<String>) -> Self {
+        self.club = Some(club.into());
+        self
+    }
+
+    /// Set the ARRL/RAC section or other location.
+    pub fn location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Set the Maidenhead grid locator.
+    pub fn grid_locator(mut self, grid: impl Into<String>) -> Self {
+        self.grid_locator = Some(grid.into());
+        self
+    }
+
+    /// Add an operator callsign.
+    pub fn operator(mut self, call: impl Into<String>) -> Self {
+        self.operators.push(call.into());
+        self
+    }
+
+    /// Set the logging program written to `CREATED-BY:`.
+    pub fn created_by(mut self, program: impl Into<String>) -> Self {
+        self.created_by = Some(program.into());
+        self
+    }
+}
+
+/// Builds a [`CabrilloLog`] from typed header values and QSOs.
+#[derive(Debug, Clone, Default)]
+pub struct CabrilloLogBuilder {
+    headers: Headers,
+    qsos: Vec<QSO>,
+    templates: TemplateRegistry,
+}
+
+impl CabrilloLogBuilder {
+    /// An empty log using the built-in contest templates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A log with the header lines filled in from station settings.
+    pub fn from_station(station: &Station) -> Self {
+        let mut builder = Self::new().callsign(&station.callsign);
+        let optional = [
+            (&station.name, Header::Name as fn(String) -> Header),
+            (&station.email, Header::Email),
+            (&station.club, Header::Club),
+            (&station.location, Header::Location),
+            (&station.grid_locator, Header::GridLocator),
+            (&station.created_by, Header::CreatedBy),
+        ];
+        for (value, header) in optional {
+            if let Some(value) = value {
+                builder = builder.header(header(value.clone()));
+            }
+        }
+        if !station.operators.is_empty() {
+            builder = builder.operators(station.operators.join(" "));
+        }
+        for line in &station.address {
+            builder = builder.address(line);
+        }
+        builder
+    }
+
+    /// Use these contest templates to lay out exchanges and validate QSOs.
+    pub fn templates(mut self, templates: TemplateRegistry) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Set a single-valued header, replacing any earlier value of the tag.
+    pub fn header(mut self, header: Header) -> Self {
+        self.headers.set(header);
+        self
+    }
+
+    /// Set `CALLSIGN:`.
+    pub fn callsign(self, call: impl Into<String>) -> Self {
+        self.header(Header::Callsign(call.into()))
+    }
+
+    /// Set `CONTEST:`.
+    pub fn contest(self, contest: impl Into<String>) -> Self {
+        self.header(Header::Contest(contest.into()))
+    }
+
+    /// Set `CATEGORY-OPERATOR:`.
+    pub fn category_operator(self, value: CategoryOperator) -> Self {
+        self.header(Header::CategoryOperator(value))
+    }
+
+    /// Set `CATEGORY-ASSISTED:`.
+    pub fn category_assisted(self, value: CategoryAssisted) -> Self {
+        self.header(Header::CategoryAssisted(value))
+    }
+
+    /// Set `CATEGORY-BAND:`.
+    pub fn category_band(self, value: CategoryBand) -> Self {
+        self.header(Header::CategoryBand(value))
+    }
+
+    /// Set `CATEGORY-MODE:`.
+    pub fn category_mode(self, value: CategoryMode) -> Self {
+        self.header(Header::CategoryMode(value))
+    }
+
+    /// Set `CATEGORY-POWER:`.
+    pub fn category_power(self, value: CategoryPower) -> Self {
+        self.header(Header::CategoryPower(value))
+    }
+
+    /// Set `CATEGORY-STATION:`.
+    pub fn category_station(self, value: CategoryStation) -> Self {
+        self.header(Header::CategoryStation(value))
+    }
+
+    /// Set `CATEGORY-TRANSMITTER:`.
+    pub fn category_transmitter(self, value: CategoryTransmitter) -> Self {
+        self.header(Header::CategoryTransmitter(value))
+    }
+
+    /// Set `CATEGORY-TIME:`.
+    pub fn category_time(self, value: CategoryTime) -> Self {
+        self.header(Header::CategoryTime(value))
+    }
+
+    /// Set `CATEGORY-OVERLAY:`.
+    pub fn category_overlay(self, value: CategoryOverlay) -> Self {
+        self.header(Header::CategoryOverlay(value))
+    }
+
+    /// Set `CLAIMED-SCORE:`.
+    pub fn claimed_score(self, score: u64) -> Self {
+        self.header(Header::ClaimedScore(score))
+    }
+
+    /// Set `OPERATORS:`.
+    pub fn operators(self, operators: impl Into<String>) -> Self {
+        self.header(Header::Operators(operators.into()))
+    }
+
+    /// Add an `ADDRESS:` line.
+    pub fn address(mut self, line: impl Into<String>) -> Self {
+        self.headers.push(Header::Address(line.into()));
+        self
+    }
+
+    /// Add a `SOAPBOX:` line.
+    pub fn soapbox(mut self, line: impl Into<String>) -> Self {
+        self.headers.push(Header::Soapbox(line.into()));
+        self
+    }
+
+    /// Add a QSO.
+    pub fn qso(mut self, qso: QSO) -> Self {
+        self.qsos.push(qso);
+        self
+    }
+
+    /// Add several QSOs.
+    pub fn qsos(mut self, qsos: impl IntoIterator<Item = QSO>) -> Self {
+        self.qsos.extend(qsos);
+        self
+    }
+
+    /// A QSO builder with the sent call from `CALLSIGN:` and the exchange
+    /// layout of the contest set so far.
+    pub fn qso_builder(&self) -> QsoBuilder {
+        let mut builder = QsoBuilder::new();
+        if let Some(call) = self.headers.callsign() {
+            builder = builder.sent_call(call);
+        }
+        if let Some(template) = self
+            .headers
+            .contest()
+            .and_then(|c| self.templates.lookup(c))
+        {
+            builder = builder.template(template);
+        }
+        builder
+    }
+
+    /// Build the log.
+    ///
+    /// Fails if `CALLSIGN:` or `CONTEST:` is missing, or if any QSO does not
+    /// pass [`CabrilloLog::validate_with_templates`] with the builder's templates.
+    pub fn build(self) -> Result<CabrilloLog, CabrilloError> {
+        for tag in ["CALLSIGN", "CONTEST"] {
+            if !self.headers.contains(tag) {
+                return Err(CabrilloError::MissingRequiredField(tag.to_string()));
+            }
+        }
+        let log = CabrilloLog {
+            headers: self.headers,
+            qsos: self.qsos,
+            comments: Vec::new(),
+            end_of_log_version: false,
+        };
+        log.validate_with_templates(&self.templates)?;
+        Ok(log)
+    }
+}
+
+/// Builds a single [`QSO`].
+///
+/// Frequency, mode, date, time and both calls are required. With a contest
+/// template the exchange values are named and typed by the template's fields.
+#[derive(Debug, Clone, Default)]
+pub struct QsoBuilder {
+    freq: Option<Frequency>,
+    mode: Option<Mode>,
+    date: Option<NaiveDate>,
+    time: Option<NaiveTime>,
+    sent_call: Option<String>,
+    sent: Vec<String>,
+    rcvd_call: Option<String>,
+    rcvd: Vec<String>,
+    tx: Option<String>,
+    excluded: bool,
+    template: Option<&'static ContestTemplate>,
+}
+
+impl QsoBuilder {
+    /// An empty QSO without a contest template.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lay out the exchanges by a contest template.
+    pub fn template(mut self, template: &'static ContestTemplate) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// Set the frequency or band.
+    pub fn freq(mut self, freq: impl Into<Frequency>) -> Self {
+        self.freq = Some(freq.into());
+        self
+    }
+
+    /// Set the frequency in kHz.
+    pub fn khz(self, khz: f64) -> Self {
+        self.freq(Frequency::Khz(khz))
+    }
+
+    /// Set the mode, either a [`CabrilloMode`](crate::CabrilloMode) or a precise [`Mode`].
+    pub fn mode(mut self, mode: impl Into<Mode>) -> Self {
+        self.mode = Some(mode.into());
+        self
+    }
+
+    /// Set the date.
+    pub fn date(mut self, date: NaiveDate) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    /// Set the time; seconds are dropped when the QSO is written.
+    pub fn time(mut self, time: NaiveTime) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Set date and time.
+    pub fn datetime(self, datetime: NaiveDateTime) -> Self {
+        self.date(datetime.date()).time(datetime.time())
+    }
+
+    /// Set the sent call.
+    pub fn sent_call(mut self, call: impl Into<String>) -> Self {
+        self.sent_call = Some(call.into());
+        self
+    }
+
+    /// Set the sent exchange values, in template order.
+    pub fn sent<S: Into<String>>(mut self, values: impl IntoIterator<Item = S>) -> Self {
+        self.sent = values.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the received call.
+    pub fn rcvd_call(mut self, call: impl Into<String>) -> Self {
+        self.rcvd_call = Some(call.into());
+        self
+    }
+
+    /// Set the received exchange values, in template order.
+    pub fn rcvd<S: Into<String>>(mut self, values: impl IntoIterator<Item = S>) -> Self {
+        self.rcvd = values.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the transmitter ID, 0 or 1.
+    pub fn tx(mut self, tx: u8) -> Self {
+        self.tx = Some(tx.to_string());
+        self
+    }
+
+    /// Mark the QSO as an X-QSO.
+    pub fn excluded(mut self, excluded: bool) -> Self {
+        self.excluded = excluded;
+        self
+    }
+
+    /// Build the QSO, checking required columns, the number of exchange fields
+    /// and the values themselves.
+    pub fn build(self) -> Result<QSO, CabrilloError> {
+        let missing = |column: &str| CabrilloError::MissingRequiredField(column.to_string());
+        let exchange = |defs: Option<&[FieldDef]>, values: &[String], side: &str| match defs {
+            Some(defs) if defs.len() != values.len() => Err(CabrilloError::InvalidFormat(format!(
+                "Expected {} {} exchange fields, found {}",
+                defs.len(),
+                side,
+                values.len()
+            ))),
+            Some(defs) => Ok(Exchange::new(
+                defs.iter()
+                    .zip(values)
+                    .map(|(def, value)| ExchangeField::new(def.name, def.kind, value.as_str()))
+                    .collect(),
+            )),
+            None => Ok(Exchange::from_untyped(values.iter().map(String::as_str))),
+        };
+
+        let qso = QSO {
+            freq: self.freq.ok_or_else(|| missing("freq"))?,
+            mode: self.mode.ok_or_else(|| missing("mode"))?,
+            date: self.date.ok_or_else(|| missing("date"))?,
+            time: self.time.ok_or_else(|| missing("time"))?,
+            sent_call: self.sent_call.ok_or_else(|| missing("sent_call"))?,
+            sent_exch: exchange(self.template.map(|t| t.sent), &self.sent, "sent")?,
+            rcvd_call: self.rcvd_call.ok_or_else(|| missing("rcvd_call"))?,
+            rcvd_exch: exchange(self.template.map(|t| t.rcvd), &self.rcvd, "received")?,
+            tx: self.tx,
+            excluded: self.excluded,
+            zero_point: false,
+        };
+        CabrilloLog::validate_qso(&qso)?;
+        Ok(qso)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Band, CabrilloMode, DupeScope, FieldKind};
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 6, 24)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_station_defaults() {
+        let station = Station::new("W1AW")
+            .name("Hiram Percy Maxim")
+            .address("225 Main St")
+            .address("Newington CT")
+            .operator("K1ABC")
+            .operator("W1XYZ");
+        let log = CabrilloLogBuilder::from_station(&station)
+            .contest("ARRL-FD")
+            .category_operator(CategoryOperator::MultiOp)
+            .build()
+            .unwrap();
+        assert_eq!(log.headers.callsign(), Some("W1AW"));
+        assert_eq!(log.headers.address(), vec!["225 Main St", "Newington CT"]);
+        assert_eq!(log.headers.operators(), vec!["K1ABC", "W1XYZ"]);
+        assert!(
+            log.to_string()
+                .contains("CATEGORY-OPERATOR: MULTI-OP\nEND-OF-LOG:")
+        );
+    }
+
+    #[test]
+    fn test_qso_by_template() {
+        let builder = CabrilloLogBuilder::new()
+            .callsign("W1AW")
+            .contest("ARRL-FD");
+        let qso = builder
+            .qso_builder()
+            .freq(Band::B20M)
+            .mode(Mode::precise("FT8"))
+            .datetime(at(18, 0))
+            .sent(["3A", "CT"])
+            .rcvd_call("K1ABC")
+            .rcvd(["1D", "EMA"])
+            .build()
+            .unwrap();
+        assert_eq!(qso.sent_call, "W1AW");
+        assert_eq!(qso.mode, CabrilloMode::Dg);
+        assert_eq!(qso.rcvd_exch.get("section"), Some("EMA"));
+
+        let log = builder.qso(qso).build().unwrap();
+        let reparsed = CabrilloLog::parse(&log.to_string()).unwrap();
+        assert_eq!(reparsed.qsos[0].rcvd_exch, log.qsos[0].rcvd_exch);
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert_eq!(
+            CabrilloLogBuilder::new().callsign("W1AW").build(),
+            Err(CabrilloError::MissingRequiredField("CONTEST".to_string()))
+        );
+
+        let qso = QsoBuilder::new()
+            .khz(14000.0)
+            .mode(CabrilloMode::Cw)
+            .datetime(at(18, 0))
+            .sent_call("W1AW");
+        assert_eq!(
+            qso.clone().build(),
+            Err(CabrilloError::MissingRequiredField("rcvd_call".to_string()))
+        );
+        assert_eq!(
+            qso.clone().rcvd_call("invalid").build(),
+            Err(CabrilloError::InvalidCallsign("invalid".to_string()))
+        );
+        let fd = TemplateRegistry::new().lookup("ARRL-FD").unwrap();
+        assert!(matches!(
+            qso.template(fd).rcvd_call("K1ABC").sent(["3A"]).build(),
+            Err(CabrilloError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_build_with_sponsor_template() {
+        // A sponsor variant of WW-DIGI that allows CW and counts dupes per contest
+        static WW_DIGI_CW: ContestTemplate = ContestTemplate {
+            contests: &["WW-DIGI"],
+            sent: &[FieldDef::new("grid", 6, FieldKind::Grid)],
+            rcvd: &[FieldDef::new("grid", 6, FieldKind::Grid)],
+            modes: &[],
+            dupes: DupeScope::PerContest,
+        };
+        let mut templates = TemplateRegistry::new();
+        templates.register(&WW_DIGI_CW);
+
+        let builder = CabrilloLogBuilder::new()
+            .templates(templates.clone())
+            .callsign("W1AW")
+            .contest("WW-DIGI");
+        let qso = builder
+            .qso_builder()
+            .freq(Band::B20M)
+            .mode(CabrilloMode::Cw)
+            .datetime(at(18, 0))
+            .sent(["FN31"])
+            .rcvd_call("K1ABC")
+            .rcvd(["FN42"])
+            .build()
+            .unwrap();
+        let log = builder.qso(qso).build().unwrap();
+        assert!(log.validate().is_err());
+        assert_eq!(log.dupe_scope(), DupeScope::PerBand);
+        assert_eq!(
+            log.dupe_scope_with_templates(&templates),
+            DupeScope::PerContest
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Structured amateur radio callsigns.
+//!
+//! A logged callsign may carry a prefix override (`DL/W1AW`, `W1AW/KH6`) and
+//! operating suffixes (`/P`, `/MM`, `/QRP`, `/R`, a new call area such as `/3`)
+//! around the base call. [`Callsign`] splits these apart so that entity
+//! lookup, dupe checking and WPX prefixes can use the right part.
+
+use crate::CabrilloError;
+use std::fmt;
+use std::str::FromStr;
+
+/// Operating suffix following the base call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Suffix {
+    /// `/P`
+    Portable,
+    /// `/M`
+    Mobile,
+    /// `/MM`
+    MaritimeMobile,
+    /// `/AM`
+    AeronauticalMobile,
+    /// `/QRP`
+    Qrp,
+    /// `/A`, alternate location
+    Alternate,
+    /// `/R`, VHF rover
+    Rover,
+    /// `/3`, operating from another call area
+    CallArea(u8),
+}
+
+impl Suffix {
+    fn parse(part: &str) -> Option<Suffix> {
+        let suffix = match part {
+            "P" => Suffix::Portable,
+            "M" => Suffix::Mobile,
+            "MM" => Suffix::MaritimeMobile,
+            "AM" => Suffix::AeronauticalMobile,
+            "QRP" => Suffix::Qrp,
+            "A" => Suffix::Alternate,
+            "R" => Suffix::Rover,
+            _ if part.len() == 1 && part.as_bytes()[0].is_ascii_digit() => {
+                Suffix::CallArea(part.as_bytes()[0] - b'0')
+            }
+            _ => return None,
+        };
+        Some(suffix)
+    }
+}
+
+impl fmt::Display for Suffix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Suffix::Portable => f.write_str("P"),
+            Suffix::Mobile => f.write_str("M"),
+            Suffix::MaritimeMobile => f.write_str("MM"),
+            Suffix::AeronauticalMobile => f.write_str("AM"),
+            Suffix::Qrp => f.write_str("QRP"),
+            Suffix::Alternate => f.write_str("A"),
+            Suffix::Rover => f.write_str("R"),
+            Suffix::CallArea(digit) => write!(f, "{}", digit),
+        }
+    }
+}
+
+/// A callsign split into prefix override, base call and suffixes.
+///
+/// Parsing is case-insensitive; the callsign is kept in upper case with its
+/// parts in the order they were written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Callsign {
+    call: String,
+    base: String,
+    prefix: Option<String>,
+    suffixes: Vec<Suffix>,
+}
+
+impl Callsign {
+    /// Parse a callsign; same as `str::parse`.
+    pub fn parse(call: &str) -> Result<Self, CabrilloError> {
+        call.parse()
+    }
+
+    /// The whole callsign as logged, in upper case.
+    pub fn as_str(&self) -> &str {
+        &self.call
+    }
+
+    /// The home call without prefix override or suffixes, e.g. `W1AW` for `DL/W1AW/P`.
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// The prefix override, e.g. `DL` for `DL/W1AW` or `KH6` for `W1AW/KH6`.
+    pub fn prefix_override(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    pub fn suffixes(&self) -> &[Suffix] {
+        &self.suffixes
+    }
+
+    pub fn has_suffix(&self, suffix: Suffix) -> bool {
+        self.suffixes.contains(&suffix)
+    }
+
+    /// Whether the station is maritime or aeronautical mobile, and so in no DXCC entity.
+    pub fn is_mobile_outside_entity(&self) -> bool {
+        self.has_suffix(Suffix::MaritimeMobile) || self.has_suffix(Suffix::AeronauticalMobile)
+    }
+
+    /// The part of the call that determines the DXCC entity: the prefix
+    /// override if there is one, otherwise the base call. `None` for `/MM` and `/AM`.
+    pub fn entity_call(&self) -> Option<&str> {
+        if self.is_mobile_outside_entity() {
+            return None;
+        }
+        Some(self.prefix.as_deref().unwrap_or(&self.base))
+    }
+
+    /// Prefix as counted in the CQ WPX contest.
+    ///
+    /// This is the base call up to and including its last digit (`WB8` for
+    /// `WB8IMY`), a prefix override with `0` appended if it has no digit
+    /// (`PA0` for `PA/N8BJQ`), or the base prefix with its call area replaced
+    /// (`KA3` for `KA5WSS/3`).
+    pub fn wpx_prefix(&self) -> String {
+        if let Some(prefix) = &self.prefix {
+            return if prefix.bytes().any(|b| b.is_ascii_digit()) {
+                prefix.clone()
+            } else {
+                format!("{}0", prefix)
+            };
+        }
+        let last_digit = self
+            .base
+            .rfind(|c: char| c.is_ascii_digit())
+            .expect("base call has a digit");
+        let prefix = &self.base[..=last_digit];
+        let area = self.suffixes.iter().find_map(|s| match s {
+            Suffix::CallArea(digit) => Some(*digit),
+            _ => None,
+        });
+        match area {
+            Some(digit) => format!(
+                "{}{}",
+                prefix.trim_end_matches(|c: char| c.is_ascii_digit()),
+                digit
+            ),
+            None => prefix.to_string(),
+        }
+    }
+}
+
+/// Whether `part` is shaped like a home call: letters and digits, a letter in
+/// the first two characters, a digit after the first character and a letter at the end.
+fn is_base_call(part: &str) -> bool {
+    let bytes = part.as_bytes();
+    (3..=12).contains(&bytes.len())
+        && bytes.iter().all(u8::is_ascii_alphanumeric)
+        && bytes[..2].iter().any(u8::is_ascii_alphabetic)
+        && bytes[1..].iter().any(u8::is_ascii_digit)
+        && bytes[bytes.len() - 1].is_ascii_alphabetic()
+}
+
+/// Whether `part` can be a prefix override such as `DL`, `VE3` or `KH6`.
+fn is_prefix(part: &str) -> bool {
+    (1..=4).contains(&part.len())
+        && part.bytes().all(|b| b.is_ascii_alphanumeric())
+        && part.bytes().any(|b| b.is_ascii_alphabetic())
+}
+
+impl FromStr for Callsign {
+    type Err = CabrilloError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let call = s.trim().to_ascii_uppercase();
+        let invalid = || CabrilloError::InvalidCallsign(s.trim().to_string());
+        let parts: Vec<&str> = call.split('/').collect();
+        if parts.len() > 4 || parts.iter().any(|p| p.is_empty()) {
+            return Err(invalid());
+        }
+
+        // The base is the longest part shaped like a call; on a tie the later
+        // one, as in `VP2E/W1AW`.
+        let base_index = parts
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| is_base_call(p) && Suffix::parse(p).is_none())
+            .max_by_key(|(i, p)| (p.len(), *i))
+            .map(|(i, _)| i)
+            .ok_or_else(invalid)?;
+
+        let mut prefix = None;
+        let mut suffixes = Vec::new();
+        for (i, part) in parts.iter().enumerate() {
+            if i == base_index {
+                continue;
+            }
+            match Suffix::parse(part) {
+                Some(suffix) if i > base_index && !suffixes.contains(&suffix) => {
+                    suffixes.push(suffix)
+                }
+                None if prefix.is_none() && is_prefix(part) => prefix = Some(part.to_string()),
+                _ => return Err(invalid()),
+            }
+        }
+        if suffixes
+            .iter()
+            .filter(|s| matches!(s, Suffix::CallArea(_)))
+            .count()
+            > 1
+        {
+            return Err(invalid());
+        }
+
+        Ok(Callsign {
+            base: parts[base_index].to_string(),
+            call,
+            prefix,
+            suffixes,
+        })
+    }
+}
+
+impl fmt::Display for Callsign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.call)
+    }
+}
+
+impl AsRef<str> for Callsign {
+    fn as_ref(&self) -> &str {
+        &self.call
+    }
+}
+
+serde_as_str!(Callsign);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_parts() {
+        let call = Callsign::parse("dl/w1aw/p").unwrap();
+        assert_eq!(call.as_str(), "DL/W1AW/P");
+        assert_eq!(call.base(), "W1AW");
+        assert_eq!(call.prefix_override(), Some("DL"));
+        assert_eq!(call.suffixes(), &[Suffix::Portable]);
+        assert_eq!(call.entity_call(), Some("DL"));
+
+        let call = Callsign::parse("W1AW/KH6").unwrap();
+        assert_eq!(call.base(), "W1AW");
+        assert_eq!(call.prefix_override(), Some("KH6"));
+
+        let call = Callsign::parse("VE3/KA5WSS").unwrap();
+        assert_eq!(call.base(), "KA5WSS");
+        assert_eq!(call.prefix_override(), Some("VE3"));
+
+        let call = Callsign::parse("G4ABC/MM").unwrap();
+        assert!(call.is_mobile_outside_entity());
+        assert_eq!(call.entity_call(), None);
+
+        let call = Callsign::parse("KA5WSS/3/QRP").unwrap();
+        assert_eq!(call.suffixes(), &[Suffix::CallArea(3), Suffix::Qrp]);
+    }
+
+    #[test]
+    fn test_reject_impossible_calls() {
+        for call in [
+            "",
+            "invalid",
+            "599",
+            "3A",
+            "12F",
+            "FN31",
+            "W1AW/",
+            "/W1AW",
+            "W1AW//P",
+            "P/W1AW",
+            "W1AW/P/P",
+            "W1AW/3/4",
+            "DL/EA/W1AW",
+            "W1AW/TOOLONG",
+            "W1-AW",
+        ] {
+            assert!(
+                Callsign::parse(call).is_err(),
+                "{} should be rejected",
+                call
+            );
+        }
+        for call in ["N1MM", "2E0ABC", "4U1UN", "3DA0RU", "HG1996NA", "SP5TLS"] {
+            assert!(Callsign::parse(call).is_ok(), "{} should be accepted", call);
+        }
+    }
+
+    #[test]
+    fn test_wpx_prefix() {
+        let wpx = |call: &str| Callsign::parse(call).unwrap().wpx_prefix();
+        assert_eq!(wpx("N8BJQ"), "N8");
+        assert_eq!(wpx("WB8IMY"), "WB8");
+        assert_eq!(wpx("2E0ABC"), "2E0");
+        assert_eq!(wpx("HG1996NA"), "HG1996");
+        assert_eq!(wpx("PA/N8BJQ"), "PA0");
+        assert_eq!(wpx("N8BJQ/KH6"), "KH6");
+        assert_eq!(wpx("KA5WSS/3"), "KA3");
+        assert_eq!(wpx("W1AW/P"), "W1");
+    }
+}
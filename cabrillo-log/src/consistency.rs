@@ -0,0 +1,264 @@
+//! Cross-checks between the header and the QSO lines.
+//!
+//! Each line of a log can be valid on its own while the log as a whole claims
+//! something its QSOs contradict: a single-band entry with QSOs on other bands,
+//! a CW entry with phone QSOs, or QSOs sent under a different call than the one
+//! in `CALLSIGN:`. [`CabrilloLog::check_consistency`] finds these.
+
+use crate::diagnostic::Severity;
+use crate::header::{CategoryBand, CategoryMode, CategoryOperator, CategoryTransmitter};
+use crate::{Band, CabrilloLog, CabrilloMode};
+use std::fmt;
+
+/// Kind of header/QSO inconsistency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConsistencyIssueKind {
+    /// The sent call of the QSO differs from `CALLSIGN:`.
+    SentCallMismatch { sent_call: String, callsign: String },
+    /// The QSO is on a band outside `CATEGORY-BAND:`.
+    BandOutsideCategory {
+        band: Option<Band>,
+        category: CategoryBand,
+    },
+    /// The QSO is in a mode outside `CATEGORY-MODE:`.
+    ModeOutsideCategory {
+        mode: CabrilloMode,
+        category: CategoryMode,
+    },
+    /// The QSO has TX ID 1 in a `CATEGORY-TRANSMITTER: ONE` log.
+    SecondTransmitter,
+    /// A multi-op log has no `OPERATORS:` header.
+    MissingOperators,
+    /// `CLAIMED-SCORE:` differs from the score computed from the QSOs.
+    ClaimedScoreMismatch { claimed: u64, computed: u64 },
+}
+
+/// A header/QSO inconsistency, with the index of the QSO in [`CabrilloLog::qsos`]
+/// when it concerns a single QSO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConsistencyIssue {
+    pub qso: Option<usize>,
+    pub kind: ConsistencyIssueKind,
+}
+
+impl ConsistencyIssue {
+    /// A missing `OPERATORS:` header and a wrong claimed score are fixed in the
+    /// header and are only warnings; QSOs that contradict the header are errors.
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            ConsistencyIssueKind::MissingOperators
+            | ConsistencyIssueKind::ClaimedScoreMismatch { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for ConsistencyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(qso) = self.qso {
+            write!(f, "QSO #{}: ", qso + 1)?;
+        }
+        match &self.kind {
+            ConsistencyIssueKind::SentCallMismatch {
+                sent_call,
+                callsign,
+            } => write!(
+                f,
+                "sent call {} differs from CALLSIGN {}",
+                sent_call, callsign
+            ),
+            ConsistencyIssueKind::BandOutsideCategory { band, category } => write!(
+                f,
+                "band {} outside CATEGORY-BAND {}",
+                band.map_or("unknown", |b| b.name()),
+                category
+            ),
+            ConsistencyIssueKind::ModeOutsideCategory { mode, category } => {
+                write!(f, "mode {} outside CATEGORY-MODE {}", mode, category)
+            }
+            ConsistencyIssueKind::SecondTransmitter => {
+                write!(f, "TX ID 1 in a CATEGORY-TRANSMITTER ONE log")
+            }
+            ConsistencyIssueKind::MissingOperators => {
+                write!(f, "multi-op log without OPERATORS")
+            }
+            ConsistencyIssueKind::ClaimedScoreMismatch { claimed, computed } => write!(
+                f,
+                "CLAIMED-SCORE {} differs from computed score {}",
+                claimed, computed
+            ),
+        }
+    }
+}
+
+impl CabrilloLog {
+    /// Cross-check the headers with the QSOs, returning log-wide issues first
+    /// and then QSO issues ordered by QSO index.
+    ///
+    /// `computed_score` is the score recomputed from the QSOs, if the contest's
+    /// scoring rules are known; `CLAIMED-SCORE:` is only checked against it.
+    /// X-QSOs are not checked against the band and mode categories.
+    pub fn check_consistency(&self, computed_score: Option<u64>) -> Vec<ConsistencyIssue> {
+        let mut issues = Vec::new();
+        let mut issue = |qso, kind| issues.push(ConsistencyIssue { qso, kind });
+
+        if self.headers.category_operator() == Some(CategoryOperator::MultiOp)
+            && self.headers.operators().is_empty()
+        {
+            issue(None, ConsistencyIssueKind::MissingOperators);
+        }
+        if let (Some(claimed), Some(computed)) = (self.headers.claimed_score(), computed_score)
+            && claimed != computed
+        {
+            issue(
+                None,
+                ConsistencyIssueKind::ClaimedScoreMismatch { claimed, computed },
+            );
+        }
+
+        let callsign = self.headers.callsign().map(str::trim);
+        let category_band = self.headers.category_band();
+        let category_mode = self.headers.category_mode();
+        let single_transmitter =
+            self.headers.category_transmitter() == Some(CategoryTransmitter::One);
+        for (index, qso) in self.qsos.iter().enumerate() {
+            if let Some(callsign) = callsign
+                && !qso.sent_call.eq_ignore_ascii_case(callsign)
+            {
+                issue(
+                    Some(index),
+                    ConsistencyIssueKind::SentCallMismatch {
+                        sent_call: qso.sent_call.clone(),
+                        callsign: callsign.to_string(),
+                    },
+                );
+            }
+            if single_transmitter && qso.tx.as_deref() == Some("1") {
+                issue(Some(index), ConsistencyIssueKind::SecondTransmitter);
+            }
+            if qso.excluded {
+                continue;
+            }
+            if let Some(category) = category_band
+                && let Some(band) = category.band()
+                && qso.freq.band() != Some(band)
+            {
+                issue(
+                    Some(index),
+                    ConsistencyIssueKind::BandOutsideCategory {
+                        band: qso.freq.band(),
+                        category,
+                    },
+                );
+            }
+            if let Some(category) = category_mode
+                && !category.allows(qso.mode.category)
+            {
+                issue(
+                    Some(index),
+                    ConsistencyIssueKind::ModeOutsideCategory {
+                        mode: qso.mode.category,
+                        category,
+                    },
+                );
+            }
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONSISTENT: &str = "START-OF-LOG: 3.0
+CONTEST: CQ-WPX-CW
+CALLSIGN: AA1ZZZ
+CATEGORY-BAND: 40M
+CATEGORY-MODE: CW
+CATEGORY-TRANSMITTER: ONE
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4 0
+X-QSO: 14005 CW 2009-05-30 0003 AA1ZZZ 599 2 EF8M 599 9 0
+END-OF-LOG:
+";
+
+    const INCONSISTENT: &str = "START-OF-LOG: 3.0
+CONTEST: CQ-WPX-CW
+CALLSIGN: AA1ZZZ
+CATEGORY-BAND: 40M
+CATEGORY-MODE: CW
+CATEGORY-TRANSMITTER: ONE
+QSO: 7005 CW 2009-05-30 0002 AA1ZZ 599 1 S50A 599 4
+QSO: 14005 PH 2009-05-30 0003 AA1ZZZ 59 2 EF8M 59 9 1
+END-OF-LOG:
+";
+
+    const MULTI_OP: &str = "START-OF-LOG: 3.0
+CONTEST: CQ-WPX-CW
+CATEGORY-OPERATOR: MULTI-OP
+CLAIMED-SCORE: 120
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4
+END-OF-LOG:
+";
+
+    #[test]
+    fn test_consistent_log() {
+        let log = CabrilloLog::parse(CONSISTENT).unwrap();
+        assert!(log.check_consistency(None).is_empty());
+    }
+
+    #[test]
+    fn test_qso_issues() {
+        let log = CabrilloLog::parse(INCONSISTENT).unwrap();
+        let issues = log.check_consistency(None);
+        let kinds: Vec<_> = issues.iter().map(|i| (i.qso, &i.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    Some(0),
+                    &ConsistencyIssueKind::SentCallMismatch {
+                        sent_call: "AA1ZZ".to_string(),
+                        callsign: "AA1ZZZ".to_string()
+                    }
+                ),
+                (Some(1), &ConsistencyIssueKind::SecondTransmitter),
+                (
+                    Some(1),
+                    &ConsistencyIssueKind::BandOutsideCategory {
+                        band: Some(Band::B20M),
+                        category: CategoryBand::B40M
+                    }
+                ),
+                (
+                    Some(1),
+                    &ConsistencyIssueKind::ModeOutsideCategory {
+                        mode: CabrilloMode::Ph,
+                        category: CategoryMode::Cw
+                    }
+                ),
+            ]
+        );
+        assert!(issues.iter().all(|i| i.severity() == Severity::Error));
+        assert_eq!(
+            issues[2].to_string(),
+            "QSO #2: band 20m outside CATEGORY-BAND 40M"
+        );
+    }
+
+    #[test]
+    fn test_operators_and_claimed_score() {
+        let log = CabrilloLog::parse(MULTI_OP).unwrap();
+        assert_eq!(log.check_consistency(Some(120)).len(), 1);
+        let issues = log.check_consistency(Some(100));
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].kind, ConsistencyIssueKind::MissingOperators);
+        assert_eq!(
+            issues[1].to_string(),
+            "CLAIMED-SCORE 120 differs from computed score 100"
+        );
+        assert!(issues.iter().all(|i| i.severity() == Severity::Warning));
+    }
+}
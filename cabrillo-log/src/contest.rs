@@ -0,0 +1,301 @@
+//! Contest templates describing the QSO exchange of each contest.
+//!
+//! A QSO line only makes sense positionally once the exchange layout is known:
+//! a Field Day class such as `3A` or a grid such as `FN32` looks just like a
+//! callsign to a heuristic scanner. Templates are looked up by the value of the
+//! `CONTEST:` header in a [`TemplateRegistry`].
+
+use crate::dupe::DupeScope;
+use crate::exchange::FieldKind;
+use crate::mode::CabrilloMode;
+
+/// One column of a sent or received exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldDef {
+    pub name: &'static str,
+    pub width: usize,
+    pub kind: FieldKind,
+}
+
+impl FieldDef {
+    pub const fn new(name: &'static str, width: usize, kind: FieldKind) -> Self {
+        FieldDef { name, width, kind }
+    }
+}
+
+/// Exchange layout of a contest, as published in the sponsor's QSO template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContestTemplate {
+    /// `CONTEST:` values this template applies to.
+    pub contests: &'static [&'static str],
+    /// Fields sent, following the sent callsign.
+    pub sent: &'static [FieldDef],
+    /// Fields received, following the received callsign.
+    pub rcvd: &'static [FieldDef],
+    /// Modes allowed in the contest; empty if any mode is allowed.
+    pub modes: &'static [CabrilloMode],
+    /// Which repeat contacts with a station are dupes.
+    pub dupes: DupeScope,
+}
+
+impl ContestTemplate {
+    /// Whether this template applies to the given `CONTEST:` value.
+    pub fn matches(&self, contest: &str) -> bool {
+        self.contests
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(contest.trim()))
+    }
+
+    /// Whether QSOs in the given mode count in this contest.
+    pub fn allows_mode(&self, mode: CabrilloMode) -> bool {
+        self.modes.is_empty() || self.modes.contains(&mode)
+    }
+
+    /// Number of QSO line tokens after the time column, excluding the TX ID.
+    pub fn token_count(&self) -> usize {
+        self.sent.len() + self.rcvd.len() + 2
+    }
+}
+
+const RST: FieldDef = FieldDef::new("rst", 3, FieldKind::Rst);
+
+const RST_SERIAL: &[FieldDef] = &[RST, FieldDef::new("serial", 6, FieldKind::Serial)];
+const RST_ZONE: &[FieldDef] = &[RST, FieldDef::new("zone", 6, FieldKind::Zone)];
+const RST_EXCH: &[FieldDef] = &[RST, FieldDef::new("exch", 6, FieldKind::Text)];
+const RST_ZONE_QTH: &[FieldDef] = &[
+    RST,
+    FieldDef::new("zone", 2, FieldKind::Zone),
+    FieldDef::new("qth", 4, FieldKind::Text),
+];
+const GRID: &[FieldDef] = &[FieldDef::new("grid", 6, FieldKind::Grid)];
+const FIELD_DAY: &[FieldDef] = &[
+    FieldDef::new("class", 3, FieldKind::Class),
+    FieldDef::new("section", 5, FieldKind::Section),
+];
+const SWEEPSTAKES: &[FieldDef] = &[
+    FieldDef::new("serial", 4, FieldKind::Serial),
+    FieldDef::new("precedence", 1, FieldKind::Precedence),
+    FieldDef::new("check", 2, FieldKind::Check),
+    FieldDef::new("section", 3, FieldKind::Section),
+];
+const NAQP: &[FieldDef] = &[
+    FieldDef::new("name", 10, FieldKind::Name),
+    FieldDef::new("qth", 3, FieldKind::Text),
+];
+
+/// Templates shipped with the library.
+pub static BUILTIN_TEMPLATES: &[ContestTemplate] = &[
+    ContestTemplate {
+        contests: &["CQ-WPX-CW"],
+        sent: RST_SERIAL,
+        rcvd: RST_SERIAL,
+        modes: &[CabrilloMode::Cw],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["CQ-WPX-SSB"],
+        sent: RST_SERIAL,
+        rcvd: RST_SERIAL,
+        modes: &[CabrilloMode::Ph],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["CQ-WPX-RTTY"],
+        sent: RST_SERIAL,
+        rcvd: RST_SERIAL,
+        modes: &[CabrilloMode::Ry],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["CQ-WW-CW"],
+        sent: RST_ZONE,
+        rcvd: RST_ZONE,
+        modes: &[CabrilloMode::Cw],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["CQ-WW-SSB"],
+        sent: RST_ZONE,
+        rcvd: RST_ZONE,
+        modes: &[CabrilloMode::Ph],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["CQ-WW-RTTY"],
+        sent: RST_ZONE_QTH,
+        rcvd: RST_ZONE_QTH,
+        modes: &[CabrilloMode::Ry],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["ARRL-DX-CW", "ARRL-160", "CQ-160-CW"],
+        sent: RST_EXCH,
+        rcvd: RST_EXCH,
+        modes: &[CabrilloMode::Cw],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["ARRL-DX-SSB", "CQ-160-SSB"],
+        sent: RST_EXCH,
+        rcvd: RST_EXCH,
+        modes: &[CabrilloMode::Ph],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["ARRL-10", "IARU-HF"],
+        sent: RST_EXCH,
+        rcvd: RST_EXCH,
+        modes: &[CabrilloMode::Cw, CabrilloMode::Ph],
+        dupes: DupeScope::PerBandMode,
+    },
+    ContestTemplate {
+        contests: &["ARRL-RTTY"],
+        sent: RST_EXCH,
+        rcvd: RST_EXCH,
+        modes: &[CabrilloMode::Ry, CabrilloMode::Dg],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["ARRL-FD", "WFD"],
+        sent: FIELD_DAY,
+        rcvd: FIELD_DAY,
+        modes: &[],
+        dupes: DupeScope::PerBandMode,
+    },
+    ContestTemplate {
+        contests: &["ARRL-SS-CW"],
+        sent: SWEEPSTAKES,
+        rcvd: SWEEPSTAKES,
+        modes: &[CabrilloMode::Cw],
+        dupes: DupeScope::PerContest,
+    },
+    ContestTemplate {
+        contests: &["ARRL-SS-SSB"],
+        sent: SWEEPSTAKES,
+        rcvd: SWEEPSTAKES,
+        modes: &[CabrilloMode::Ph],
+        dupes: DupeScope::PerContest,
+    },
+    ContestTemplate {
+        contests: &["NAQP-CW"],
+        sent: NAQP,
+        rcvd: NAQP,
+        modes: &[CabrilloMode::Cw],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["NAQP-SSB"],
+        sent: NAQP,
+        rcvd: NAQP,
+        modes: &[CabrilloMode::Ph],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["NAQP-RTTY"],
+        sent: NAQP,
+        rcvd: NAQP,
+        modes: &[CabrilloMode::Ry],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["WW-DIGI"],
+        sent: GRID,
+        rcvd: GRID,
+        modes: &[CabrilloMode::Dg],
+        dupes: DupeScope::PerBand,
+    },
+    ContestTemplate {
+        contests: &["ARRL-VHF-JAN", "ARRL-VHF-JUN", "ARRL-VHF-SEP", "ARRL-222"],
+        sent: GRID,
+        rcvd: GRID,
+        modes: &[],
+        dupes: DupeScope::PerBandRoverGrid,
+    },
+];
+
+/// Set of contest templates, keyed by `CONTEST:` value.
+///
+/// The default registry holds [`BUILTIN_TEMPLATES`]; templates registered later
+/// take precedence, so sponsors' variants can override built-in layouts.
+#[derive(Debug, Clone)]
+pub struct TemplateRegistry {
+    templates: Vec<&'static ContestTemplate>,
+}
+
+impl Default for TemplateRegistry {
+    fn default() -> Self {
+        TemplateRegistry {
+            templates: BUILTIN_TEMPLATES.iter().collect(),
+        }
+    }
+}
+
+impl TemplateRegistry {
+    /// Create a registry holding only the built-in templates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry without any templates.
+    pub fn empty() -> Self {
+        TemplateRegistry {
+            templates: Vec::new(),
+        }
+    }
+
+    /// Add a template.
+    pub fn register(&mut self, template: &'static ContestTemplate) {
+        self.templates.push(template);
+    }
+
+    /// Find the template for a `CONTEST:` value.
+    pub fn lookup(&self, contest: &str) -> Option<&'static ContestTemplate> {
+        self.templates
+            .iter()
+            .rev()
+            .find(|t| t.matches(contest))
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_builtin() {
+        let registry = TemplateRegistry::new();
+        let wpx = registry.lookup("cq-wpx-cw").unwrap();
+        assert_eq!(wpx.sent[1].kind, FieldKind::Serial);
+        assert_eq!(wpx.token_count(), 6);
+        assert!(registry.lookup("UNKNOWN-TEST").is_none());
+    }
+
+    #[test]
+    fn test_allowed_modes() {
+        let registry = TemplateRegistry::new();
+        let digi = registry.lookup("WW-DIGI").unwrap();
+        assert!(digi.allows_mode(CabrilloMode::Dg));
+        assert!(!digi.allows_mode(CabrilloMode::Cw));
+        assert!(
+            registry
+                .lookup("ARRL-FD")
+                .unwrap()
+                .allows_mode(CabrilloMode::Fm)
+        );
+    }
+
+    #[test]
+    fn test_registered_template_overrides_builtin() {
+        static CUSTOM: ContestTemplate = ContestTemplate {
+            contests: &["ARRL-10"],
+            sent: &[FieldDef::new("rst", 3, FieldKind::Rst)],
+            rcvd: &[FieldDef::new("rst", 3, FieldKind::Rst)],
+            modes: &[],
+            dupes: DupeScope::PerBand,
+        };
+        let mut registry = TemplateRegistry::new();
+        registry.register(&CUSTOM);
+        assert_eq!(registry.lookup("ARRL-10").unwrap().sent.len(), 1);
+    }
+}
@@ -0,0 +1,572 @@
+//! Cross-checking the logs of several stations.
+//!
+//! Contest sponsors check every QSO against the log of the station worked:
+//! the QSO must be in that log too, at about the same time, on the same band
+//! and with the exchange that station sent. [`CabrilloLog::cross_check`] does
+//! the same for a set of logs, e.g. those of a club, and gives each station a
+//! UBN report (unique, busted, not-in-log) of its QSOs.
+//!
+//! ```rust
+//! use cabrillo_log::{CabrilloLog, CheckResult, CrossCheckOptions};
+//!
+//! let a = CabrilloLog::parse("START-OF-LOG: 3.0\nCALLSIGN: AA1ZZZ\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4\nEND-OF-LOG:\n").unwrap();
+//! let b = CabrilloLog::parse("START-OF-LOG: 3.0\nCALLSIGN: S50A\nQSO: 7005 CW 2009-05-30 0003 S50A 599 4 AA1ZZZ 599 1\nEND-OF-LOG:\n").unwrap();
+//! let reports = CabrilloLog::cross_check(&[a, b], &CrossCheckOptions::default());
+//! assert_eq!(reports[0].checks[0].result, CheckResult::Match { log: 1, qso: 0 });
+//! ```
+
+use crate::{Band, CabrilloFormat, CabrilloLog, CabrilloMode, Exchange, FieldKind, QSO};
+use chrono::TimeDelta;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// How [`CabrilloLog::cross_check`] pairs QSOs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossCheckOptions {
+    /// Largest time difference between the two logs of a QSO, for clocks
+    /// that are off by a little.
+    pub tolerance: TimeDelta,
+}
+
+impl Default for CrossCheckOptions {
+    fn default() -> Self {
+        CrossCheckOptions {
+            tolerance: TimeDelta::minutes(3),
+        }
+    }
+}
+
+/// Outcome of checking one QSO against the other station's log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CheckResult {
+    /// The other station logged the QSO too, as QSO `qso` of log `log`.
+    Match { log: usize, qso: usize },
+    /// The other station's log has no such QSO.
+    NotInLog,
+    /// The call was miscopied; `correct` logged the QSO at that time.
+    BustedCall { correct: String },
+    /// An exchange field differs from what the other station sent.
+    BustedExchange {
+        field: String,
+        logged: String,
+        sent: String,
+    },
+    /// The other station logged the QSO on another band.
+    BandMismatch {
+        band: Option<Band>,
+        other: Option<Band>,
+    },
+    /// The other station logged the QSO on the same band in another mode.
+    ModeMismatch {
+        mode: CabrilloMode,
+        other: CabrilloMode,
+    },
+    /// No log from the station, and nobody else worked it.
+    Unique,
+    /// No log from the station to check against.
+    Unverified,
+}
+
+impl CheckResult {
+    /// Whether the QSO is in order, or could not be checked.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, CheckResult::Match { .. } | CheckResult::Unverified)
+    }
+
+    /// Section of the rendered report listing QSOs with this result.
+    fn section(&self) -> Option<&'static str> {
+        match self {
+            CheckResult::Unique => Some("Unique"),
+            CheckResult::BustedCall { .. } => Some("Busted calls"),
+            CheckResult::BustedExchange { .. } => Some("Busted exchanges"),
+            CheckResult::BandMismatch { .. } => Some("Band mismatches"),
+            CheckResult::ModeMismatch { .. } => Some("Mode mismatches"),
+            CheckResult::NotInLog => Some("Not in log"),
+            CheckResult::Match { .. } | CheckResult::Unverified => None,
+        }
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let band = |band: &Option<Band>| band.map_or("unknown", |b| b.name());
+        match self {
+            CheckResult::Match { .. } => write!(f, "matched"),
+            CheckResult::NotInLog => write!(f, "not in log"),
+            CheckResult::BustedCall { correct } => write!(f, "busted call, correct is {}", correct),
+            CheckResult::BustedExchange {
+                field,
+                logged,
+                sent,
+            } => write!(f, "busted {}: logged {}, sent {}", field, logged, sent),
+            CheckResult::BandMismatch { band: b, other } => write!(
+                f,
+                "band mismatch: logged on {}, other log has {}",
+                band(b),
+                band(other)
+            ),
+            CheckResult::ModeMismatch { mode, other } => write!(
+                f,
+                "mode mismatch: logged in {}, other log has {}",
+                mode, other
+            ),
+            CheckResult::Unique => write!(f, "unique"),
+            CheckResult::Unverified => write!(f, "no log to check against"),
+        }
+    }
+}
+
+/// The check of a QSO, with its index in [`CabrilloLog::qsos`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QsoCheck {
+    pub qso: usize,
+    pub result: CheckResult,
+}
+
+/// Cross-check results for one station's log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UbnReport {
+    pub callsign: String,
+    /// Checks of the log's QSOs in log order; X-QSOs are not checked.
+    pub checks: Vec<QsoCheck>,
+}
+
+/// Sections of a rendered report, in UBN order.
+const SECTIONS: &[&str] = &[
+    "Unique",
+    "Busted calls",
+    "Busted exchanges",
+    "Band mismatches",
+    "Mode mismatches",
+    "Not in log",
+];
+
+impl UbnReport {
+    /// Number of checks with a result for which `filter` holds.
+    pub fn count(&self, filter: impl Fn(&CheckResult) -> bool) -> usize {
+        self.checks.iter().filter(|c| filter(&c.result)).count()
+    }
+
+    /// Render the report as text, with the QSO lines of the checked `log`.
+    ///
+    /// ```text
+    /// UBN report for AA1ZZZ
+    /// 3 QSOs checked: 1 matched, 1 unverified, 0 unique, 0 busted calls, 1 busted exchanges, ...
+    ///
+    /// Busted exchanges:
+    /// QSO #2: QSO:    7006 CW 2009-05-30 0015 AA1ZZZ        599 2      EF8M          599 34
+    ///   busted rcvd serial: logged 34, sent 43
+    /// ```
+    pub fn render(&self, log: &CabrilloLog) -> String {
+        let format = CabrilloFormat::default();
+        let mut counts = vec![
+            format!(
+                "{} matched",
+                self.count(|r| matches!(r, CheckResult::Match { .. }))
+            ),
+            format!(
+                "{} unverified",
+                self.count(|r| matches!(r, CheckResult::Unverified))
+            ),
+        ];
+        for name in SECTIONS {
+            counts.push(format!(
+                "{} {}",
+                self.count(|r| r.section() == Some(name)),
+                name.to_ascii_lowercase()
+            ));
+        }
+        let mut out = format!(
+            "UBN report for {}\n{} QSOs checked: {}\n",
+            self.callsign,
+            self.checks.len(),
+            counts.join(", ")
+        );
+        for name in SECTIONS {
+            let checks: Vec<_> = self
+                .checks
+                .iter()
+                .filter(|c| c.result.section() == Some(name))
+                .collect();
+            if checks.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("\n{}:\n", name));
+            for check in checks {
+                let line = log
+                    .qsos
+                    .get(check.qso)
+                    .map(|qso| format.qso_line(qso))
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "QSO #{}: {}\n  {}\n",
+                    check.qso + 1,
+                    line,
+                    check.result
+                ));
+            }
+        }
+        out
+    }
+}
+
+impl CabrilloLog {
+    /// Check every QSO of each log against the logs of the stations worked,
+    /// returning one report per log in the order of `logs`.
+    ///
+    /// A log's station is its `CALLSIGN:`, or the sent call of its first QSO.
+    /// QSOs pair up when the other log has the QSO with this station, or with
+    /// a call one character off, within the time tolerance on the same band
+    /// and mode. Each QSO of the other log pairs up with one QSO at most.
+    pub fn cross_check(logs: &[CabrilloLog], options: &CrossCheckOptions) -> Vec<UbnReport> {
+        let calls: Vec<String> = logs.iter().map(station_call).collect();
+        let mut by_call: HashMap<&str, usize> = HashMap::new();
+        for (index, call) in calls.iter().enumerate() {
+            by_call.entry(call.as_str()).or_insert(index);
+        }
+        // Number of logs each call was worked in, for uniques
+        let mut worked_in: HashMap<String, usize> = HashMap::new();
+        for log in logs {
+            let worked: HashSet<String> = log
+                .qsos
+                .iter()
+                .map(|q| q.rcvd_call.to_ascii_uppercase())
+                .collect();
+            for call in worked {
+                *worked_in.entry(call).or_default() += 1;
+            }
+        }
+
+        let mut reports = Vec::with_capacity(logs.len());
+        for (index, log) in logs.iter().enumerate() {
+            let checker = Checker {
+                logs,
+                own: index,
+                call: &calls[index],
+                tolerance: options.tolerance,
+            };
+            let mut claimed = HashSet::new();
+            let mut checks = Vec::new();
+            for (qso_index, qso) in log.qsos.iter().enumerate() {
+                if qso.excluded {
+                    continue;
+                }
+                let worked = qso.rcvd_call.to_ascii_uppercase();
+                let other = by_call.get(worked.as_str()).copied();
+                let result = if let Some(other) = other.filter(|&o| o != index)
+                    && let Some(found) = checker.find(other, qso, &claimed, true)
+                {
+                    claimed.insert((other, found));
+                    exchange_result(qso, &logs[other].qsos[found], other, found)
+                } else if let Some((other, found)) = checker.find_busted(&worked, qso, &claimed) {
+                    claimed.insert((other, found));
+                    CheckResult::BustedCall {
+                        correct: calls[other].clone(),
+                    }
+                } else if let Some(other) = other {
+                    match checker.find(other, qso, &claimed, false) {
+                        Some(found) => mismatch(qso, &logs[other].qsos[found]),
+                        None => CheckResult::NotInLog,
+                    }
+                } else if worked_in.get(&worked).copied().unwrap_or_default() <= 1 {
+                    CheckResult::Unique
+                } else {
+                    CheckResult::Unverified
+                };
+                checks.push(QsoCheck {
+                    qso: qso_index,
+                    result,
+                });
+            }
+            reports.push(UbnReport {
+                callsign: calls[index].clone(),
+                checks,
+            });
+        }
+        reports
+    }
+}
+
+/// Result for a QSO the other station logged on another band or in another mode.
+fn mismatch(qso: &QSO, other: &QSO) -> CheckResult {
+    if qso.freq.band() != other.freq.band() {
+        CheckResult::BandMismatch {
+            band: qso.freq.band(),
+            other: other.freq.band(),
+        }
+    } else {
+        CheckResult::ModeMismatch {
+            mode: qso.mode.category,
+            other: other.mode.category,
+        }
+    }
+}
+
+/// Call of the station that submitted a log, upper case.
+fn station_call(log: &CabrilloLog) -> String {
+    log.headers
+        .callsign()
+        .or_else(|| log.qsos.first().map(|q| q.sent_call.as_str()))
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_uppercase()
+}
+
+/// Looks up the other side of the QSOs of one log.
+struct Checker<'a> {
+    logs: &'a [CabrilloLog],
+    own: usize,
+    /// Station call of the checked log.
+    call: &'a str,
+    tolerance: TimeDelta,
+}
+
+impl Checker<'_> {
+    /// The unclaimed QSO of log `other` with this station closest in time to
+    /// `qso`, on the same band and mode unless `same_band` is false. X-QSOs of
+    /// the other log do not count.
+    fn find(
+        &self,
+        other: usize,
+        qso: &QSO,
+        claimed: &HashSet<(usize, usize)>,
+        same_band: bool,
+    ) -> Option<usize> {
+        self.logs[other]
+            .qsos
+            .iter()
+            .enumerate()
+            .filter(|(i, r)| !r.excluded && !claimed.contains(&(other, *i)))
+            .filter(|(_, r)| {
+                !same_band
+                    || (r.freq.band() == qso.freq.band() && r.mode.category == qso.mode.category)
+            })
+            .filter(|(_, r)| {
+                let rcvd = r.rcvd_call.to_ascii_uppercase();
+                rcvd == self.call || (same_band && one_off(&rcvd, self.call))
+            })
+            .map(|(i, r)| (i, (r.datetime() - qso.datetime()).abs()))
+            .filter(|(_, distance)| *distance <= self.tolerance)
+            .min_by_key(|(i, distance)| {
+                let exact = self.logs[other].qsos[*i]
+                    .rcvd_call
+                    .eq_ignore_ascii_case(self.call);
+                (!exact, *distance)
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// A log of a station with a call one character off `worked` that has
+    /// this QSO, i.e. the station whose call was miscopied.
+    fn find_busted(
+        &self,
+        worked: &str,
+        qso: &QSO,
+        claimed: &HashSet<(usize, usize)>,
+    ) -> Option<(usize, usize)> {
+        (0..self.logs.len())
+            .filter(|&other| other != self.own)
+            .filter(|&other| one_off(&station_call(&self.logs[other]), worked))
+            .find_map(|other| {
+                self.find(other, qso, claimed, true)
+                    .filter(|&found| {
+                        self.logs[other].qsos[found]
+                            .rcvd_call
+                            .eq_ignore_ascii_case(self.call)
+                    })
+                    .map(|found| (other, found))
+            })
+    }
+}
+
+/// Compare the logged exchange with what the other station sent; signal
+/// reports are not checked. An exchange with more or fewer fields than the
+/// one sent is busted as a whole.
+fn exchange_result(qso: &QSO, other_qso: &QSO, other: usize, found: usize) -> CheckResult {
+    let (logged, sent): (&Exchange, &Exchange) = (&qso.rcvd_exch, &other_qso.sent_exch);
+    if logged.fields.len() != sent.fields.len() {
+        return CheckResult::BustedExchange {
+            field: "rcvd exchange".to_string(),
+            logged: logged.to_string(),
+            sent: sent.to_string(),
+        };
+    }
+    for (field, sent) in logged.fields.iter().zip(&sent.fields) {
+        if field.kind == FieldKind::Rst {
+            continue;
+        }
+        let same = match (field.value.parse::<u32>(), sent.value.parse::<u32>()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => field.value.trim().eq_ignore_ascii_case(sent.value.trim()),
+        };
+        if !same {
+            return CheckResult::BustedExchange {
+                field: format!("rcvd {}", field.name),
+                logged: field.value.clone(),
+                sent: sent.value.clone(),
+            };
+        }
+    }
+    CheckResult::Match {
+        log: other,
+        qso: found,
+    }
+}
+
+/// Whether two different calls differ by one inserted, deleted, replaced or
+/// swapped character.
+fn one_off(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a == b || a.len().abs_diff(b.len()) > 1 {
+        return false;
+    }
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    match a.len().cmp(&b.len()) {
+        std::cmp::Ordering::Less => a == &b[1..],
+        std::cmp::Ordering::Greater => &a[1..] == b,
+        std::cmp::Ordering::Equal => {
+            a[1..] == b[1..] || (a.len() >= 2 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGS: [&str; 5] = [
+        "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4
+QSO: 7006 CW 2009-05-30 0015 AA1ZZZ 599 2 S50A 599 34
+QSO: 14010 CW 2009-05-30 0020 AA1ZZZ 599 3 DL1ABD 599 5
+QSO: 14012 CW 2009-05-30 0030 AA1ZZZ 599 4 EF8M 599 6
+QSO: 21010 CW 2009-05-30 0040 AA1ZZZ 599 5 JA1AA 599 7
+QSO: 21010 CW 2009-05-30 0041 AA1ZZZ 599 6 S50A 599 9
+QSO: 14020 PH 2009-05-30 0050 AA1ZZZ 59 7 VE3XX 59 8
+END-OF-LOG:
+",
+        "START-OF-LOG: 3.0
+CALLSIGN: S50A
+CONTEST: CQ-WPX-CW
+QSO: 7005 CW 2009-05-30 0003 S50A 599 4 AA1ZZZ 599 1
+QSO: 7006 CW 2009-05-30 0015 S50A 599 43 AA1ZZ 599 2
+END-OF-LOG:
+",
+        "START-OF-LOG: 3.0
+CALLSIGN: DL1ABC
+CONTEST: CQ-WPX-CW
+QSO: 14010 CW 2009-05-30 0021 DL1ABC 599 5 AA1ZZZ 599 3
+END-OF-LOG:
+",
+        "START-OF-LOG: 3.0
+CALLSIGN: EF8M
+CONTEST: CQ-WPX-CW
+QSO: 7012 CW 2009-05-30 0030 EF8M 599 6 AA1ZZZ 599 4
+END-OF-LOG:
+",
+        "START-OF-LOG: 3.0
+CALLSIGN: VE3XX
+CONTEST: CQ-WPX-CW
+QSO: 14020 CW 2009-05-30 0050 VE3XX 599 8 AA1ZZZ 599 7
+END-OF-LOG:
+",
+    ];
+
+    const UNTYPED: [&str; 2] = [
+        "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4 MA
+QSO: 14005 CW 2009-05-30 0010 AA1ZZZ 599 2 S50A 599 5
+END-OF-LOG:
+",
+        "START-OF-LOG: 3.0
+CALLSIGN: S50A
+QSO: 7005 CW 2009-05-30 0002 S50A 599 4 AA1ZZZ 599 1
+X-QSO: 14005 CW 2009-05-30 0010 S50A 599 5 AA1ZZZ 599 2
+END-OF-LOG:
+",
+    ];
+
+    #[test]
+    fn test_one_off() {
+        assert!(one_off("K1ABC", "K1ABD"));
+        assert!(one_off("K1ABC", "K1AB"));
+        assert!(one_off("K1ABC", "K1BAC"));
+        assert!(!one_off("K1ABC", "K1ABC"));
+        assert!(!one_off("K1ABC", "K1XYC"));
+    }
+
+    #[test]
+    fn test_cross_check() {
+        let logs = LOGS.map(|log| CabrilloLog::parse(log).unwrap());
+        let reports = CabrilloLog::cross_check(&logs, &CrossCheckOptions::default());
+        let results: Vec<_> = reports[0].checks.iter().map(|c| &c.result).collect();
+        assert_eq!(
+            results,
+            vec![
+                &CheckResult::Match { log: 1, qso: 0 },
+                &CheckResult::BustedExchange {
+                    field: "rcvd serial".to_string(),
+                    logged: "34".to_string(),
+                    sent: "43".to_string()
+                },
+                &CheckResult::BustedCall {
+                    correct: "DL1ABC".to_string()
+                },
+                &CheckResult::BandMismatch {
+                    band: Some(Band::B20M),
+                    other: Some(Band::B40M)
+                },
+                &CheckResult::Unique,
+                &CheckResult::NotInLog,
+                &CheckResult::ModeMismatch {
+                    mode: CabrilloMode::Ph,
+                    other: CabrilloMode::Cw
+                },
+            ]
+        );
+
+        // S50A miscopied AA1ZZZ; the QSO is busted on its side only
+        let results: Vec<_> = reports[1].checks.iter().map(|c| &c.result).collect();
+        assert_eq!(
+            results,
+            vec![
+                &CheckResult::Match { log: 0, qso: 0 },
+                &CheckResult::BustedCall {
+                    correct: "AA1ZZZ".to_string()
+                },
+            ]
+        );
+
+        let rendered = reports[0].render(&logs[0]);
+        assert!(rendered.starts_with(
+            "UBN report for AA1ZZZ\n7 QSOs checked: 1 matched, 0 unverified, 1 unique, 1 busted calls, 1 busted exchanges, 1 band mismatches, 1 mode mismatches, 1 not in log\n\nUnique:\nQSO #5: "
+        ));
+        assert!(rendered.contains("\n  mode mismatch: logged in PH, other log has CW\n"));
+        assert!(rendered.ends_with("599 9\n  not in log\n"));
+    }
+
+    #[test]
+    fn test_field_count_and_x_qsos() {
+        let logs = UNTYPED.map(|log| CabrilloLog::parse(log).unwrap());
+        let reports = CabrilloLog::cross_check(&logs, &CrossCheckOptions::default());
+        let results: Vec<_> = reports[0].checks.iter().map(|c| &c.result).collect();
+        assert_eq!(
+            results,
+            vec![
+                &CheckResult::BustedExchange {
+                    field: "rcvd exchange".to_string(),
+                    logged: "599 4 MA".to_string(),
+                    sent: "599 4".to_string()
+                },
+                &CheckResult::NotInLog,
+            ]
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Located parse diagnostics.
+//!
+//! [`CabrilloLog::parse_with_diagnostics`] keeps going after a bad line and
+//! returns everything it could parse together with a [`Diagnostic`] for each
+//! problem, so a batch of submitted logs can be checked in one pass.
+
+use crate::{CabrilloError, CabrilloLog};
+use std::fmt;
+use std::ops::Range;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// The line was skipped or kept verbatim; the log is still usable.
+    Warning,
+    /// The line could not be parsed and is missing from the log.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found at a specific place in the source text.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub line: usize,           // 1-based line number
+    pub columns: Range<usize>, // Byte offsets within the line, end exclusive
+    pub severity: Severity,
+    pub kind: CabrilloError,
+}
+
+impl Diagnostic {
+    /// Create an error diagnostic.
+    pub fn error(line: usize, columns: Range<usize>, kind: CabrilloError) -> Self {
+        Diagnostic {
+            line,
+            columns,
+            severity: Severity::Error,
+            kind,
+        }
+    }
+
+    /// Create a warning diagnostic.
+    pub fn warning(line: usize, columns: Range<usize>, kind: CabrilloError) -> Self {
+        Diagnostic {
+            line,
+            columns,
+            severity: Severity::Warning,
+            kind,
+        }
+    }
+
+    /// Render the diagnostic compiler-style, quoting the offending line of `source`.
+    ///
+    /// ```text
+    /// error: Invalid date: 2023-13-01
+    ///  --> line 3:15
+    ///   |
+    /// 3 | QSO: 14000 CW 2023-13-01 1200 N1MM 599 001 W1AW 599 001
+    ///   |               ^^^^^^^^^^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let text = source
+            .lines()
+            .nth(self.line.saturating_sub(1))
+            .unwrap_or("");
+        let start = self.columns.start.min(text.len());
+        let end = self.columns.end.clamp(start, text.len());
+        let pad = text[..start].chars().count();
+        let width = text[start..end].chars().count().max(1);
+        let gutter = " ".repeat(self.line.to_string().len());
+
+        format!(
+            "{}: {}\n{}--> line {}:{}\n{} |\n{} | {}\n{} | {}{}\n",
+            self.severity,
+            self.kind,
+            gutter,
+            self.line,
+            pad + 1,
+            gutter,
+            self.line,
+            text.replace('\t', " "),
+            gutter,
+            " ".repeat(pad),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}:{}: {}: {}",
+            self.line,
+            self.columns.start + 1,
+            self.severity,
+            self.kind
+        )
+    }
+}
+
+/// Result of a diagnostic parse: the partially parsed log and all problems found.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseReport {
+    pub log: CabrilloLog,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseReport {
+    /// Whether any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// Diagnostics with [`Severity::Error`].
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+    }
+
+    /// Render all diagnostics compiler-style against the parsed source.
+    pub fn render(&self, source: &str) -> String {
+        self.diagnostics
+            .iter()
+            .map(|d| d.render(source))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TemplateRegistry;
+
+    const SOURCE: &str = "START-OF-LOG: 3.0
+CALLSIGN: N1MM
+CATEGORY-POWER: KILOWATT
+QSO: 14000 CW 2023-13-01 1200 N1MM 599 001 W1AW 599 001
+QSO: 14000 CW 2023-10-01 1201 N1MM 599 002 K1ZZ 599 002
+QSO: 14000 CW 2023-10-01 12:02 N1MM 599 003 W2XX 599 003
+garbage
+END-OF-LOG:
+";
+
+    #[test]
+    fn test_collects_all_problems() {
+        let report = CabrilloLog::parse_with_diagnostics(SOURCE, &TemplateRegistry::default());
+        assert_eq!(report.log.qsos.len(), 1);
+        assert_eq!(report.log.qsos[0].rcvd_call, "K1ZZ");
+
+        let found: Vec<_> = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.severity))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (3, Severity::Warning),
+                (4, Severity::Error),
+                (6, Severity::Error),
+                (7, Severity::Warning),
+            ]
+        );
+        assert_eq!(report.errors().count(), 2);
+        assert_eq!(report.diagnostics[0].columns, 16..24);
+        assert_eq!(
+            report.diagnostics[2].kind,
+            CabrilloError::InvalidTime("12:02".to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_points_at_column() {
+        let report = CabrilloLog::parse_with_diagnostics(SOURCE, &TemplateRegistry::default());
+        let rendered = report.diagnostics[1].render(SOURCE);
+        assert_eq!(
+            rendered,
+            "error: Invalid date: 2023-13-01
+ --> line 4:15
+  |
+4 | QSO: 14000 CW 2023-13-01 1200 N1MM 599 001 W1AW 599 001
+  |               ^^^^^^^^^^
+"
+        );
+        assert_eq!(
+            report.diagnostics[1].to_string(),
+            "line 4:15: error: Invalid date: 2023-13-01"
+        );
+    }
+
+    #[test]
+    fn test_strict_parse_reports_first_error() {
+        assert_eq!(
+            CabrilloLog::parse(SOURCE),
+            Err(CabrilloError::InvalidDate("2023-13-01".to_string()))
+        );
+    }
+}
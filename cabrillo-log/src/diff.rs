@@ -0,0 +1,394 @@
+//! Semantic differences between two versions of a log.
+//!
+//! A corrected log often changes little: a busted call is fixed, an exchange
+//! is completed, a QSO is added. A line diff buries that under column changes
+//! and reordered lines. [`CabrilloLog::diff`] matches the QSOs of both versions
+//! instead and reports what happened to each of them.
+//!
+//! ```rust
+//! use cabrillo_log::CabrilloLog;
+//!
+//! let old = CabrilloLog::parse("START-OF-LOG: 3.0\nCALLSIGN: AA1ZZZ\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50B 599 4\nEND-OF-LOG:\n").unwrap();
+//! let new = CabrilloLog::parse("START-OF-LOG: 3.0\nCALLSIGN: AA1ZZZ\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4\nEND-OF-LOG:\n").unwrap();
+//! let diff = old.diff(&new);
+//! assert_eq!(diff.to_string(), "~ QSO #1: rcvd call S50B -> S50A\n");
+//! ```
+
+use crate::{CabrilloFormat, CabrilloLog, Exchange, ExchangeField, QSO};
+use chrono::TimeDelta;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+
+/// QSOs with the same station on the same band at most this far apart are
+/// taken as the same QSO with a corrected time.
+const TIME_WINDOW: TimeDelta = TimeDelta::minutes(10);
+
+/// A header line added, removed or changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeaderChange {
+    pub tag: String,
+    /// Value in the old log; `None` if the line was added.
+    pub old: Option<String>,
+    /// Value in the new log; `None` if the line was removed.
+    pub new: Option<String>,
+}
+
+impl fmt::Display for HeaderChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, "~ {}: {} -> {}", self.tag, old, new),
+            (Some(old), None) => write!(f, "- {}: {}", self.tag, old),
+            (None, new) => write!(f, "+ {}: {}", self.tag, new.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+/// A QSO column whose value changed, e.g. `rcvd call` or `sent serial`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} -> {}", self.field, self.old, self.new)
+    }
+}
+
+/// What happened to a QSO, with indices into [`CabrilloLog::qsos`] of the old
+/// and the new log.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QsoChange {
+    Added {
+        new: usize,
+        qso: QSO,
+    },
+    Removed {
+        old: usize,
+        qso: QSO,
+    },
+    /// The QSO is in both logs with different values; `qso` is the new version.
+    Edited {
+        old: usize,
+        new: usize,
+        qso: QSO,
+        fields: Vec<FieldChange>,
+    },
+}
+
+impl fmt::Display for QsoChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = |qso: &QSO| CabrilloFormat::default().qso_line(qso);
+        match self {
+            QsoChange::Added { new, qso } => write!(f, "+ QSO #{}: {}", new + 1, line(qso)),
+            QsoChange::Removed { old, qso } => write!(f, "- QSO #{}: {}", old + 1, line(qso)),
+            QsoChange::Edited {
+                old, new, fields, ..
+            } => {
+                write!(f, "~ QSO #{}", new + 1)?;
+                if old != new {
+                    write!(f, " (was #{})", old + 1)?;
+                }
+                let fields: Vec<_> = fields.iter().map(ToString::to_string).collect();
+                write!(f, ": {}", fields.join(", "))
+            }
+        }
+    }
+}
+
+/// Result of [`CabrilloLog::diff`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogDiff {
+    /// Header changes, in order of the old log's tags and then the new tags.
+    pub headers: Vec<HeaderChange>,
+    /// QSO changes, in time order.
+    pub qsos: Vec<QsoChange>,
+}
+
+impl LogDiff {
+    /// Whether both logs have the same headers and QSOs.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.qsos.is_empty()
+    }
+}
+
+impl fmt::Display for LogDiff {
+    /// One change per line, headers first.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.headers {
+            writeln!(f, "{}", change)?;
+        }
+        for change in &self.qsos {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+impl CabrilloLog {
+    /// Compare this log with a newer version of it.
+    ///
+    /// QSOs are matched by time, band and received call. QSOs left over are
+    /// then matched by time and band, for fixed calls, and by band and call
+    /// within a few minutes, for fixed times. Comments and the order of the
+    /// QSO lines are not compared.
+    pub fn diff(&self, newer: &CabrilloLog) -> LogDiff {
+        let mut pairs = Pairing::new(self.qsos.len(), newer.qsos.len());
+        pairs.pair_by(&self.qsos, &newer.qsos, |q| {
+            (
+                q.datetime(),
+                q.freq.band(),
+                q.rcvd_call.to_ascii_uppercase(),
+            )
+        });
+        pairs.pair_by(&self.qsos, &newer.qsos, |q| (q.datetime(), q.freq.band()));
+        pairs.pair_within(&self.qsos, &newer.qsos, |q| {
+            (q.freq.band(), q.rcvd_call.to_ascii_uppercase())
+        });
+
+        let mut qsos: Vec<QsoChange> = Vec::new();
+        for (old, new) in pairs.old.iter().enumerate() {
+            match new {
+                Some(new) => {
+                    let fields = qso_changes(&self.qsos[old], &newer.qsos[*new]);
+                    if !fields.is_empty() {
+                        qsos.push(QsoChange::Edited {
+                            old,
+                            new: *new,
+                            qso: newer.qsos[*new].clone(),
+                            fields,
+                        });
+                    }
+                }
+                None => qsos.push(QsoChange::Removed {
+                    old,
+                    qso: self.qsos[old].clone(),
+                }),
+            }
+        }
+        for (new, old) in pairs.new.iter().enumerate() {
+            if old.is_none() {
+                qsos.push(QsoChange::Added {
+                    new,
+                    qso: newer.qsos[new].clone(),
+                });
+            }
+        }
+        qsos.sort_by_key(|change| match change {
+            QsoChange::Added { qso, .. }
+            | QsoChange::Removed { qso, .. }
+            | QsoChange::Edited { qso, .. } => qso.datetime(),
+        });
+
+        LogDiff {
+            headers: header_changes(self, newer),
+            qsos,
+        }
+    }
+}
+
+/// QSOs of the old and the new log paired so far.
+struct Pairing {
+    old: Vec<Option<usize>>,
+    new: Vec<Option<usize>>,
+}
+
+impl Pairing {
+    fn new(old: usize, new: usize) -> Self {
+        Pairing {
+            old: vec![None; old],
+            new: vec![None; new],
+        }
+    }
+
+    fn pair(&mut self, old: usize, new: usize) {
+        self.old[old] = Some(new);
+        self.new[new] = Some(old);
+    }
+
+    /// Pair unpaired QSOs with equal keys, in log order.
+    fn pair_by<K: Hash + Eq>(&mut self, old: &[QSO], new: &[QSO], key: impl Fn(&QSO) -> K) {
+        let mut candidates: HashMap<K, VecDeque<usize>> = HashMap::new();
+        for (index, qso) in new.iter().enumerate() {
+            if self.new[index].is_none() {
+                candidates.entry(key(qso)).or_default().push_back(index);
+            }
+        }
+        for (index, qso) in old.iter().enumerate() {
+            if self.old[index].is_none()
+                && let Some(new) = candidates.get_mut(&key(qso)).and_then(VecDeque::pop_front)
+            {
+                self.pair(index, new);
+            }
+        }
+    }
+
+    /// Pair unpaired QSOs with equal keys at most [`TIME_WINDOW`] apart,
+    /// closest first.
+    fn pair_within<K: Eq>(&mut self, old: &[QSO], new: &[QSO], key: impl Fn(&QSO) -> K) {
+        for (index, qso) in old.iter().enumerate() {
+            if self.old[index].is_some() {
+                continue;
+            }
+            let closest = new
+                .iter()
+                .enumerate()
+                .filter(|(n, other)| self.new[*n].is_none() && key(other) == key(qso))
+                .map(|(n, other)| (n, (other.datetime() - qso.datetime()).abs()))
+                .filter(|(_, distance)| *distance <= TIME_WINDOW)
+                .min_by_key(|(_, distance)| *distance);
+            if let Some((n, _)) = closest {
+                self.pair(index, n);
+            }
+        }
+    }
+}
+
+/// Columns that differ between two versions of a QSO.
+fn qso_changes(old: &QSO, new: &QSO) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    let mut compare = |field: &str, old: String, new: String| {
+        if old != new {
+            changes.push(FieldChange {
+                field: field.to_string(),
+                old,
+                new,
+            });
+        }
+    };
+    compare("freq", old.freq.to_string(), new.freq.to_string());
+    compare("mode", old.mode.to_string(), new.mode.to_string());
+    compare(
+        "time",
+        old.datetime().format("%Y-%m-%d %H%M").to_string(),
+        new.datetime().format("%Y-%m-%d %H%M").to_string(),
+    );
+    compare("sent call", old.sent_call.clone(), new.sent_call.clone());
+    compare_exchange(&mut compare, "sent", &old.sent_exch, &new.sent_exch);
+    compare("rcvd call", old.rcvd_call.clone(), new.rcvd_call.clone());
+    compare_exchange(&mut compare, "rcvd", &old.rcvd_exch, &new.rcvd_exch);
+    let tx = |qso: &QSO| qso.tx.clone().unwrap_or_else(|| "-".to_string());
+    compare("tx", tx(old), tx(new));
+    let line = |qso: &QSO| if qso.excluded { "X-QSO" } else { "QSO" }.to_string();
+    compare("line", line(old), line(new));
+    changes
+}
+
+/// Compare exchange fields by position, named after the old field.
+fn compare_exchange(
+    compare: &mut impl FnMut(&str, String, String),
+    side: &str,
+    old: &Exchange,
+    new: &Exchange,
+) {
+    let count = old.fields.len().max(new.fields.len());
+    for i in 0..count {
+        let (old, new) = (old.fields.get(i), new.fields.get(i));
+        let name = old.or(new).map_or("", |f| f.name.as_str());
+        let value = |field: Option<&ExchangeField>| {
+            field.map_or_else(|| "-".to_string(), |f| f.value.clone())
+        };
+        compare(&format!("{} {}", side, name), value(old), value(new));
+    }
+}
+
+/// Header lines that differ, compared tag by tag.
+fn header_changes(old: &CabrilloLog, new: &CabrilloLog) -> Vec<HeaderChange> {
+    let mut tags: Vec<&str> = Vec::new();
+    for header in old.headers.iter().chain(&new.headers) {
+        if !tags.contains(&header.tag()) {
+            tags.push(header.tag());
+        }
+    }
+    let mut changes = Vec::new();
+    for tag in tags {
+        let (before, after) = (old.headers.get_all(tag), new.headers.get_all(tag));
+        if before == after {
+            continue;
+        }
+        if let ([before], [after]) = (before.as_slice(), after.as_slice()) {
+            changes.push(HeaderChange {
+                tag: tag.to_string(),
+                old: Some(before.clone()),
+                new: Some(after.clone()),
+            });
+            continue;
+        }
+        for value in before.iter().filter(|v| !after.contains(v)) {
+            changes.push(HeaderChange {
+                tag: tag.to_string(),
+                old: Some(value.clone()),
+                new: None,
+            });
+        }
+        for value in after.iter().filter(|v| !before.contains(v)) {
+            changes.push(HeaderChange {
+                tag: tag.to_string(),
+                old: None,
+                new: Some(value.clone()),
+            });
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+CLAIMED-SCORE: 100
+SOAPBOX: first
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50B 599 4
+QSO: 7006 CW 2009-05-30 0015 AA1ZZZ 599 2 EF8M 599 34
+QSO: 7010 CW 2009-05-30 0020 AA1ZZZ 599 3 DL1AA 599 5
+QSO: 14010 CW 2009-05-30 0030 AA1ZZZ 599 4 G3XYZ 599 6
+END-OF-LOG:
+";
+
+    const NEW: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+CLAIMED-SCORE: 120
+SOAPBOX: first
+SOAPBOX: second
+QSO:  7005 CW 2009-05-30 0002 AA1ZZZ        599 1      S50A          599 4
+QSO: 7006 CW 2009-05-30 0015 AA1ZZZ 599 2 EF8M 599 43
+QSO: 14010 CW 2009-05-30 0033 AA1ZZZ 599 4 G3XYZ 599 6
+QSO: 21010 CW 2009-05-30 0040 AA1ZZZ 599 5 JA1AA 599 7
+END-OF-LOG:
+";
+
+    #[test]
+    fn test_identical_logs() {
+        let log = CabrilloLog::parse(OLD).unwrap();
+        assert!(log.diff(&log).is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = CabrilloLog::parse(OLD).unwrap();
+        let new = CabrilloLog::parse(NEW).unwrap();
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.to_string(),
+            "~ CLAIMED-SCORE: 100 -> 120
++ SOAPBOX: second
+~ QSO #1: rcvd call S50B -> S50A
+~ QSO #2: rcvd serial 34 -> 43
+- QSO #3: QSO:    7010 CW 2009-05-30 0020 AA1ZZZ        599 3      DL1AA         599 5
+~ QSO #3 (was #4): time 2009-05-30 0030 -> 2009-05-30 0033
++ QSO #4: QSO:   21010 CW 2009-05-30 0040 AA1ZZZ        599 5      JA1AA         599 7
+"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Duplicate QSO detection.
+//!
+//! Whether two contacts with the same station are dupes depends on the
+//! contest: most allow one QSO per band, some one per band and mode, the
+//! Sweepstakes only one per contest, and VHF contests allow working a station
+//! again whenever either side has moved to a new grid. The scope of a contest
+//! comes from its [`ContestTemplate`](crate::ContestTemplate).
+
+use crate::{CabrilloLog, Callsign, Exchange, FieldKind, QSO, TemplateRegistry};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+/// Which contacts with the same station count as dupes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DupeScope {
+    /// Once per band.
+    PerBand,
+    /// Once per band and mode.
+    #[default]
+    PerBandMode,
+    /// Once in the whole contest.
+    PerContest,
+    /// Once per band for each pair of sent and received grid, so rovers can be
+    /// worked again from every grid they activate.
+    PerBandRoverGrid,
+}
+
+/// What to do with the later QSO of each dupe pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DupeAction {
+    /// Keep the QSO in the log but score it zero points.
+    ZeroPoint,
+    /// Turn the QSO into an X-QSO.
+    Exclude,
+}
+
+/// A QSO that duplicates an earlier one, as indexes into [`CabrilloLog::qsos`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dupe {
+    /// The first QSO with the station in this scope.
+    pub first: usize,
+    /// The later, duplicate QSO.
+    pub dupe: usize,
+}
+
+impl DupeScope {
+    /// Dupe scope of a `CONTEST:` value, [`DupeScope::PerBandMode`] for unknown contests.
+    pub fn for_contest(contest: &str, templates: &TemplateRegistry) -> Self {
+        templates
+            .lookup(contest)
+            .map(|template| template.dupes)
+            .unwrap_or_default()
+    }
+
+    /// Key under which QSOs with the same station collide.
+    fn key(&self, qso: &QSO) -> String {
+        let call = Callsign::parse(&qso.rcvd_call).map_or_else(
+            |_| qso.rcvd_call.trim().to_ascii_uppercase(),
+            |c| c.to_string(),
+        );
+        let band = || qso.freq.band().map_or("?", |b| b.name());
+        let grid = |exch: &Exchange| {
+            exch.first_of(FieldKind::Grid)
+                .map(|f| f.value.to_ascii_uppercase())
+                .unwrap_or_default()
+        };
+        match self {
+            DupeScope::PerBand => format!("{} {}", call, band()),
+            DupeScope::PerBandMode => format!("{} {} {}", call, band(), qso.mode),
+            DupeScope::PerContest => call,
+            DupeScope::PerBandRoverGrid => format!(
+                "{} {} {} {}",
+                call,
+                band(),
+                grid(&qso.sent_exch),
+                grid(&qso.rcvd_exch)
+            ),
+        }
+    }
+}
+
+impl CabrilloLog {
+    /// Dupe scope for this log's `CONTEST:` header, from the built-in templates.
+    pub fn dupe_scope(&self) -> DupeScope {
+        self.dupe_scope_with_templates(&TemplateRegistry::default())
+    }
+
+    /// Dupe scope for this log's `CONTEST:` header, from the given templates.
+    pub fn dupe_scope_with_templates(&self, templates: &TemplateRegistry) -> DupeScope {
+        self.headers
+            .contest()
+            .map(|contest| DupeScope::for_contest(contest, templates))
+            .unwrap_or_default()
+    }
+
+    /// Find the QSOs that duplicate an earlier one in `scope`, in log order.
+    /// X-QSOs are ignored.
+    pub fn find_dupes(&self, scope: DupeScope) -> Vec<Dupe> {
+        let mut first_seen = HashMap::new();
+        let mut dupes = Vec::new();
+        for (index, qso) in self.qsos.iter().enumerate() {
+            if qso.excluded {
+                continue;
+            }
+            match first_seen.entry(scope.key(qso)) {
+                Entry::Occupied(first) => dupes.push(Dupe {
+                    first: *first.get(),
+                    dupe: index,
+                }),
+                Entry::Vacant(entry) => {
+                    entry.insert(index);
+                }
+            }
+        }
+        dupes
+    }
+
+    /// Apply `action` to the later QSO of each dupe pair.
+    pub fn mark_dupes(&mut self, dupes: &[Dupe], action: DupeAction) {
+        for dupe in dupes {
+            if let Some(qso) = self.qsos.get_mut(dupe.dupe) {
+                match action {
+                    DupeAction::ZeroPoint => qso.zero_point = true,
+                    DupeAction::Exclude => qso.excluded = true,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELD_DAY: &str = "START-OF-LOG: 3.0
+CALLSIGN: W1AW
+CONTEST: ARRL-FD
+QSO: 7030 CW 2023-06-24 1800 W1AW 3A CT K1ABC 1D EMA
+QSO: 7200 PH 2023-06-24 1801 W1AW 3A CT k1abc 1D EMA
+QSO: 7031 CW 2023-06-24 1802 W1AW 3A CT K1ABC 1D EMA
+QSO: 14030 CW 2023-06-24 1803 W1AW 3A CT K1ABC 1D EMA
+END-OF-LOG:
+";
+
+    const VHF: &str = "START-OF-LOG: 3.0
+CALLSIGN: W1AW
+CONTEST: ARRL-VHF-JUN
+QSO: 50 PH 2023-06-10 1800 W1AW FN31 K1ABC/R FN42
+QSO: 50 PH 2023-06-10 1900 W1AW FN31 K1ABC/R FN43
+QSO: 50 PH 2023-06-10 2000 W1AW FN31 K1ABC/R FN43
+END-OF-LOG:
+";
+
+    const WPX: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4
+QSO: 7010 CW 2009-05-30 0020 AA1ZZZ 599 2 S50A 599 5
+END-OF-LOG:
+";
+
+    #[test]
+    fn test_dupe_scopes() {
+        let log = CabrilloLog::parse(FIELD_DAY).unwrap();
+        assert_eq!(log.dupe_scope(), DupeScope::PerBandMode);
+        assert_eq!(
+            log.find_dupes(DupeScope::PerBandMode),
+            vec![Dupe { first: 0, dupe: 2 }]
+        );
+        assert_eq!(log.find_dupes(DupeScope::PerBand).len(), 2);
+        assert_eq!(log.find_dupes(DupeScope::PerContest).len(), 3);
+    }
+
+    #[test]
+    fn test_rover_grid_scope() {
+        let log = CabrilloLog::parse(VHF).unwrap();
+        assert_eq!(log.dupe_scope(), DupeScope::PerBandRoverGrid);
+        assert_eq!(
+            log.find_dupes(log.dupe_scope()),
+            vec![Dupe { first: 1, dupe: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_mark_dupes() {
+        let mut log = CabrilloLog::parse(WPX).unwrap();
+        let dupes = log.find_dupes(log.dupe_scope());
+        let mut zeroed = log.clone();
+        zeroed.mark_dupes(&dupes, DupeAction::ZeroPoint);
+        assert!(zeroed.qsos[1].zero_point && !zeroed.qsos[1].excluded);
+
+        log.mark_dupes(&dupes, DupeAction::Exclude);
+        assert!(log.qsos[1].excluded);
+        assert!(log.to_string().contains("X-QSO:  7010 CW"));
+        assert!(log.find_dupes(log.dupe_scope()).is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+//! Named, typed QSO exchange fields.
+
+use std::fmt;
+
+/// Kind of value carried by an exchange field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldKind {
+    /// Signal report, e.g. `599` or `59`, or a dB report of digital modes
+    /// such as FT8, e.g. `-10` or `+03`.
+    Rst,
+    /// Serial number.
+    Serial,
+    /// CQ or ITU zone number.
+    Zone,
+    /// Maidenhead grid square, 4 or 6 characters.
+    Grid,
+    /// ARRL/RAC section or similar short location code.
+    Section,
+    /// Sweepstakes precedence letter.
+    Precedence,
+    /// Sweepstakes check (two-digit year first licensed).
+    Check,
+    /// Field Day class, e.g. `3A`.
+    Class,
+    /// Operator name.
+    Name,
+    /// Free-form token (state, power, serial-or-state, ...).
+    Text,
+}
+
+impl FieldKind {
+    /// Check whether a token is a plausible value of this kind.
+    pub fn accepts(&self, value: &str) -> bool {
+        let all_digits = !value.is_empty() && value.chars().all(|c| c.is_ascii_digit());
+        match self {
+            FieldKind::Rst => (all_digits && (2..=3).contains(&value.len())) || is_db_report(value),
+            FieldKind::Serial => all_digits,
+            FieldKind::Zone => all_digits && value.len() <= 2,
+            FieldKind::Grid => is_grid(value),
+            FieldKind::Section | FieldKind::Name => {
+                !value.is_empty() && value.chars().all(|c| c.is_ascii_alphabetic())
+            }
+            FieldKind::Precedence => {
+                value.len() == 1 && "QABUMS".contains(value.to_ascii_uppercase().as_str())
+            }
+            FieldKind::Check => all_digits && value.len() == 2,
+            FieldKind::Class => {
+                let digits = value.chars().take_while(|c| c.is_ascii_digit()).count();
+                digits > 0
+                    && value.len() > digits
+                    && value[digits..].chars().all(|c| c.is_ascii_alphabetic())
+            }
+            FieldKind::Text => !value.is_empty() && value.is_ascii(),
+        }
+    }
+}
+
+/// Check whether a string is a signed dB report, e.g. `-10` or `+03`.
+fn is_db_report(value: &str) -> bool {
+    value
+        .strip_prefix(['-', '+'])
+        .is_some_and(|db| (1..=2).contains(&db.len()) && db.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Check whether a string is a 4 or 6 character Maidenhead locator.
+fn is_grid(value: &str) -> bool {
+    let b = value.as_bytes();
+    if b.len() != 4 && b.len() != 6 {
+        return false;
+    }
+    let field = |c: u8| (b'A'..=b'R').contains(&c.to_ascii_uppercase());
+    let square = |c: u8| c.is_ascii_digit();
+    let subsquare = |c: u8| (b'A'..=b'X').contains(&c.to_ascii_uppercase());
+    field(b[0])
+        && field(b[1])
+        && square(b[2])
+        && square(b[3])
+        && (b.len() == 4 || (subsquare(b[4]) && subsquare(b[5])))
+}
+
+/// A single exchange field, e.g. the `rst` or `serial` part of a QSO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExchangeField {
+    pub name: String,
+    pub kind: FieldKind,
+    pub value: String,
+}
+
+impl ExchangeField {
+    /// Create an exchange field.
+    pub fn new(name: impl Into<String>, kind: FieldKind, value: impl Into<String>) -> Self {
+        ExchangeField {
+            name: name.into(),
+            kind,
+            value: value.into(),
+        }
+    }
+
+    /// The value as a number, if it is one.
+    pub fn number(&self) -> Option<u32> {
+        self.value.parse().ok()
+    }
+
+    /// Whether the value is plausible for the field kind.
+    pub fn is_valid(&self) -> bool {
+        self.kind.accepts(&self.value)
+    }
+}
+
+/// The sent or received exchange of a QSO, as an ordered list of fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Exchange {
+    pub fields: Vec<ExchangeField>,
+}
+
+impl Exchange {
+    /// Create an exchange from its fields.
+    pub fn new(fields: Vec<ExchangeField>) -> Self {
+        Exchange { fields }
+    }
+
+    /// Build an exchange from bare tokens when no contest template is known.
+    ///
+    /// A leading signal report becomes an `rst` field; the remaining tokens are
+    /// named `exch1`, `exch2`, ... and typed as [`FieldKind::Text`].
+    pub fn from_untyped<'a>(tokens: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut fields = Vec::new();
+        for token in tokens {
+            if fields.is_empty() && FieldKind::Rst.accepts(token) && token.starts_with('5') {
+                fields.push(ExchangeField::new("rst", FieldKind::Rst, token));
+            } else {
+                let index = fields.iter().filter(|f| f.kind == FieldKind::Text).count() + 1;
+                fields.push(ExchangeField::new(
+                    format!("exch{}", index),
+                    FieldKind::Text,
+                    token,
+                ));
+            }
+        }
+        Exchange { fields }
+    }
+
+    /// Look up a field by name.
+    pub fn field(&self, name: &str) -> Option<&ExchangeField> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Value of a field by name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.field(name).map(|f| f.value.as_str())
+    }
+
+    /// The first field of the given kind.
+    pub fn first_of(&self, kind: FieldKind) -> Option<&ExchangeField> {
+        self.fields.iter().find(|f| f.kind == kind)
+    }
+
+    /// The signal report, if the exchange has one.
+    pub fn rst(&self) -> Option<&str> {
+        self.first_of(FieldKind::Rst).map(|f| f.value.as_str())
+    }
+
+    /// The serial number, if the exchange has one.
+    pub fn serial(&self) -> Option<u32> {
+        self.first_of(FieldKind::Serial)
+            .and_then(ExchangeField::number)
+    }
+
+    /// Whether the exchange has no fields.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl fmt::Display for Exchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", field.value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_kinds() {
+        assert!(FieldKind::Rst.accepts("599"));
+        assert!(!FieldKind::Rst.accepts("5999"));
+        assert!(FieldKind::Rst.accepts("-10"));
+        assert!(FieldKind::Rst.accepts("+03"));
+        assert!(!FieldKind::Rst.accepts("-"));
+        assert!(!FieldKind::Rst.accepts("10-"));
+        assert!(FieldKind::Grid.accepts("FN32"));
+        assert!(FieldKind::Grid.accepts("fn32ab"));
+        assert!(!FieldKind::Grid.accepts("FN3"));
+        assert!(FieldKind::Class.accepts("3A"));
+        assert!(FieldKind::Class.accepts("12F"));
+        assert!(!FieldKind::Class.accepts("A3"));
+        assert!(FieldKind::Precedence.accepts("B"));
+        assert!(!FieldKind::Zone.accepts("123"));
+    }
+
+    #[test]
+    fn test_untyped_exchange() {
+        let exch = Exchange::from_untyped(["599", "001"]);
+        assert_eq!(exch.rst(), Some("599"));
+        assert_eq!(exch.get("exch1"), Some("001"));
+        assert_eq!(exch.to_string(), "599 001");
+
+        let exch = Exchange::from_untyped(["1211", "B", "74", "SCV"]);
+        assert_eq!(exch.rst(), None);
+        assert_eq!(exch.get("exch4"), Some("SCV"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Typed QSO frequency and amateur bands.
+//!
+//! The frequency column of a QSO line holds either an exact frequency in kHz
+//! (`14025`) or, from 50 MHz up, a band designator (`50`, `1.2G`, `LIGHT`).
+//! [`Frequency`] keeps the two apart; both convert to a [`Band`].
+
+use crate::header::CategoryBand;
+use std::fmt;
+use std::str::FromStr;
+
+/// Amateur band, ordered by frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Band {
+    B160M,
+    B80M,
+    B60M,
+    B40M,
+    B30M,
+    B20M,
+    B17M,
+    B15M,
+    B12M,
+    B10M,
+    B6M,
+    B4M,
+    B2M,
+    B222,
+    B432,
+    B902,
+    B1_2G,
+    B2_3G,
+    B3_4G,
+    B5_7G,
+    B10G,
+    B24G,
+    B47G,
+    B75G,
+    B122G,
+    B134G,
+    B241G,
+    Light,
+}
+
+impl Band {
+    /// All bands, lowest first.
+    pub const ALL: &'static [Band] = &[
+        Band::B160M,
+        Band::B80M,
+        Band::B60M,
+        Band::B40M,
+        Band::B30M,
+        Band::B20M,
+        Band::B17M,
+        Band::B15M,
+        Band::B12M,
+        Band::B10M,
+        Band::B6M,
+        Band::B4M,
+        Band::B2M,
+        Band::B222,
+        Band::B432,
+        Band::B902,
+        Band::B1_2G,
+        Band::B2_3G,
+        Band::B3_4G,
+        Band::B5_7G,
+        Band::B10G,
+        Band::B24G,
+        Band::B47G,
+        Band::B75G,
+        Band::B122G,
+        Band::B134G,
+        Band::B241G,
+        Band::Light,
+    ];
+
+    /// Name, lower edge and upper edge in kHz, and Cabrillo designator.
+    fn info(&self) -> (&'static str, f64, f64, Option<&'static str>) {
+        match self {
+            Band::B160M => ("160m", 1_800.0, 2_000.0, None),
+            Band::B80M => ("80m", 3_500.0, 4_000.0, None),
+            Band::B60M => ("60m", 5_060.0, 5_450.0, None),
+            Band::B40M => ("40m", 7_000.0, 7_300.0, None),
+            Band::B30M => ("30m", 10_100.0, 10_150.0, None),
+            Band::B20M => ("20m", 14_000.0, 14_350.0, None),
+            Band::B17M => ("17m", 18_068.0, 18_168.0, None),
+            Band::B15M => ("15m", 21_000.0, 21_450.0, None),
+            Band::B12M => ("12m", 24_890.0, 24_990.0, None),
+            Band::B10M => ("10m", 28_000.0, 29_700.0, None),
+            Band::B6M => ("6m", 50_000.0, 54_000.0, Some("50")),
+            Band::B4M => ("4m", 70_000.0, 71_000.0, Some("70")),
+            Band::B2M => ("2m", 144_000.0, 148_000.0, Some("144")),
+            Band::B222 => ("1.25m", 222_000.0, 225_000.0, Some("222")),
+            Band::B432 => ("70cm", 420_000.0, 450_000.0, Some("432")),
+            Band::B902 => ("33cm", 902_000.0, 928_000.0, Some("902")),
+            Band::B1_2G => ("23cm", 1_240_000.0, 1_300_000.0, Some("1.2G")),
+            Band::B2_3G => ("13cm", 2_300_000.0, 2_450_000.0, Some("2.3G")),
+            Band::B3_4G => ("9cm", 3_300_000.0, 3_500_000.0, Some("3.4G")),
+            Band::B5_7G => ("6cm", 5_650_000.0, 5_925_000.0, Some("5.7G")),
+            Band::B10G => ("3cm", 10_000_000.0, 10_500_000.0, Some("10G")),
+            Band::B24G => ("1.25cm", 24_000_000.0, 24_250_000.0, Some("24G")),
+            Band::B47G => ("6mm", 47_000_000.0, 47_200_000.0, Some("47G")),
+            Band::B75G => ("4mm", 75_500_000.0, 81_000_000.0, Some("75G")),
+            Band::B122G => ("2.5mm", 119_980_000.0, 123_000_000.0, Some("122G")),
+            Band::B134G => ("2mm", 134_000_000.0, 149_000_000.0, Some("134G")),
+            Band::B241G => ("1mm", 241_000_000.0, 250_000_000.0, Some("241G")),
+            Band::Light => ("light", 3.0e11, 3.0e12, Some("LIGHT")),
+        }
+    }
+
+    /// Conventional name, e.g. `20m` or `70cm` (as used by ADIF).
+    pub fn name(&self) -> &'static str {
+        self.info().0
+    }
+
+    /// Lower and upper band edge in kHz.
+    pub fn edges_khz(&self) -> (f64, f64) {
+        let (_, low, high, _) = self.info();
+        (low, high)
+    }
+
+    /// Cabrillo band designator used in place of a frequency; only bands from 50 MHz up have one.
+    pub fn designator(&self) -> Option<&'static str> {
+        self.info().3
+    }
+
+    /// The band containing a frequency in kHz.
+    pub fn from_khz(khz: f64) -> Option<Band> {
+        Band::ALL.iter().copied().find(|band| {
+            let (low, high) = band.edges_khz();
+            (low..=high).contains(&khz)
+        })
+    }
+
+    /// The band for a Cabrillo designator such as `50` or `1.2G`.
+    pub fn from_designator(designator: &str) -> Option<Band> {
+        let designator = designator.trim();
+        Band::ALL.iter().copied().find(|band| {
+            band.designator()
+                .is_some_and(|d| d.eq_ignore_ascii_case(designator))
+        })
+    }
+}
+
+impl FromStr for Band {
+    type Err = ();
+
+    /// Accepts a band name (`20m`, `70cm`) or a Cabrillo designator (`432`, `LIGHT`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Band::ALL
+            .iter()
+            .copied()
+            .find(|band| band.name().eq_ignore_ascii_case(s))
+            .or_else(|| Band::from_designator(s))
+            .ok_or(())
+    }
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl CategoryBand {
+    /// The single band of this category, or `None` for `ALL` and the multi-band VHF categories.
+    pub fn band(&self) -> Option<Band> {
+        let band = match self {
+            CategoryBand::B160M => Band::B160M,
+            CategoryBand::B80M => Band::B80M,
+            CategoryBand::B40M => Band::B40M,
+            CategoryBand::B20M => Band::B20M,
+            CategoryBand::B15M => Band::B15M,
+            CategoryBand::B10M => Band::B10M,
+            CategoryBand::B6M => Band::B6M,
+            CategoryBand::B4M => Band::B4M,
+            CategoryBand::B2M => Band::B2M,
+            CategoryBand::B222 => Band::B222,
+            CategoryBand::B432 => Band::B432,
+            CategoryBand::B902 => Band::B902,
+            CategoryBand::B1_2G => Band::B1_2G,
+            CategoryBand::B2_3G => Band::B2_3G,
+            CategoryBand::B3_4G => Band::B3_4G,
+            CategoryBand::B5_7G => Band::B5_7G,
+            CategoryBand::B10G => Band::B10G,
+            CategoryBand::B24G => Band::B24G,
+            CategoryBand::B47G => Band::B47G,
+            CategoryBand::B75G => Band::B75G,
+            CategoryBand::B122G => Band::B122G,
+            CategoryBand::B134G => Band::B134G,
+            CategoryBand::B241G => Band::B241G,
+            CategoryBand::Light => Band::Light,
+            CategoryBand::All | CategoryBand::Vhf3Band | CategoryBand::VhfFmOnly => return None,
+        };
+        Some(band)
+    }
+
+    /// The single-band category for a band; `None` for bands without one, such as the WARC bands.
+    pub fn for_band(band: Band) -> Option<CategoryBand> {
+        CategoryBand::ALL
+            .iter()
+            .find(|category| category.band() == Some(band))
+            .copied()
+    }
+}
+
+/// Frequency column of a QSO line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    /// Exact frequency in kHz.
+    Khz(f64),
+    /// Band designator, used from 50 MHz up.
+    Band(Band),
+}
+
+impl Frequency {
+    /// The band this frequency falls in.
+    pub fn band(&self) -> Option<Band> {
+        match self {
+            Frequency::Khz(khz) => Band::from_khz(*khz),
+            Frequency::Band(band) => Some(*band),
+        }
+    }
+
+    /// The exact frequency in kHz, if known.
+    pub fn khz(&self) -> Option<f64> {
+        match self {
+            Frequency::Khz(khz) => Some(*khz),
+            Frequency::Band(_) => None,
+        }
+    }
+}
+
+impl Default for Frequency {
+    fn default() -> Self {
+        Frequency::Khz(0.0)
+    }
+}
+
+impl From<Band> for Frequency {
+    /// The designator of a VHF+ band, or the lower band edge for bands without one.
+    fn from(band: Band) -> Self {
+        match band.designator() {
+            Some(_) => Frequency::Band(band),
+            None => Frequency::Khz(band.edges_khz().0),
+        }
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(band) = Band::from_designator(s) {
+            return Ok(Frequency::Band(band));
+        }
+        match s.trim().parse::<f64>() {
+            Ok(khz) if khz.is_finite() && khz > 0.0 => Ok(Frequency::Khz(khz)),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Frequency::Khz(khz) => write!(f, "{}", khz),
+            Frequency::Band(band) => {
+                write!(f, "{}", band.designator().unwrap_or_else(|| band.name()))
+            }
+        }
+    }
+}
+
+serde_as_str!(Band);
+serde_as_str!(Frequency);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frequency() {
+        assert_eq!("14025".parse(), Ok(Frequency::Khz(14025.0)));
+        assert_eq!("50".parse(), Ok(Frequency::Band(Band::B6M)));
+        assert_eq!("1.2g".parse(), Ok(Frequency::Band(Band::B1_2G)));
+        assert_eq!("LIGHT".parse(), Ok(Frequency::Band(Band::Light)));
+        assert_eq!("invalid".parse::<Frequency>(), Err(()));
+        assert_eq!("-7000".parse::<Frequency>(), Err(()));
+
+        assert_eq!(Frequency::Khz(7030.5).to_string(), "7030.5");
+        assert_eq!(Frequency::Band(Band::B2M).to_string(), "144");
+    }
+
+    #[test]
+    fn test_frequency_band() {
+        assert_eq!(Frequency::Khz(10120.0).band(), Some(Band::B30M));
+        assert_eq!(Frequency::Khz(50125.0).band(), Some(Band::B6M));
+        assert_eq!(Frequency::Band(Band::B6M).band(), Some(Band::B6M));
+        assert_eq!(Frequency::Khz(12345.0).band(), None);
+        assert_eq!(Frequency::from(Band::B17M), Frequency::Khz(18068.0));
+        assert_eq!(Frequency::from(Band::B10G), Frequency::Band(Band::B10G));
+    }
+
+    #[test]
+    fn test_band_names() {
+        assert_eq!("70cm".parse(), Ok(Band::B432));
+        assert_eq!("432".parse(), Ok(Band::B432));
+        assert_eq!("12M".parse(), Ok(Band::B12M));
+        assert_eq!(Band::B222.to_string(), "1.25m");
+        assert_eq!(CategoryBand::B1_2G.band(), Some(Band::B1_2G));
+        assert_eq!(CategoryBand::All.band(), None);
+        assert!(Band::B160M < Band::Light);
+    }
+}
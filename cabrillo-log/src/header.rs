@@ -0,0 +1,748 @@
+//! Ordered, multi-valued, typed Cabrillo header storage.
+//!
+//! Cabrillo allows several tags (`ADDRESS:`, `SOAPBOX:`, `OPERATORS:`, ...) to
+//! appear more than once, and submitted logs are expected to keep their header
+//! lines in the order the author wrote them. [`Headers`] therefore stores every
+//! occurrence in file order instead of a map keyed by tag.
+//!
+//! Tags defined by the Cabrillo 3.0 specification are parsed into [`Header`]
+//! variants with typed values. Unknown tags, `X-` tags and known tags whose value
+//! cannot be parsed are kept verbatim as [`Header::Other`], so nothing is lost
+//! when a log is written back.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Defines a header value enum together with its Cabrillo spelling.
+macro_rules! header_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $text:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            /// All values, in specification order.
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+            /// The value as written in a Cabrillo file.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $text),+
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ();
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let s = s.trim();
+                $name::ALL
+                    .iter()
+                    .find(|v| v.as_str().eq_ignore_ascii_case(s))
+                    .copied()
+                    .ok_or(())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+                s.parse().map_err(|_| {
+                    serde::de::Error::custom(format!("invalid {}: {}", stringify!($name), s))
+                })
+            }
+        }
+    };
+}
+
+header_enum!(
+    /// Value of `CATEGORY-OPERATOR:`.
+    CategoryOperator {
+        SingleOp => "SINGLE-OP",
+        MultiOp => "MULTI-OP",
+        Checklog => "CHECKLOG",
+    }
+);
+
+header_enum!(
+    /// Value of `CATEGORY-ASSISTED:`.
+    CategoryAssisted {
+        Assisted => "ASSISTED",
+        NonAssisted => "NON-ASSISTED",
+    }
+);
+
+header_enum!(
+    /// Value of `CATEGORY-BAND:`.
+    CategoryBand {
+        All => "ALL",
+        B160M => "160M",
+        B80M => "80M",
+        B40M => "40M",
+        B20M => "20M",
+        B15M => "15M",
+        B10M => "10M",
+        B6M => "6M",
+        B4M => "4M",
+        B2M => "2M",
+        B222 => "222",
+        B432 => "432",
+        B902 => "902",
+        B1_2G => "1.2G",
+        B2_3G => "2.3G",
+        B3_4G => "3.4G",
+        B5_7G => "5.7G",
+        B10G => "10G",
+        B24G => "24G",
+        B47G => "47G",
+        B75G => "75G",
+        B122G => "122G",
+        B134G => "134G",
+        B241G => "241G",
+        Light => "LIGHT",
+        Vhf3Band => "VHF-3-BAND",
+        VhfFmOnly => "VHF-FM-ONLY",
+    }
+);
+
+header_enum!(
+    /// Value of `CATEGORY-MODE:`.
+    CategoryMode {
+        Cw => "CW",
+        Digi => "DIGI",
+        Fm => "FM",
+        Rtty => "RTTY",
+        Ssb => "SSB",
+        Mixed => "MIXED",
+    }
+);
+
+header_enum!(
+    /// Value of `CATEGORY-POWER:`.
+    CategoryPower {
+        High => "HIGH",
+        Low => "LOW",
+        Qrp => "QRP",
+    }
+);
+
+header_enum!(
+    /// Value of `CATEGORY-STATION:`.
+    CategoryStation {
+        Distributed => "DISTRIBUTED",
+        Fixed => "FIXED",
+        Mobile => "MOBILE",
+        Portable => "PORTABLE",
+        Rover => "ROVER",
+        RoverLimited => "ROVER-LIMITED",
+        RoverUnlimited => "ROVER-UNLIMITED",
+        Expedition => "EXPEDITION",
+        Hq => "HQ",
+        School => "SCHOOL",
+        Explorer => "EXPLORER",
+    }
+);
+
+header_enum!(
+    /// Value of `CATEGORY-TRANSMITTER:`.
+    CategoryTransmitter {
+        One => "ONE",
+        Two => "TWO",
+        Limited => "LIMITED",
+        Unlimited => "UNLIMITED",
+        Swl => "SWL",
+    }
+);
+
+header_enum!(
+    /// Value of `CATEGORY-TIME:`.
+    CategoryTime {
+        Hours6 => "6-HOURS",
+        Hours8 => "8-HOURS",
+        Hours12 => "12-HOURS",
+        Hours24 => "24-HOURS",
+    }
+);
+
+header_enum!(
+    /// Value of `CATEGORY-OVERLAY:`.
+    CategoryOverlay {
+        Classic => "CLASSIC",
+        Rookie => "ROOKIE",
+        TbWires => "TB-WIRES",
+        Youth => "YOUTH",
+        NoviceTech => "NOVICE-TECH",
+        Over50 => "OVER-50",
+    }
+);
+
+/// Tags whose value is parsed into something other than free text.
+const TYPED_VALUE_TAGS: &[&str] = &[
+    "CATEGORY-OPERATOR",
+    "CATEGORY-ASSISTED",
+    "CATEGORY-BAND",
+    "CATEGORY-MODE",
+    "CATEGORY-POWER",
+    "CATEGORY-STATION",
+    "CATEGORY-TRANSMITTER",
+    "CATEGORY-TIME",
+    "CATEGORY-OVERLAY",
+    "CLAIMED-SCORE",
+];
+
+/// A single header line, e.g. `CALLSIGN: N1MM`.
+///
+/// With the `serde` feature a header is stored as its `tag` and `value` strings
+/// and re-parsed on the way back in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "RawHeader", into = "RawHeader")
+)]
+pub enum Header {
+    Callsign(String),
+    Contest(String),
+    CategoryOperator(CategoryOperator),
+    CategoryAssisted(CategoryAssisted),
+    CategoryBand(CategoryBand),
+    CategoryMode(CategoryMode),
+    CategoryPower(CategoryPower),
+    CategoryStation(CategoryStation),
+    CategoryTransmitter(CategoryTransmitter),
+    CategoryTime(CategoryTime),
+    CategoryOverlay(CategoryOverlay),
+    ClaimedScore(u64),
+    Club(String),
+    CreatedBy(String),
+    Email(String),
+    GridLocator(String),
+    Location(String),
+    Name(String),
+    Address(String),
+    AddressCity(String),
+    AddressStateProvince(String),
+    AddressPostalcode(String),
+    AddressCountry(String),
+    Operators(String),
+    Soapbox(String),
+    /// Unknown or `X-` tag, or a known tag whose value did not parse.
+    Other {
+        tag: String,
+        value: String,
+    },
+}
+
+impl Header {
+    /// Parse a header line from its tag and value.
+    ///
+    /// This never fails: anything that does not map onto a typed variant is kept
+    /// as [`Header::Other`].
+    pub fn parse(tag: &str, value: &str) -> Self {
+        let tag = tag.trim();
+        let value = value.trim();
+        let text = || value.to_string();
+        let typed = match tag.to_ascii_uppercase().as_str() {
+            "CALLSIGN" => Some(Header::Callsign(text())),
+            "CONTEST" => Some(Header::Contest(text())),
+            "CATEGORY-OPERATOR" => value.parse().ok().map(Header::CategoryOperator),
+            "CATEGORY-ASSISTED" => value.parse().ok().map(Header::CategoryAssisted),
+            "CATEGORY-BAND" => value.parse().ok().map(Header::CategoryBand),
+            "CATEGORY-MODE" => value.parse().ok().map(Header::CategoryMode),
+            "CATEGORY-POWER" => value.parse().ok().map(Header::CategoryPower),
+            "CATEGORY-STATION" => value.parse().ok().map(Header::CategoryStation),
+            "CATEGORY-TRANSMITTER" => value.parse().ok().map(Header::CategoryTransmitter),
+            "CATEGORY-TIME" => value.parse().ok().map(Header::CategoryTime),
+            "CATEGORY-OVERLAY" => value.parse().ok().map(Header::CategoryOverlay),
+            "CLAIMED-SCORE" => value
+                .parse()
+                .ok()
+                .filter(|score: &u64| score.to_string() == value)
+                .map(Header::ClaimedScore),
+            "CLUB" => Some(Header::Club(text())),
+            "CREATED-BY" => Some(Header::CreatedBy(text())),
+            "EMAIL" => Some(Header::Email(text())),
+            "GRID-LOCATOR" => Some(Header::GridLocator(text())),
+            "LOCATION" => Some(Header::Location(text())),
+            "NAME" => Some(Header::Name(text())),
+            "ADDRESS" => Some(Header::Address(text())),
+            "ADDRESS-CITY" => Some(Header::AddressCity(text())),
+            "ADDRESS-STATE-PROVINCE" => Some(Header::AddressStateProvince(text())),
+            "ADDRESS-POSTALCODE" => Some(Header::AddressPostalcode(text())),
+            "ADDRESS-COUNTRY" => Some(Header::AddressCountry(text())),
+            "OPERATORS" => Some(Header::Operators(text())),
+            "SOAPBOX" => Some(Header::Soapbox(text())),
+            _ => None,
+        };
+        typed.unwrap_or_else(|| Header::Other {
+            tag: tag.to_string(),
+            value: text(),
+        })
+    }
+
+    /// The tag as written in a Cabrillo file, without the trailing colon.
+    pub fn tag(&self) -> &str {
+        match self {
+            Header::Callsign(_) => "CALLSIGN",
+            Header::Contest(_) => "CONTEST",
+            Header::CategoryOperator(_) => "CATEGORY-OPERATOR",
+            Header::CategoryAssisted(_) => "CATEGORY-ASSISTED",
+            Header::CategoryBand(_) => "CATEGORY-BAND",
+            Header::CategoryMode(_) => "CATEGORY-MODE",
+            Header::CategoryPower(_) => "CATEGORY-POWER",
+            Header::CategoryStation(_) => "CATEGORY-STATION",
+            Header::CategoryTransmitter(_) => "CATEGORY-TRANSMITTER",
+            Header::CategoryTime(_) => "CATEGORY-TIME",
+            Header::CategoryOverlay(_) => "CATEGORY-OVERLAY",
+            Header::ClaimedScore(_) => "CLAIMED-SCORE",
+            Header::Club(_) => "CLUB",
+            Header::CreatedBy(_) => "CREATED-BY",
+            Header::Email(_) => "EMAIL",
+            Header::GridLocator(_) => "GRID-LOCATOR",
+            Header::Location(_) => "LOCATION",
+            Header::Name(_) => "NAME",
+            Header::Address(_) => "ADDRESS",
+            Header::AddressCity(_) => "ADDRESS-CITY",
+            Header::AddressStateProvince(_) => "ADDRESS-STATE-PROVINCE",
+            Header::AddressPostalcode(_) => "ADDRESS-POSTALCODE",
+            Header::AddressCountry(_) => "ADDRESS-COUNTRY",
+            Header::Operators(_) => "OPERATORS",
+            Header::Soapbox(_) => "SOAPBOX",
+            Header::Other { tag, .. } => tag,
+        }
+    }
+
+    /// Whether this is a known tag with a typed value that failed to parse.
+    pub fn has_invalid_value(&self) -> bool {
+        match self {
+            Header::Other { tag, .. } => TYPED_VALUE_TAGS
+                .iter()
+                .any(|typed| typed.eq_ignore_ascii_case(tag)),
+            _ => false,
+        }
+    }
+
+    /// The value as written in a Cabrillo file.
+    pub fn value(&self) -> String {
+        match self {
+            Header::CategoryOperator(v) => v.to_string(),
+            Header::CategoryAssisted(v) => v.to_string(),
+            Header::CategoryBand(v) => v.to_string(),
+            Header::CategoryMode(v) => v.to_string(),
+            Header::CategoryPower(v) => v.to_string(),
+            Header::CategoryStation(v) => v.to_string(),
+            Header::CategoryTransmitter(v) => v.to_string(),
+            Header::CategoryTime(v) => v.to_string(),
+            Header::CategoryOverlay(v) => v.to_string(),
+            Header::ClaimedScore(v) => v.to_string(),
+            Header::Callsign(v)
+            | Header::Contest(v)
+            | Header::Club(v)
+            | Header::CreatedBy(v)
+            | Header::Email(v)
+            | Header::GridLocator(v)
+            | Header::Location(v)
+            | Header::Name(v)
+            | Header::Address(v)
+            | Header::AddressCity(v)
+            | Header::AddressStateProvince(v)
+            | Header::AddressPostalcode(v)
+            | Header::AddressCountry(v)
+            | Header::Operators(v)
+            | Header::Soapbox(v)
+            | Header::Other { value: v, .. } => v.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.tag(), self.value())
+    }
+}
+
+/// Serialized form of a [`Header`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RawHeader {
+    tag: String,
+    value: String,
+}
+
+#[cfg(feature = "serde")]
+impl From<RawHeader> for Header {
+    fn from(raw: RawHeader) -> Self {
+        Header::parse(&raw.tag, &raw.value)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Header> for RawHeader {
+    fn from(header: Header) -> Self {
+        RawHeader {
+            tag: header.tag().to_string(),
+            value: header.value(),
+        }
+    }
+}
+
+/// Returns the first header matching a pattern, mapped to its value.
+macro_rules! find_header {
+    ($self:ident, $variant:ident) => {
+        $self.entries.iter().find_map(|h| match h {
+            Header::$variant(v) => Some(v),
+            _ => None,
+        })
+    };
+}
+
+/// All header lines of a log, in the order they appear in the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Headers {
+    entries: Vec<Header>,
+}
+
+impl Headers {
+    /// Create an empty header list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a header line, keeping any previous occurrences of the tag.
+    pub fn push(&mut self, header: Header) {
+        self.entries.push(header);
+    }
+
+    /// Set a single-valued tag.
+    ///
+    /// The first occurrence is replaced in place and any further occurrences are
+    /// removed. If the tag is not present it is appended.
+    pub fn set(&mut self, header: Header) {
+        let tag = header.tag().to_string();
+        match self.entries.iter().position(|h| h.tag() == tag) {
+            Some(index) => {
+                self.entries[index] = header;
+                let mut seen = 0;
+                self.entries.retain(|h| {
+                    if h.tag() != tag {
+                        return true;
+                    }
+                    seen += 1;
+                    seen == 1
+                });
+            }
+            None => self.push(header),
+        }
+    }
+
+    /// Remove every occurrence of a tag, returning how many lines were removed.
+    pub fn remove(&mut self, tag: &str) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|h| h.tag() != tag);
+        before - self.entries.len()
+    }
+
+    /// Value of the first occurrence of a tag.
+    pub fn get(&self, tag: &str) -> Option<String> {
+        self.entries
+            .iter()
+            .find(|h| h.tag() == tag)
+            .map(Header::value)
+    }
+
+    /// Values of every occurrence of a tag, in file order.
+    pub fn get_all(&self, tag: &str) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|h| h.tag() == tag)
+            .map(Header::value)
+            .collect()
+    }
+
+    /// Whether the tag appears at least once.
+    pub fn contains(&self, tag: &str) -> bool {
+        self.entries.iter().any(|h| h.tag() == tag)
+    }
+
+    /// Iterate over all header lines in file order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Header> {
+        self.entries.iter()
+    }
+
+    /// Number of header lines.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no header lines.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// `CALLSIGN:` value.
+    pub fn callsign(&self) -> Option<&str> {
+        find_header!(self, Callsign).map(String::as_str)
+    }
+
+    /// `CONTEST:` value.
+    pub fn contest(&self) -> Option<&str> {
+        find_header!(self, Contest).map(String::as_str)
+    }
+
+    /// `CATEGORY-OPERATOR:` value.
+    pub fn category_operator(&self) -> Option<CategoryOperator> {
+        find_header!(self, CategoryOperator).copied()
+    }
+
+    /// `CATEGORY-ASSISTED:` value.
+    pub fn category_assisted(&self) -> Option<CategoryAssisted> {
+        find_header!(self, CategoryAssisted).copied()
+    }
+
+    /// `CATEGORY-BAND:` value.
+    pub fn category_band(&self) -> Option<CategoryBand> {
+        find_header!(self, CategoryBand).copied()
+    }
+
+    /// `CATEGORY-MODE:` value.
+    pub fn category_mode(&self) -> Option<CategoryMode> {
+        find_header!(self, CategoryMode).copied()
+    }
+
+    /// `CATEGORY-POWER:` value.
+    pub fn category_power(&self) -> Option<CategoryPower> {
+        find_header!(self, CategoryPower).copied()
+    }
+
+    /// `CATEGORY-STATION:` value.
+    pub fn category_station(&self) -> Option<CategoryStation> {
+        find_header!(self, CategoryStation).copied()
+    }
+
+    /// `CATEGORY-TRANSMITTER:` value.
+    pub fn category_transmitter(&self) -> Option<CategoryTransmitter> {
+        find_header!(self, CategoryTransmitter).copied()
+    }
+
+    /// `CATEGORY-TIME:` value.
+    pub fn category_time(&self) -> Option<CategoryTime> {
+        find_header!(self, CategoryTime).copied()
+    }
+
+    /// `CATEGORY-OVERLAY:` value.
+    pub fn category_overlay(&self) -> Option<CategoryOverlay> {
+        find_header!(self, CategoryOverlay).copied()
+    }
+
+    /// `CLAIMED-SCORE:` value.
+    pub fn claimed_score(&self) -> Option<u64> {
+        find_header!(self, ClaimedScore).copied()
+    }
+
+    /// `GRID-LOCATOR:` value.
+    pub fn grid_locator(&self) -> Option<&str> {
+        find_header!(self, GridLocator).map(String::as_str)
+    }
+
+    /// `LOCATION:` value.
+    pub fn location(&self) -> Option<&str> {
+        find_header!(self, Location).map(String::as_str)
+    }
+
+    /// `CREATED-BY:` value.
+    pub fn created_by(&self) -> Option<&str> {
+        find_header!(self, CreatedBy).map(String::as_str)
+    }
+
+    /// All `ADDRESS:` lines, in file order.
+    pub fn address(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter_map(|h| match h {
+                Header::Address(v) => Some(v.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// `ADDRESS-CITY:` value.
+    pub fn address_city(&self) -> Option<&str> {
+        find_header!(self, AddressCity).map(String::as_str)
+    }
+
+    /// `ADDRESS-STATE-PROVINCE:` value.
+    pub fn address_state_province(&self) -> Option<&str> {
+        find_header!(self, AddressStateProvince).map(String::as_str)
+    }
+
+    /// `ADDRESS-POSTALCODE:` value.
+    pub fn address_postalcode(&self) -> Option<&str> {
+        find_header!(self, AddressPostalcode).map(String::as_str)
+    }
+
+    /// `ADDRESS-COUNTRY:` value.
+    pub fn address_country(&self) -> Option<&str> {
+        find_header!(self, AddressCountry).map(String::as_str)
+    }
+
+    /// Operator callsigns from all `OPERATORS:` lines.
+    ///
+    /// Both space and comma separated lists are accepted. A leading `@` (used to
+    /// mark the station host) is stripped.
+    pub fn operators(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter_map(|h| match h {
+                Header::Operators(v) => Some(v.as_str()),
+                _ => None,
+            })
+            .flat_map(|v| v.split(|c: char| c == ',' || c.is_whitespace()))
+            .map(|op| op.trim_start_matches('@'))
+            .filter(|op| !op.is_empty())
+            .collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+    type Item = &'a Header;
+    type IntoIter = std::slice::Iter<'a, Header>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Header> for Headers {
+    fn from_iter<I: IntoIterator<Item = Header>>(iter: I) -> Self {
+        Headers {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for Headers {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter()
+            .map(|(tag, value)| Header::parse(tag.as_ref(), value.as_ref()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_valued_tags_keep_order() {
+        let headers: Headers = [
+            ("SOAPBOX", "first"),
+            ("CALLSIGN", "N1MM"),
+            ("SOAPBOX", "second"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(headers.get("SOAPBOX").as_deref(), Some("first"));
+        assert_eq!(headers.get_all("SOAPBOX"), vec!["first", "second"]);
+        assert_eq!(headers.len(), 3);
+    }
+
+    #[test]
+    fn test_set_replaces_first_and_drops_rest() {
+        let mut headers: Headers = [("NAME", "A"), ("CALLSIGN", "N1MM"), ("NAME", "B")]
+            .into_iter()
+            .collect();
+        headers.set(Header::Name("C".to_string()));
+        let tags: Vec<_> = headers.iter().map(|h| h.to_string()).collect();
+        assert_eq!(tags, vec!["NAME: C", "CALLSIGN: N1MM"]);
+
+        headers.set(Header::Club("YCCC".to_string()));
+        assert_eq!(headers.iter().last().unwrap().tag(), "CLUB");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut headers: Headers = [("ADDRESS", "1 Main St"), ("ADDRESS", "Uxbridge")]
+            .into_iter()
+            .collect();
+        assert_eq!(headers.remove("ADDRESS"), 2);
+        assert!(headers.is_empty());
+    }
+
+    #[test]
+    fn test_typed_categories() {
+        let headers: Headers = [
+            ("CATEGORY-OPERATOR", "single-op"),
+            ("CATEGORY-BAND", "20M"),
+            ("CATEGORY-TIME", "12-HOURS"),
+            ("CATEGORY-OVERLAY", "TB-WIRES"),
+            ("CLAIMED-SCORE", "9447852"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            headers.category_operator(),
+            Some(CategoryOperator::SingleOp)
+        );
+        assert_eq!(headers.category_band(), Some(CategoryBand::B20M));
+        assert_eq!(headers.category_time(), Some(CategoryTime::Hours12));
+        assert_eq!(headers.category_overlay(), Some(CategoryOverlay::TbWires));
+        assert_eq!(headers.claimed_score(), Some(9447852));
+        assert_eq!(
+            headers.iter().next().unwrap().to_string(),
+            "CATEGORY-OPERATOR: SINGLE-OP"
+        );
+    }
+
+    #[test]
+    fn test_unknown_values_are_kept_raw() {
+        let bad_power = Header::parse("CATEGORY-POWER", "KILOWATT");
+        assert_eq!(
+            bad_power,
+            Header::Other {
+                tag: "CATEGORY-POWER".to_string(),
+                value: "KILOWATT".to_string()
+            }
+        );
+        assert_eq!(bad_power.to_string(), "CATEGORY-POWER: KILOWATT");
+        assert!(bad_power.has_invalid_value());
+        assert!(!Header::parse("X-CATEGORY", "ANY").has_invalid_value());
+        assert!(matches!(
+            Header::parse("X-INSTRUCTIONS", "see web"),
+            Header::Other { .. }
+        ));
+        assert!(matches!(
+            Header::parse("CLAIMED-SCORE", "007"),
+            Header::Other { .. }
+        ));
+    }
+
+    #[test]
+    fn test_operators_list() {
+        let headers: Headers = [("OPERATORS", "K6AW, W6OTC W0YK"), ("OPERATORS", "@K5ZD")]
+            .into_iter()
+            .collect();
+        assert_eq!(headers.operators(), vec!["K6AW", "W6OTC", "W0YK", "K5ZD"]);
+    }
+}
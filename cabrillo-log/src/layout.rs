@@ -0,0 +1,284 @@
+//! Column-aligned Cabrillo output.
+//!
+//! Sponsors publish a QSO template for each contest showing every exchange field in a column of
+//! its own, e.g. for the CQ WPX:
+//!
+//! ```text
+//! QSO:   freq  mo date       time call          rst exch   call          rst exch   t
+//! QSO:    7005 CW 2009-05-30 0002 AA1ZZZ        599 1      S50A          599 4
+//! X-QSO: 14005 CW 2009-05-30 0015 AA1ZZZ        599 2      EF8M          599 34
+//! ```
+//!
+//! [`CabrilloFormat`] lays out QSO lines by the widths of the contest template's
+//! [`FieldDef`](crate::FieldDef)s. Its output parses back to the same log and is
+//! written again byte for byte, so `fmt --check` is stable. `Display for
+//! CabrilloLog` uses the layout of [`CabrilloFormat::for_log`].
+
+use crate::{
+    CabrilloLog, ContestTemplate, ExchangeField, FieldDef, FieldKind, QSO, TemplateRegistry,
+};
+use std::io::{self, Write};
+
+/// Width of the callsign columns, from the Cabrillo 3.0 QSO template.
+const CALL_WIDTH: usize = 13;
+
+/// Header tags in the order of the Cabrillo 3.0 specification, used by
+/// [`HeaderOrder::Normalized`]. Unknown and `X-` tags follow in file order.
+pub(crate) const HEADER_ORDER: &[&str] = &[
+    "CALLSIGN",
+    "CONTEST",
+    "CATEGORY-OPERATOR",
+    "CATEGORY-ASSISTED",
+    "CATEGORY-BAND",
+    "CATEGORY-MODE",
+    "CATEGORY-POWER",
+    "CATEGORY-STATION",
+    "CATEGORY-TRANSMITTER",
+    "CATEGORY-TIME",
+    "CATEGORY-OVERLAY",
+    "CERTIFICATE",
+    "CLAIMED-SCORE",
+    "CLUB",
+    "CREATED-BY",
+    "EMAIL",
+    "GRID-LOCATOR",
+    "LOCATION",
+    "NAME",
+    "ADDRESS",
+    "ADDRESS-CITY",
+    "ADDRESS-STATE-PROVINCE",
+    "ADDRESS-POSTALCODE",
+    "ADDRESS-COUNTRY",
+    "OPERATORS",
+    "OFFTIME",
+    "SOAPBOX",
+];
+
+/// Line terminator of the written file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    /// `\r\n`, as expected by some sponsors' robots.
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Order of the header lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderOrder {
+    /// As they appear in the log.
+    #[default]
+    Original,
+    /// In specification order; repeated tags keep their relative order.
+    Normalized,
+}
+
+/// Layout options for writing a [`CabrilloLog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CabrilloFormat {
+    /// Exchange layout; without one each field is as wide as its kind needs.
+    pub template: Option<&'static ContestTemplate>,
+    pub line_ending: LineEnding,
+    pub header_order: HeaderOrder,
+    /// Write `END-OF-LOG: 3.0` rather than a bare `END-OF-LOG:`.
+    pub end_of_log_version: bool,
+}
+
+impl CabrilloFormat {
+    /// Default options with the template for the log's `CONTEST:` header,
+    /// keeping the version on `END-OF-LOG:` if the log had one.
+    pub fn for_log(log: &CabrilloLog, templates: &TemplateRegistry) -> Self {
+        CabrilloFormat {
+            template: log.headers.contest().and_then(|c| templates.lookup(c)),
+            end_of_log_version: log.end_of_log_version,
+            ..Self::default()
+        }
+    }
+
+    /// A QSO or X-QSO line without the line ending. The tag is padded so that
+    /// QSO and X-QSO lines have the same columns.
+    pub fn qso_line(&self, qso: &QSO) -> String {
+        let mut line = format!(
+            "{:<6} {:>5} {:<2} {} {} {:<call$}",
+            if qso.excluded { "X-QSO:" } else { "QSO:" },
+            qso.freq.to_string(),
+            qso.mode.to_string(),
+            qso.date.format("%Y-%m-%d"),
+            qso.time.format("%H%M"),
+            qso.sent_call,
+            call = CALL_WIDTH
+        );
+        let (sent, rcvd) = self
+            .template
+            .map_or((&[][..], &[][..]), |t| (t.sent, t.rcvd));
+        push_exchange(&mut line, &qso.sent_exch.fields, sent);
+        line.push_str(&format!(" {:<call$}", qso.rcvd_call, call = CALL_WIDTH));
+        push_exchange(&mut line, &qso.rcvd_exch.fields, rcvd);
+        if let Some(tx) = &qso.tx {
+            line.push_str(&format!(" {}", tx));
+        }
+        line.truncate(line.trim_end().len());
+        line
+    }
+
+    /// Write the whole log.
+    ///
+    /// Comments stay in front of the line they preceded; with
+    /// [`HeaderOrder::Normalized`] that is counted in the reordered header.
+    pub fn write<W: Write>(&self, log: &CabrilloLog, mut writer: W) -> io::Result<()> {
+        let eol = self.line_ending.as_str();
+        let mut headers: Vec<_> = log.headers.iter().collect();
+        if self.header_order == HeaderOrder::Normalized {
+            headers.sort_by_key(|h| {
+                HEADER_ORDER
+                    .iter()
+                    .position(|tag| tag.eq_ignore_ascii_case(h.tag()))
+                    .unwrap_or(HEADER_ORDER.len())
+            });
+        }
+        let lines = headers
+            .iter()
+            .map(|h| h.to_string())
+            .chain(log.qsos.iter().map(|q| self.qso_line(q)));
+
+        let mut comments = log.comments.iter().peekable();
+        write!(writer, "START-OF-LOG: 3.0{}", eol)?;
+        for (position, line) in lines.enumerate() {
+            while let Some(comment) = comments.next_if(|c| c.position <= position) {
+                write!(writer, "#{}{}", comment.text, eol)?;
+            }
+            write!(writer, "{}{}", line, eol)?;
+        }
+        for comment in comments {
+            write!(writer, "#{}{}", comment.text, eol)?;
+        }
+        if self.end_of_log_version {
+            write!(writer, "END-OF-LOG: 3.0{}", eol)
+        } else {
+            write!(writer, "END-OF-LOG:{}", eol)
+        }
+    }
+
+    /// The whole log as a string.
+    pub fn format(&self, log: &CabrilloLog) -> String {
+        let mut out = Vec::new();
+        self.write(log, &mut out)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("log text is UTF-8")
+    }
+}
+
+/// Append exchange fields in columns as wide as the template's field definitions.
+fn push_exchange(line: &mut String, fields: &[ExchangeField], defs: &[FieldDef]) {
+    for (i, field) in fields.iter().enumerate() {
+        let width = defs
+            .get(i)
+            .map_or_else(|| default_width(field.kind), |def| def.width);
+        line.push_str(&format!(" {:<width$}", field.value));
+    }
+}
+
+/// Column width of an exchange field not described by a template.
+fn default_width(kind: FieldKind) -> usize {
+    match kind {
+        FieldKind::Rst => 3,
+        FieldKind::Precedence => 1,
+        FieldKind::Zone | FieldKind::Check => 2,
+        FieldKind::Class | FieldKind::Section => 3,
+        FieldKind::Grid | FieldKind::Serial | FieldKind::Text => 6,
+        FieldKind::Name => 10,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WPX: &str = "START-OF-LOG: 3.0
+CONTEST: CQ-WPX-CW
+CALLSIGN: AA1ZZZ
+# first QSO
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4
+X-QSO: 14005 CW 2009-05-30 0015 AA1ZZZ 599 2 EF8M 599 34 1
+END-OF-LOG:
+";
+
+    #[test]
+    fn test_template_columns() {
+        let log = CabrilloLog::parse(WPX).unwrap();
+        let format = CabrilloFormat::for_log(&log, &TemplateRegistry::default());
+        assert_eq!(
+            format.qso_line(&log.qsos[0]),
+            "QSO:    7005 CW 2009-05-30 0002 AA1ZZZ        599 1      S50A          599 4"
+        );
+        assert_eq!(
+            format.qso_line(&log.qsos[1]),
+            "X-QSO: 14005 CW 2009-05-30 0015 AA1ZZZ        599 2      EF8M          599 34     1"
+        );
+
+        let fd = CabrilloLog::parse(
+            "START-OF-LOG: 3.0\nCONTEST: ARRL-FD\nQSO: 14000 CW 2023-06-24 1800 W1AW 3A CT K1ABC 12F EMA\nEND-OF-LOG:\n",
+        )
+        .unwrap();
+        let format = CabrilloFormat::for_log(&fd, &TemplateRegistry::default());
+        assert_eq!(
+            format.qso_line(&fd.qsos[0]),
+            "QSO:   14000 CW 2023-06-24 1800 W1AW          3A  CT    K1ABC         12F EMA"
+        );
+    }
+
+    #[test]
+    fn test_reemit_is_byte_identical() {
+        let log = CabrilloLog::parse(WPX).unwrap();
+        for line_ending in [LineEnding::Lf, LineEnding::CrLf] {
+            let format = CabrilloFormat {
+                line_ending,
+                end_of_log_version: true,
+                ..CabrilloFormat::for_log(&log, &TemplateRegistry::default())
+            };
+            let written = format.format(&log);
+            let reparsed = CabrilloLog::parse(&written).unwrap();
+            assert_eq!(
+                reparsed,
+                CabrilloLog {
+                    end_of_log_version: true,
+                    ..log.clone()
+                }
+            );
+            assert_eq!(format.format(&reparsed), written);
+        }
+        let crlf = CabrilloFormat {
+            line_ending: LineEnding::CrLf,
+            end_of_log_version: true,
+            ..CabrilloFormat::default()
+        }
+        .format(&log);
+        assert!(crlf.starts_with("START-OF-LOG: 3.0\r\nCONTEST: CQ-WPX-CW\r\n"));
+        assert!(crlf.ends_with("\r\nEND-OF-LOG: 3.0\r\n"));
+    }
+
+    #[test]
+    fn test_normalized_header_order() {
+        let log = CabrilloLog::parse(
+            "START-OF-LOG: 3.0\nSOAPBOX: one\nX-CLUB-ID: 7\nCALLSIGN: N1MM\nSOAPBOX: two\nCONTEST: ARRL-10\nEND-OF-LOG:\n",
+        )
+        .unwrap();
+        let format = CabrilloFormat {
+            header_order: HeaderOrder::Normalized,
+            ..CabrilloFormat::default()
+        };
+        assert_eq!(
+            format.format(&log),
+            "START-OF-LOG: 3.0\nCALLSIGN: N1MM\nCONTEST: ARRL-10\nSOAPBOX: one\nSOAPBOX: two\nX-CLUB-ID: 7\nEND-OF-LOG:\n"
+        );
+    }
+}
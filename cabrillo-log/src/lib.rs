@@ -5,9 +5,24 @@
 //!
 //! ## Features
 //! - Parse Cabrillo files into structured data
-//! - Generate Cabrillo files from data structures
-//! - Validate log entries
+//! - Generate Cabrillo files from data structures, or build them with [`CabrilloLogBuilder`]
+//! - Write QSO columns aligned by the contest's QSO template with [`CabrilloFormat`]
+//! - Validate log entries, and QSO times against the contest period
+//! - Cross-check the header categories with the QSOs
+//! - Find dupes by the contest's dupe rules
+//! - Compute QSO points, multipliers and the claimed score with pluggable [`ContestRules`]
+//! - Check sent serial numbers for gaps, repeats and out-of-order numbers
+//! - Merge the logs of several computers into one multi-transmitter log
+//! - Split a log by band, mode, transmitter or date into valid sub-logs
+//! - Compare two versions of a log QSO by QSO
+//! - Cross-check the logs of several stations and report uniques, busted calls and NILs
+//! - Import and export ADIF (`.adi` and `.adx`)
+//! - Report every parse problem with its line and column
+//! - Repair common formatting problems in hand-made logs, with an audit of every change
+//! - Stream large logs line by line with [`CabrilloReader`] and [`CabrilloWriter`]
 //! - Support for various contest types
+//! - Read Cabrillo 2.0 logs and upgrade them to 3.0
+//! - Optional `serde` support for storing logs as JSON, TOML, MessagePack, ...
 //! - Error handling for malformed files
 //!
 //! ## Example
@@ -19,63 +34,152 @@
 //! ```
 
 use chrono::{NaiveDate, NaiveTime};
-use std::collections::HashMap;
 use std::fmt;
+use std::ops::Range;
+
+/// Serializes a type as its `Display` string and parses it back with `FromStr`.
+macro_rules! serde_as_str {
+    ($name:ident) => {
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+                s.parse().map_err(|_| {
+                    serde::de::Error::custom(format!("invalid {}: {}", stringify!($name), s))
+                })
+            }
+        }
+    };
+}
+
+pub mod adif;
+pub mod builder;
+pub mod callsign;
+pub mod consistency;
+pub mod contest;
+pub mod crosscheck;
+pub mod diagnostic;
+pub mod diff;
+pub mod dupe;
+pub mod exchange;
+pub mod frequency;
+pub mod header;
+pub mod layout;
+pub mod merge;
+pub mod mode;
+pub mod repair;
+pub mod scoring;
+pub mod serial;
+pub mod split;
+pub mod stream;
+pub mod timing;
+pub mod version;
+
+pub use adif::{Adif, AdifRecord};
+pub use builder::{CabrilloLogBuilder, QsoBuilder, Station};
+pub use callsign::{Callsign, Suffix};
+pub use consistency::{ConsistencyIssue, ConsistencyIssueKind};
+pub use contest::{ContestTemplate, FieldDef, TemplateRegistry};
+pub use crosscheck::{CheckResult, CrossCheckOptions, QsoCheck, UbnReport};
+pub use diagnostic::{Diagnostic, ParseReport, Severity};
+pub use diff::{FieldChange, HeaderChange, LogDiff, QsoChange};
+pub use dupe::{Dupe, DupeAction, DupeScope};
+pub use exchange::{Exchange, ExchangeField, FieldKind};
+pub use frequency::{Band, Frequency};
+pub use header::{
+    CategoryAssisted, CategoryBand, CategoryMode, CategoryOperator, CategoryOverlay, CategoryPower,
+    CategoryStation, CategoryTime, CategoryTransmitter, Header, Headers,
+};
+pub use layout::{CabrilloFormat, HeaderOrder, LineEnding};
+pub use merge::{MergeIssue, MergeOptions, MergeReport, QsoSource};
+pub use mode::{CabrilloMode, Mode, ModeDef};
+pub use repair::{Repair, RepairKind, RepairReport};
+pub use scoring::{
+    BandScore, ContestRules, CqWpx, CqWw, Multiplier, QsoContext, QsoScore, ScoreReport,
+    StationInfo, StationLookup, rules_for_contest,
+};
+pub use serial::{SerialIssue, SerialIssueKind, SerialSequence};
+pub use split::{SplitKey, SplitPart};
+pub use stream::{CabrilloReader, CabrilloWriter};
+pub use timing::{ContestPeriod, TimingIssue, TimingIssueKind, TimingRules};
+pub use version::CabrilloVersion;
 
 /// Represents a Cabrillo log file, containing headers and QSOs.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CabrilloLog {
-    pub headers: HashMap<String, String>,
-    pub qsos: Vec<QSO>,
+    pub headers: Headers, // Header lines in file order
+    pub qsos: Vec<QSO>,   // QSO and X-QSO lines in file order
+    pub comments: Vec<Comment>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub end_of_log_version: bool, // END-OF-LOG: carried a version, e.g. `END-OF-LOG: 3.0`
 }
 
 /// Represents a single QSO (contact) in the log.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QSO {
-    pub freq: String, // Frequency or band
-    pub mode: String, // Mode like CW, PH
+    pub freq: Frequency, // kHz or band designator
+    pub mode: Mode,      // Cabrillo mode category and precise mode, if known
     pub date: NaiveDate,
     pub time: NaiveTime,
     pub sent_call: String,
-    pub sent_rst_exch: String, // Combined RST and EXCH
+    pub sent_exch: Exchange, // RST and exchange fields sent
     pub rcvd_call: String,
-    pub rcvd_rst_exch: String, // Combined RST and EXCH
-    pub tx: Option<String>,    // Transmitter ID, 0 or 1, optional
+    pub rcvd_exch: Exchange, // RST and exchange fields received
+    pub tx: Option<String>,  // Transmitter ID, 0 or 1, optional
+    pub excluded: bool,      // X-QSO: logged but not counted for score
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub zero_point: bool, // Counted but worth no points, e.g. a dupe; not written to Cabrillo
+}
+
+/// A `#` comment line and where it appeared in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comment {
+    /// Number of header and QSO lines preceding the comment.
+    pub position: usize,
+    /// Text after the `#`.
+    pub text: String,
+}
+
+impl CabrilloLog {
+    /// QSOs that count for score, i.e. everything except X-QSO lines.
+    pub fn counted_qsos(&self) -> impl Iterator<Item = &QSO> {
+        self.qsos.iter().filter(|qso| !qso.excluded)
+    }
+
+    /// QSOs logged as X-QSO.
+    pub fn excluded_qsos(&self) -> impl Iterator<Item = &QSO> {
+        self.qsos.iter().filter(|qso| qso.excluded)
+    }
 }
 
+impl fmt::Display for QSO {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&CabrilloFormat::default().qso_line(self))
+    }
+}
+
+/// Writes the log as [`CabrilloFormat::for_log`] lays it out with the built-in
+/// contest templates.
 impl fmt::Display for CabrilloLog {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "START-OF-LOG: 3.0")?;
-        let mut keys: Vec<_> = self.headers.keys().collect();
-        keys.sort();
-        for key in keys {
-            writeln!(f, "{}: {}", key, self.headers[key])?;
-        }
-        for qso in &self.qsos {
-            write!(
-                f,
-                "QSO: {} {} {} {} {:<13} {:<8} {:<13} {:<8}",
-                qso.freq,
-                qso.mode,
-                qso.date.format("%Y-%m-%d"),
-                qso.time.format("%H%M"),
-                qso.sent_call,
-                qso.sent_rst_exch,
-                qso.rcvd_call,
-                qso.rcvd_rst_exch
-            )?;
-            if let Some(tx) = &qso.tx {
-                write!(f, " {}", tx)?;
-            }
-            writeln!(f)?;
-        }
-        writeln!(f, "END-OF-LOG:")?;
-        Ok(())
+        let format = CabrilloFormat::for_log(self, &TemplateRegistry::default());
+        f.write_str(&format.format(self))
     }
 }
 
 /// Errors that can occur during parsing or validation.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CabrilloError {
     InvalidFormat(String),
     MissingRequiredField(String),
@@ -102,41 +206,133 @@ impl fmt::Display for CabrilloError {
 
 impl std::error::Error for CabrilloError {}
 
+/// Sent call, sent exchange, received call, received exchange and TX ID.
+type QsoColumns = (String, Exchange, String, Exchange, Option<String>);
+
 impl CabrilloLog {
     /// Parse a Cabrillo log from a string.
+    ///
+    /// QSO exchanges are split according to the built-in contest template for
+    /// the `CONTEST:` header, see [`CabrilloLog::parse_with_templates`].
     pub fn parse(content: &str) -> Result<Self, CabrilloError> {
-        let mut headers = HashMap::new();
+        Self::parse_with_templates(content, &TemplateRegistry::default())
+    }
+
+    /// Parse a Cabrillo log from a string using the given contest templates.
+    ///
+    /// If no template matches the `CONTEST:` header, the received callsign is
+    /// located heuristically and exchange fields are left untyped.
+    pub fn parse_with_templates(
+        content: &str,
+        templates: &TemplateRegistry,
+    ) -> Result<Self, CabrilloError> {
+        let report = Self::parse_with_diagnostics(content, templates);
+        match report
+            .diagnostics
+            .into_iter()
+            .find(|d| d.severity == Severity::Error)
+        {
+            Some(diagnostic) => Err(diagnostic.kind),
+            None => Ok(report.log),
+        }
+    }
+
+    /// Parse a Cabrillo log without stopping at the first bad line.
+    ///
+    /// Cabrillo 2.0 headers are upgraded to 3.0, see [`version`].
+    ///
+    /// Lines that cannot be parsed are left out of the log and reported as
+    /// errors; suspicious lines that are skipped or kept verbatim are reported
+    /// as warnings.
+    pub fn parse_with_diagnostics(content: &str, templates: &TemplateRegistry) -> ParseReport {
+        let mut headers = Headers::new();
+        let mut template = None;
         let mut qsos = Vec::new();
+        let mut comments = Vec::new();
+        let mut diagnostics = Vec::new();
         let mut in_header = true;
-
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue; // Skip empty lines and comments
+        let mut version = CabrilloVersion::V3;
+        let mut end_of_log_version = false;
+
+        for (index, raw) in content.lines().enumerate() {
+            let line_no = index + 1;
+            let line = raw.trim();
+            let whole_line = span_of(raw, line);
+            if line.is_empty() {
+                continue;
             }
-            if line.starts_with("START-OF-LOG:") || line.starts_with("END-OF-LOG:") {
+            if let Some(text) = line.strip_prefix('#') {
+                comments.push(Comment {
+                    position: headers.len() + qsos.len(),
+                    text: text.to_string(),
+                });
                 continue;
-            } else if in_header {
-                if line.starts_with("QSO:") {
-                    in_header = false;
-                    let qso = Self::parse_qso_line(line)?;
-                    qsos.push(qso);
-                } else if line.starts_with("X-QSO:") {
+            }
+            if let Some(value) = line.strip_prefix("START-OF-LOG:") {
+                version = CabrilloVersion::from_start_of_log(value);
+                continue;
+            } else if let Some(value) = line.strip_prefix("END-OF-LOG:") {
+                end_of_log_version = !value.trim().is_empty();
+                continue;
+            } else if line.starts_with("QSO:") || line.starts_with("X-QSO:") {
+                if in_header {
                     in_header = false;
-                    // Ignore X-QSO
-                } else if let Some((key, value)) = line.split_once(':') {
-                    headers.insert(key.trim().to_string(), value.trim().to_string());
+                    template = headers.contest().and_then(|c| templates.lookup(c));
                 }
-            } else if line.starts_with("QSO:") {
-                let qso = Self::parse_qso_line(line)?;
-                qsos.push(qso);
-            } else if line.starts_with("X-QSO:") {
-                // Ignore X-QSO lines as per spec
-                continue;
+                match Self::parse_qso_line(raw, template) {
+                    Ok(qso) => qsos.push(qso),
+                    // X-QSO lines do not count, so a broken one does not make the log unusable
+                    Err((kind, columns)) if line.starts_with("X-QSO:") => {
+                        diagnostics.push(Diagnostic::warning(line_no, columns, kind))
+                    }
+                    Err((kind, columns)) => {
+                        diagnostics.push(Diagnostic::error(line_no, columns, kind))
+                    }
+                }
+            } else if let Some((key, value)) = line.split_once(':') {
+                if !in_header {
+                    diagnostics.push(Diagnostic::warning(
+                        line_no,
+                        whole_line,
+                        CabrilloError::InvalidFormat(format!(
+                            "Header {} after QSO lines is ignored",
+                            key.trim()
+                        )),
+                    ));
+                    continue;
+                }
+                let header = Header::parse(key, value);
+                if header.has_invalid_value() {
+                    diagnostics.push(Diagnostic::warning(
+                        line_no,
+                        span_of(raw, value.trim()),
+                        CabrilloError::InvalidFormat(format!(
+                            "Unknown {} value: {}",
+                            header.tag(),
+                            header.value()
+                        )),
+                    ));
+                }
+                headers.push(header);
+            } else {
+                diagnostics.push(Diagnostic::warning(
+                    line_no,
+                    whole_line,
+                    CabrilloError::InvalidFormat("Unrecognized line is ignored".to_string()),
+                ));
             }
         }
 
-        Ok(CabrilloLog { headers, qsos })
+        let mut log = CabrilloLog {
+            headers,
+            qsos,
+            comments,
+            end_of_log_version,
+        };
+        if version == CabrilloVersion::V2 {
+            log.upgrade_from_v2();
+        }
+        ParseReport { log, diagnostics }
     }
 
     /// Parse a Cabrillo log from a file.
@@ -146,79 +342,187 @@ impl CabrilloLog {
         Self::parse(&content)
     }
 
-    /// Parse a single QSO line.
-    fn parse_qso_line(line: &str) -> Result<QSO, CabrilloError> {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 10 || parts[0] != "QSO:" {
-            return Err(CabrilloError::InvalidFormat("Invalid QSO line".to_string()));
+    /// Parse a single QSO line, splitting the exchange by `template` if known.
+    ///
+    /// On failure, returns the error and the byte range of the offending part of
+    /// the line.
+    fn parse_qso_line(
+        line: &str,
+        template: Option<&ContestTemplate>,
+    ) -> Result<QSO, (CabrilloError, Range<usize>)> {
+        let tokens = tokenize(line);
+        let parts: Vec<&str> = tokens.iter().map(|(_, token)| *token).collect();
+        let columns = |range: Range<usize>| {
+            let start = tokens.get(range.start).map_or(line.len(), |(at, _)| *at);
+            let end = range
+                .end
+                .checked_sub(1)
+                .and_then(|last| tokens.get(last))
+                .map_or(start, |(at, token)| at + token.len());
+            start..end.max(start)
+        };
+        let excluded = parts.first() == Some(&"X-QSO:");
+        if parts.len() < 8 || (parts[0] != "QSO:" && !excluded) {
+            return Err((
+                CabrilloError::InvalidFormat("Invalid QSO line".to_string()),
+                columns(0..parts.len()),
+            ));
+        }
+
+        let freq = parts[1].parse().map_err(|_| {
+            (
+                CabrilloError::InvalidFormat(format!("Invalid frequency: {}", parts[1])),
+                columns(1..2),
+            )
+        })?;
+        let mode = parts[2].parse().map_err(|_| {
+            (
+                CabrilloError::InvalidFormat(format!("Invalid mode: {}", parts[2])),
+                columns(2..3),
+            )
+        })?;
+        let date = NaiveDate::parse_from_str(parts[3], "%Y-%m-%d").map_err(|_| {
+            (
+                CabrilloError::InvalidDate(parts[3].to_string()),
+                columns(3..4),
+            )
+        })?;
+        let time = NaiveTime::parse_from_str(parts[4], "%H%M").map_err(|_| {
+            (
+                CabrilloError::InvalidTime(parts[4].to_string()),
+                columns(4..5),
+            )
+        })?;
+
+        let (sent_call, sent_exch, rcvd_call, rcvd_exch, tx) = match template {
+            Some(template) => Self::split_by_template(&parts[5..], template),
+            None => Self::split_heuristically(&parts[5..]),
         }
+        .map_err(|(kind, range)| (kind, columns(range.start + 5..range.end + 5)))?;
+
+        Ok(QSO {
+            freq,
+            mode,
+            date,
+            time,
+            sent_call,
+            sent_exch,
+            rcvd_call,
+            rcvd_exch,
+            tx,
+            excluded,
+            zero_point: false,
+        })
+    }
+
+    /// Split the call/exchange columns of a QSO line positionally.
+    ///
+    /// Errors carry the range of offending tokens within `parts`.
+    fn split_by_template(
+        parts: &[&str],
+        template: &ContestTemplate,
+    ) -> Result<QsoColumns, (CabrilloError, Range<usize>)> {
+        let expected = template.token_count();
+        if parts.len() != expected && parts.len() != expected + 1 {
+            return Err((
+                CabrilloError::InvalidFormat(format!(
+                    "Expected {} call and exchange fields for {}, found {}",
+                    expected,
+                    template.contests[0],
+                    parts.len()
+                )),
+                0..parts.len(),
+            ));
+        }
+
+        let exchange = |defs: &[FieldDef], tokens: &[&str]| {
+            Exchange::new(
+                defs.iter()
+                    .zip(tokens)
+                    .map(|(def, token)| ExchangeField::new(def.name, def.kind, *token))
+                    .collect(),
+            )
+        };
+        let rcvd_start = 1 + template.sent.len();
+        let rcvd_end = rcvd_start + 1 + template.rcvd.len();
+
+        Ok((
+            parts[0].to_string(),
+            exchange(template.sent, &parts[1..rcvd_start]),
+            parts[rcvd_start].to_string(),
+            exchange(template.rcvd, &parts[rcvd_start + 1..rcvd_end]),
+            parts.get(expected).map(|tx| tx.to_string()),
+        ))
+    }
 
-        let freq = parts[1].to_string();
-        let mode = parts[2].to_string();
-        let date = NaiveDate::parse_from_str(parts[3], "%Y-%m-%d")
-            .map_err(|_| CabrilloError::InvalidDate(parts[3].to_string()))?;
-        let time = NaiveTime::parse_from_str(parts[4], "%H%M")
-            .map_err(|_| CabrilloError::InvalidTime(parts[4].to_string()))?;
-        let sent_call = parts[5].to_string();
+    /// Split the call/exchange columns of a QSO line without a template.
+    ///
+    /// Errors carry the range of offending tokens within `parts`.
+    fn split_heuristically(parts: &[&str]) -> Result<QsoColumns, (CabrilloError, Range<usize>)> {
+        let sent_call = parts[0].to_string();
 
         // Find the received callsign (first valid callsign after sent_call)
-        let mut rcvd_call_index = 6;
+        let mut rcvd_call_index = 1;
         while rcvd_call_index < parts.len() && !is_valid_callsign(parts[rcvd_call_index]) {
             rcvd_call_index += 1;
         }
         if rcvd_call_index >= parts.len() {
-            return Err(CabrilloError::InvalidFormat(
-                "No valid received callsign found".to_string(),
+            return Err((
+                CabrilloError::InvalidFormat("No valid received callsign found".to_string()),
+                1..parts.len(),
             ));
         }
         let rcvd_call = parts[rcvd_call_index].to_string();
-
-        let sent_rst_exch = if rcvd_call_index > 6 {
-            parts[6..rcvd_call_index].join(" ")
-        } else {
-            "".to_string()
-        };
+        let sent_exch = Exchange::from_untyped(parts[1..rcvd_call_index].iter().copied());
 
         // Now, parse the received part
         let rcvd_start = rcvd_call_index + 1;
         if rcvd_start >= parts.len() {
-            return Err(CabrilloError::InvalidFormat(
-                "Missing received RST/EXCH".to_string(),
+            return Err((
+                CabrilloError::InvalidFormat("Missing received RST/EXCH".to_string()),
+                rcvd_call_index..rcvd_start,
             ));
         }
 
-        let tx: Option<String>;
-        let rcvd_rst_exch: String;
-
-        if parts[parts.len() - 1] == "0" || parts[parts.len() - 1] == "1" {
-            tx = Some(parts[parts.len() - 1].to_string());
-            rcvd_rst_exch = if rcvd_start < parts.len() - 1 {
-                parts[rcvd_start..parts.len() - 1].join(" ")
-            } else {
-                "".to_string()
-            };
+        let last = parts[parts.len() - 1];
+        let (rcvd_end, tx) = if last == "0" || last == "1" {
+            (parts.len() - 1, Some(last.to_string()))
         } else {
-            tx = None;
-            rcvd_rst_exch = parts[rcvd_start..].join(" ");
-        }
+            (parts.len(), None)
+        };
+        let rcvd_exch = Exchange::from_untyped(parts[rcvd_start..rcvd_end].iter().copied());
 
-        Ok(QSO {
-            freq,
-            mode,
-            date,
-            time,
-            sent_call,
-            sent_rst_exch,
-            rcvd_call,
-            rcvd_rst_exch,
-            tx,
-        })
+        Ok((sent_call, sent_exch, rcvd_call, rcvd_exch, tx))
     }
 
     /// Validate the log.
+    ///
+    /// QSOs must also be in a mode allowed by the built-in template for the
+    /// `CONTEST:` header, if there is one.
     pub fn validate(&self) -> Result<(), CabrilloError> {
+        self.validate_with_templates(&TemplateRegistry::default())
+    }
+
+    /// Validate the log, checking QSO modes against the given contest templates.
+    pub fn validate_with_templates(
+        &self,
+        templates: &TemplateRegistry,
+    ) -> Result<(), CabrilloError> {
+        let template = self
+            .headers
+            .contest()
+            .and_then(|contest| templates.lookup(contest));
         for qso in &self.qsos {
             Self::validate_qso(qso)?;
+            if let Some(template) = template
+                && !template.allows_mode(qso.mode.category)
+            {
+                return Err(CabrilloError::InvalidFormat(format!(
+                    "Mode {} not allowed in {}",
+                    qso.mode.precise_or_category(),
+                    self.headers.contest().unwrap_or_default()
+                )));
+            }
         }
 
         Ok(())
@@ -232,17 +536,19 @@ impl CabrilloLog {
         if qso.rcvd_call.is_empty() || !is_valid_callsign(&qso.rcvd_call) {
             return Err(CabrilloError::InvalidCallsign(qso.rcvd_call.clone()));
         }
-        if !is_valid_band(&qso.freq) {
+        if qso.freq.band().is_none() {
             return Err(CabrilloError::InvalidFormat(format!(
                 "Invalid band/freq: {}",
                 qso.freq
             )));
         }
-        if !is_valid_mode(&qso.mode) {
-            return Err(CabrilloError::InvalidFormat(format!(
-                "Invalid mode: {}",
-                qso.mode
-            )));
+        for field in qso.sent_exch.fields.iter().chain(&qso.rcvd_exch.fields) {
+            if !field.is_valid() {
+                return Err(CabrilloError::InvalidFormat(format!(
+                    "Invalid {}: {}",
+                    field.name, field.value
+                )));
+            }
         }
         if let Some(tx) = &qso.tx
             && tx != "0"
@@ -257,27 +563,22 @@ impl CabrilloLog {
     }
 }
 
-/// Check if a string is a valid amateur radio callsign (basic check).
-fn is_valid_callsign(call: &str) -> bool {
-    let is_ascii = call.is_ascii();
-    let has_digits = call.chars().any(|c| c.is_ascii_digit());
-    let has_letters = call.chars().any(|c| c.is_ascii_alphabetic());
-    is_ascii && has_digits && has_letters && call.len() > 2
+/// Split a line into whitespace-separated tokens with their byte offsets.
+fn tokenize(line: &str) -> Vec<(usize, &str)> {
+    line.split_whitespace()
+        .map(|token| (span_of(line, token).start, token))
+        .collect()
 }
 
-/// Check if a string is a valid band or frequency.
-fn is_valid_band(band: &str) -> bool {
-    let valid_bands = [
-        "160", "80", "40", "20", "15", "10", "6", "2", "222", "432", "902", "1.2G", "2.3G", "3.4G",
-        "5.7G", "10G", "24G", "47G", "75G", "122G", "134G", "241G", "LIGHT", "50", "70", "144",
-    ];
-    valid_bands.contains(&band) || band.parse::<f64>().is_ok() // Allow frequencies like 14000
+/// Byte range of `part` within `line`; `part` must be a subslice of `line`.
+fn span_of(line: &str, part: &str) -> Range<usize> {
+    let start = part.as_ptr() as usize - line.as_ptr() as usize;
+    start..start + part.len()
 }
 
-/// Check if a string is a valid mode.
-fn is_valid_mode(mode: &str) -> bool {
-    let valid_modes = ["CW", "PH", "FM", "RY", "DG"];
-    valid_modes.contains(&mode)
+/// Check if a string is a structurally valid amateur radio callsign.
+fn is_valid_callsign(call: &str) -> bool {
+    call.parse::<Callsign>().is_ok()
 }
 
 #[cfg(test)]
@@ -289,21 +590,136 @@ mod tests {
         let content = "START-OF-LOG: 3.0\nCALLSIGN: N1MM\nQSO: 14000 CW 2023-10-01 1200 N1MM 599 001 W1AW 599 001 0\nEND-OF-LOG: 3.0\n";
         let log = CabrilloLog::parse(content).unwrap();
         assert_eq!(log.headers.get("CALLSIGN").unwrap(), "N1MM");
+        assert_eq!(log.headers.callsign(), Some("N1MM"));
         assert_eq!(log.qsos.len(), 1);
-        assert_eq!(log.qsos[0].freq, "14000");
-        assert_eq!(log.qsos[0].mode, "CW");
+        assert_eq!(log.qsos[0].freq, Frequency::Khz(14000.0));
+        assert_eq!(log.qsos[0].mode, CabrilloMode::Cw);
         assert_eq!(log.qsos[0].tx, Some("0".to_string()));
     }
 
+    #[test]
+    fn test_repeated_headers_round_trip() {
+        let content = "START-OF-LOG: 3.0\nCALLSIGN: AA1ZZZ\nSOAPBOX: Put your comments here.\nADDRESS: 1 Main St\nSOAPBOX: Use multiple lines if needed.\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4\nEND-OF-LOG:\n";
+        let log = CabrilloLog::parse(content).unwrap();
+        assert_eq!(
+            log.headers.get_all("SOAPBOX"),
+            vec!["Put your comments here.", "Use multiple lines if needed."]
+        );
+        let output = log.to_string();
+        let header_lines: Vec<_> = output.lines().skip(1).take(4).collect();
+        assert_eq!(
+            header_lines,
+            vec![
+                "CALLSIGN: AA1ZZZ",
+                "SOAPBOX: Put your comments here.",
+                "ADDRESS: 1 Main St",
+                "SOAPBOX: Use multiple lines if needed.",
+            ]
+        );
+        assert_eq!(CabrilloLog::parse(&output).unwrap(), log);
+    }
+
+    #[test]
+    fn test_parse_typed_headers() {
+        let content = "START-OF-LOG: 3.0\nCALLSIGN: AA1ZZZ\nCATEGORY-MODE: CW\nCATEGORY-POWER: HIGH\nX-CLUB-ID: 42\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4\nEND-OF-LOG:\n";
+        let log = CabrilloLog::parse(content).unwrap();
+        assert_eq!(log.headers.category_mode(), Some(CategoryMode::Cw));
+        assert_eq!(log.headers.category_power(), Some(CategoryPower::High));
+        assert_eq!(log.headers.get("X-CLUB-ID").unwrap(), "42");
+        assert!(
+            log.to_string()
+                .contains("CATEGORY-POWER: HIGH\nX-CLUB-ID: 42\n")
+        );
+    }
+
+    #[test]
+    fn test_x_qso_and_comments_round_trip() {
+        let content = "START-OF-LOG: 3.0
+# exported by hand
+CALLSIGN: N1MM
+QSO:   14000 CW 2023-10-01 1200 N1MM          599 001    W1AW          599 001
+# dupe, kept for the checker
+X-QSO: 14000 CW 2023-10-01 1201 N1MM          599 002    W1AW          599 002
+QSO:    7000 CW 2023-10-01 1300 N1MM          599 003    K1ZZ          599 003
+#end
+END-OF-LOG:
+";
+        let log = CabrilloLog::parse(content).unwrap();
+        assert_eq!(log.qsos.len(), 3);
+        assert!(log.qsos[1].excluded);
+        assert_eq!(log.counted_qsos().count(), 2);
+        assert_eq!(log.excluded_qsos().count(), 1);
+        assert_eq!(log.comments.len(), 3);
+        assert_eq!(log.comments[1].text, " dupe, kept for the checker");
+        assert_eq!(log.to_string(), content);
+    }
+
+    #[test]
+    fn test_display_keeps_formatted_log() {
+        let content = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+QSO:    7005 CW 2009-05-30 0002 AA1ZZZ        599 1      S50A          599 4
+X-QSO: 14005 CW 2009-05-30 0015 AA1ZZZ        599 2      EF8M          599 34
+END-OF-LOG: 3.0
+";
+        let log = CabrilloLog::parse(content).unwrap();
+        assert!(log.end_of_log_version);
+        assert_eq!(log.to_string(), content);
+
+        let bare = content.replace("END-OF-LOG: 3.0", "END-OF-LOG:");
+        assert_eq!(CabrilloLog::parse(&bare).unwrap().to_string(), bare);
+    }
+
     #[test]
     fn test_parse_multi_exchange() {
         let content = "START-OF-LOG: 3.0\nQSO: 14042 CW 2023-10-01 0101 N5KO 1211 B 74 SCV VE3/KA5WSS 1071 A 74 ON 0\nEND-OF-LOG: 3.0\n";
         let log = CabrilloLog::parse(content).unwrap();
-        assert_eq!(log.qsos[0].sent_rst_exch, "1211 B 74 SCV");
-        assert_eq!(log.qsos[0].rcvd_rst_exch, "1071 A 74 ON");
+        assert_eq!(log.qsos[0].sent_exch.to_string(), "1211 B 74 SCV");
+        assert_eq!(log.qsos[0].rcvd_exch.to_string(), "1071 A 74 ON");
         assert_eq!(log.qsos[0].tx, Some("0".to_string()));
     }
 
+    #[test]
+    fn test_parse_with_contest_template() {
+        let content = "START-OF-LOG: 3.0\nCONTEST: ARRL-FD\nQSO: 14000 CW 2023-06-24 1800 W1AW 3A CT K1ABC 12F EMA\nEND-OF-LOG:\n";
+        let log = CabrilloLog::parse(content).unwrap();
+        let qso = &log.qsos[0];
+        assert_eq!(qso.sent_call, "W1AW");
+        assert_eq!(qso.sent_exch.get("class"), Some("3A"));
+        assert_eq!(qso.rcvd_call, "K1ABC");
+        assert_eq!(qso.rcvd_exch.get("section"), Some("EMA"));
+        assert_eq!(qso.tx, None);
+        assert!(log.validate().is_ok());
+
+        let content = "START-OF-LOG: 3.0\nCONTEST: CQ-WPX-CW\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4 1\nEND-OF-LOG:\n";
+        let log = CabrilloLog::parse(content).unwrap();
+        assert_eq!(log.qsos[0].sent_exch.serial(), Some(1));
+        assert_eq!(log.qsos[0].rcvd_exch.serial(), Some(4));
+        assert_eq!(log.qsos[0].tx, Some("1".to_string()));
+    }
+
+    #[test]
+    fn test_template_field_count_mismatch() {
+        let content = "START-OF-LOG: 3.0\nCONTEST: CQ-WPX-CW\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 S50A 599 4\nEND-OF-LOG:\n";
+        assert!(matches!(
+            CabrilloLog::parse(content),
+            Err(CabrilloError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_typed_exchange() {
+        let content = "START-OF-LOG: 3.0\nCONTEST: WW-DIGI\nQSO: 3595 DG 2019-08-31 1711 HC8N EI00 W1AW FN3 0\nEND-OF-LOG:\n";
+        let log = CabrilloLog::parse(content).unwrap();
+        assert_eq!(
+            log.validate(),
+            Err(CabrilloError::InvalidFormat(
+                "Invalid grid: FN3".to_string()
+            ))
+        );
+    }
+
     #[test]
     fn test_validate_log() {
         let content = "START-OF-LOG: 3.0\nCALLSIGN: N1MM\nQSO: 14000 CW 2023-10-01 1200 N1MM 599 001 W1AW 599 001 0\nEND-OF-LOG: 3.0\n";
@@ -325,7 +741,7 @@ mod tests {
         let output = log.to_string();
         assert!(output.contains("CALLSIGN: N1MM"));
         assert!(output.contains(
-            "QSO: 14000 CW 2023-10-01 1200 N1MM          599 001  W1AW          599 001  0"
+            "QSO:   14000 CW 2023-10-01 1200 N1MM          599 001    W1AW          599 001    0\n"
         ));
     }
 
@@ -334,14 +750,14 @@ mod tests {
         let content = "START-OF-LOG: 3.0\nCALLSIGN: N1MM\nQSO: 14000 CW 2023-10-01 1200 N1MM 599 001 W1AW 599 001\nEND-OF-LOG: 3.0\n";
         let log = CabrilloLog::parse(content).unwrap();
         assert_eq!(log.qsos.len(), 1);
-        assert_eq!(log.qsos[0].freq, "14000");
-        assert_eq!(log.qsos[0].mode, "CW");
+        assert_eq!(log.qsos[0].freq, Frequency::Khz(14000.0));
+        assert_eq!(log.qsos[0].mode, CabrilloMode::Cw);
         assert_eq!(log.qsos[0].tx, None);
-        assert_eq!(log.qsos[0].sent_rst_exch, "599 001");
-        assert_eq!(log.qsos[0].rcvd_rst_exch, "599 001");
+        assert_eq!(log.qsos[0].sent_exch.to_string(), "599 001");
+        assert_eq!(log.qsos[0].rcvd_exch.to_string(), "599 001");
         let output = log.to_string();
         assert!(output.contains(
-            "QSO: 14000 CW 2023-10-01 1200 N1MM          599 001  W1AW          599 001 "
+            "QSO:   14000 CW 2023-10-01 1200 N1MM          599 001    W1AW          599 001\n"
         ));
     }
 
@@ -349,7 +765,7 @@ mod tests {
     fn test_parse_exchange_with_zero() {
         let content = "START-OF-LOG: 3.0\nQSO: 14000 CW 2023-10-01 1200 N1MM 599 001 W1AW 599 001 0\nEND-OF-LOG: 3.0\n";
         let log = CabrilloLog::parse(content).unwrap();
-        assert_eq!(log.qsos[0].sent_rst_exch, "599 001");
+        assert_eq!(log.qsos[0].sent_exch.to_string(), "599 001");
         assert_eq!(log.qsos[0].tx, Some("0".to_string()));
     }
 
@@ -357,7 +773,7 @@ mod tests {
     fn test_parse_exchange_with_one() {
         let content = "START-OF-LOG: 3.0\nQSO: 14000 CW 2023-10-01 1200 N1MM 599 001 W1AW 599 001 1\nEND-OF-LOG: 3.0\n";
         let log = CabrilloLog::parse(content).unwrap();
-        assert_eq!(log.qsos[0].sent_rst_exch, "599 001");
+        assert_eq!(log.qsos[0].sent_exch.to_string(), "599 001");
         assert_eq!(log.qsos[0].tx, Some("1".to_string()));
     }
 
@@ -369,15 +785,63 @@ mod tests {
     }
 
     #[test]
-    fn test_is_valid_band() {
-        assert!(is_valid_band("20"));
-        assert!(is_valid_band("14000"));
-        assert!(!is_valid_band("invalid"));
+    fn test_frequency_column() {
+        let line = "QSO: 14000 CW 2023-10-01 1200 N1MM 599 001 W1AW 599 001";
+        let mut qso = CabrilloLog::parse_qso_line(line, None).unwrap();
+        assert_eq!(qso.freq.band(), Some(Band::B20M));
+
+        let (kind, columns) =
+            CabrilloLog::parse_qso_line(&line.replace("14000", "invalid"), None).unwrap_err();
+        assert_eq!(
+            kind,
+            CabrilloError::InvalidFormat("Invalid frequency: invalid".to_string())
+        );
+        assert_eq!(columns, 5..12);
+
+        qso.freq = Frequency::Khz(12345.0);
+        assert!(CabrilloLog::validate_qso(&qso).is_err());
+    }
+
+    #[test]
+    fn test_mode_column() {
+        let line = "QSO: 14074 FT8 2023-10-01 1200 N1MM FN31 W1AW FN42";
+        let qso = CabrilloLog::parse_qso_line(line, None).unwrap();
+        assert_eq!(qso.mode, CabrilloMode::Dg);
+        assert_eq!(qso.mode.precise.as_deref(), Some("FT8"));
+        assert!(qso.to_string().starts_with("QSO:   14074 DG "));
+
+        let (kind, columns) =
+            CabrilloLog::parse_qso_line(&line.replace("FT8", "invalid"), None).unwrap_err();
+        assert_eq!(
+            kind,
+            CabrilloError::InvalidFormat("Invalid mode: invalid".to_string())
+        );
+        assert_eq!(columns, 11..18);
     }
 
     #[test]
-    fn test_is_valid_mode() {
-        assert!(is_valid_mode("CW"));
-        assert!(!is_valid_mode("invalid"));
+    fn test_validate_contest_modes() {
+        let content = "START-OF-LOG: 3.0\nCONTEST: WW-DIGI\nQSO: 14074 DG 2023-10-01 1200 N1MM FN31 W1AW FN42\nEND-OF-LOG:\n";
+        assert!(CabrilloLog::parse(content).unwrap().validate().is_ok());
+        let cw = CabrilloLog::parse(&content.replace(" DG ", " CW ")).unwrap();
+        assert_eq!(
+            cw.validate(),
+            Err(CabrilloError::InvalidFormat(
+                "Mode CW not allowed in WW-DIGI".to_string()
+            ))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json_round_trip() {
+        let content = "START-OF-LOG: 3.0\nCONTEST: CQ-WPX-CW\nCATEGORY-BAND: 40M\nX-CUSTOM: kept\n# note\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4\nEND-OF-LOG:\n";
+        let log = CabrilloLog::parse(content).unwrap();
+        let json = serde_json::to_string(&log).unwrap();
+        assert!(json.contains(r#"{"tag":"CATEGORY-BAND","value":"40M"}"#));
+        assert!(json.contains(r#""date":"2009-05-30""#));
+        let back: CabrilloLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, log);
+        assert_eq!(back.headers.category_band(), Some(CategoryBand::B40M));
     }
 }
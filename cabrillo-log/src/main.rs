@@ -1,62 +1,817 @@
-use cabrillo_log::{CabrilloError, CabrilloLog, QSO};
-use chrono::{NaiveDate, NaiveTime};
-
-fn main() -> Result<(), CabrilloError> {
-    // Example Cabrillo content
-    let content = r#"START-OF-LOG: 3.0
-CALLSIGN: N1MM
-CONTEST: ARRL-10
-CATEGORY-OPERATOR: SINGLE-OP
-CATEGORY-ASSISTED: NON-ASSISTED
-CATEGORY-BAND: ALL
-CATEGORY-MODE: CW
-CATEGORY-POWER: LOW
-CATEGORY-STATION: FIXED
-CATEGORY-TRANSMITTER: ONE
-CLAIMED-SCORE: 100
-CLUB: Test Club
-CONTEST: ARRL-10
-CREATED-BY: Cabrillo Log 1.0
-NAME: John Doe
-ADDRESS: 123 Main St
-ADDRESS: Anytown, USA
-EMAIL: john@example.com
-OPERATORS: N1MM
-SOAPBOX: Test log
-QSO: 14000 CW 2023-10-01 1200 N1MM 599 001 0 W1AW 599 001
-QSO: 7000 CW 2023-10-01 1300 N1MM 599 002 0 K1ZZ 599 002
-END-OF-LOG: 3.0
-"#;
-
-    // Parse the log
-    let log = CabrilloLog::parse(content)?;
-    println!("Parsed log: {:?}", log);
-
-    // Validate the log
-    log.validate()?;
-
-    // Generate string using Display trait
-    println!("Generated log:\n{}", log);
-
-    // Create a new QSO
-    let qso = QSO {
-        freq: "21000".to_string(),
-        mode: "CW".to_string(),
-        date: NaiveDate::from_ymd_opt(2023, 10, 2).unwrap(),
-        time: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
-        sent_call: "N1MM".to_string(),
-        sent_rst_exch: "599 003".to_string(),
-        tx: Some("0".to_string()),
-        rcvd_call: "W2XX".to_string(),
-        rcvd_rst_exch: "599 003".to_string(),
+//! `cabrillo-log` command-line tool.
+//!
+//! Every subcommand reads a file argument or, when it is missing or `-`, standard
+//! input, and writes to standard output unless `--output` is given. Exit codes:
+//! `0` on success, `1` when the log has errors (or `fmt --check` would change
+//! it, or `diff` finds changes) and `2` when the command itself fails, e.g. on
+//! an unreadable file.
+//!
+//! With `--json`, JSON goes to standard output. `repair` and `merge` then put
+//! the log into the JSON as `"log"`, unless `--output` is given.
+
+use cabrillo_log::{
+    Adif, Band, CabrilloFormat, CabrilloLog, CabrilloMode, ContestPeriod, CrossCheckOptions,
+    Diagnostic, DupeScope, HeaderOrder, LineEnding, MergeOptions, ParseReport, QSO, QsoChange,
+    Severity, SplitKey, TemplateRegistry, TimingRules,
+};
+use chrono::{NaiveDateTime, TimeDelta};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(version, about = "Validate, format and convert Cabrillo contest logs")]
+struct Cli {
+    /// Print machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check a log for parse, validation, timing, consistency and serial number errors
+    Validate {
+        /// Input file, `-` for stdin
+        file: Option<PathBuf>,
+        /// Contest start, e.g. `2009-05-30T00:00:00`
+        #[arg(long, requires = "end")]
+        start: Option<NaiveDateTime>,
+        /// Contest end (first minute after the contest)
+        #[arg(long, requires = "start")]
+        end: Option<NaiveDateTime>,
+    },
+    /// Normalize a log and write it back
+    Fmt {
+        /// Input file, `-` for stdin
+        file: Option<PathBuf>,
+        /// Output file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Rewrite the input file in place
+        #[arg(short, long, conflicts_with = "output")]
+        in_place: bool,
+        /// Only check whether the log is already formatted
+        #[arg(long, conflicts_with_all = ["output", "in_place"])]
+        check: bool,
+        /// End lines with CR LF
+        #[arg(long)]
+        crlf: bool,
+        /// Put header lines in specification order
+        #[arg(long)]
+        sort_headers: bool,
+    },
+    /// Fix common formatting problems and list every change
+    Repair {
+        /// Input file, `-` for stdin
+        file: Option<PathBuf>,
+        /// Output file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Rewrite the input file in place
+        #[arg(short, long, conflicts_with = "output")]
+        in_place: bool,
+    },
+    /// Convert a Cabrillo or ADIF log to another format
+    Convert {
+        /// Input file (Cabrillo, .adi or .adx), `-` for stdin
+        file: Option<PathBuf>,
+        /// Output format
+        #[arg(long, value_enum)]
+        to: Format,
+        /// Output file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Summarize QSO counts
+    Stats {
+        /// Input file, `-` for stdin
+        file: Option<PathBuf>,
+    },
+    /// List duplicate contacts by the contest's dupe rules
+    Dupes {
+        /// Input file, `-` for stdin
+        file: Option<PathBuf>,
+        /// Dupe rule to use instead of the contest's
+        #[arg(long, value_enum)]
+        scope: Option<Scope>,
+    },
+    /// Merge the logs of several computers into one multi-transmitter log
+    Merge {
+        /// Input files; the first gets TX ID 0 and the second TX ID 1, so more
+        /// than two need --keep-tx
+        #[arg(required = true, num_args = 2..)]
+        files: Vec<PathBuf>,
+        /// Output file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Keep the TX IDs of the input files
+        #[arg(long)]
+        keep_tx: bool,
+        /// Minutes apart that QSOs with the same station in two files are the same contact
+        #[arg(long, default_value_t = 2)]
+        window: i64,
+    },
+    /// Show what changed between two versions of a log, QSO by QSO
+    Diff {
+        /// Old version of the log
+        old: PathBuf,
+        /// New version of the log
+        new: PathBuf,
+    },
+    /// Cross-check the logs of several stations and write a UBN report for each
+    Crosscheck {
+        /// Logs of the participating stations
+        #[arg(required = true, num_args = 2..)]
+        files: Vec<PathBuf>,
+        /// Largest time difference in minutes between the two logs of a QSO
+        #[arg(long, default_value_t = 3)]
+        tolerance: i64,
+        /// Write `<CALLSIGN>.ubn` files to this directory instead of printing the reports;
+        /// logs without a call of their own are named after their file
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+    /// Split a log into one log per band, mode, transmitter or date
+    Split {
+        /// Input file, `-` for stdin
+        file: Option<PathBuf>,
+        /// What to split by
+        #[arg(long, value_enum, default_value_t = SplitBy::Band)]
+        by: SplitBy,
+        /// Directory for the parts, named `<input>-<part>.log`; default is the input's directory
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SplitBy {
+    Band,
+    Mode,
+    Tx,
+    Date,
+}
+
+impl From<SplitBy> for SplitKey {
+    fn from(by: SplitBy) -> Self {
+        match by {
+            SplitBy::Band => SplitKey::Band,
+            SplitBy::Mode => SplitKey::Mode,
+            SplitBy::Tx => SplitKey::Transmitter,
+            SplitBy::Date => SplitKey::Date,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Cabrillo,
+    Adif,
+    Adx,
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Scope {
+    Band,
+    BandMode,
+    Contest,
+    BandRoverGrid,
+}
+
+impl From<Scope> for DupeScope {
+    fn from(scope: Scope) -> Self {
+        match scope {
+            Scope::Band => DupeScope::PerBand,
+            Scope::BandMode => DupeScope::PerBandMode,
+            Scope::Contest => DupeScope::PerContest,
+            Scope::BandRoverGrid => DupeScope::PerBandRoverGrid,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Run a subcommand; `Ok(false)` means the log did not pass.
+fn run(cli: &Cli) -> Result<bool> {
+    match &cli.command {
+        Command::Validate { file, start, end } => {
+            let period = start
+                .zip(*end)
+                .map(|(start, end)| ContestPeriod::new(start, end));
+            validate(file.as_deref(), period, cli.json)
+        }
+        Command::Fmt {
+            file,
+            output,
+            in_place,
+            check,
+            crlf,
+            sort_headers,
+        } => {
+            let (source, report) = load(file.as_deref())?;
+            if report.has_errors() {
+                return report_failure(&source, &report, cli.json);
+            }
+            let format = CabrilloFormat {
+                line_ending: if *crlf {
+                    LineEnding::CrLf
+                } else {
+                    LineEnding::Lf
+                },
+                header_order: if *sort_headers {
+                    HeaderOrder::Normalized
+                } else {
+                    HeaderOrder::Original
+                },
+                ..CabrilloFormat::for_log(&report.log, &TemplateRegistry::default())
+            };
+            let formatted = format.format(&report.log);
+            if *check {
+                let ok = formatted == source;
+                if cli.json {
+                    println!("{}", json!({ "formatted": ok }));
+                } else if !ok {
+                    eprintln!("{}: not formatted", display_name(file.as_deref()));
+                }
+                return Ok(ok);
+            }
+            let output = if *in_place {
+                file.as_deref()
+            } else {
+                output.as_deref()
+            };
+            write_output(output, &formatted)?;
+            Ok(true)
+        }
+        Command::Repair {
+            file,
+            output,
+            in_place,
+        } => {
+            let source = read_input(file.as_deref())?;
+            let report = CabrilloLog::repair(&source, &TemplateRegistry::default());
+            let repairs: Vec<_> = report
+                .repairs
+                .iter()
+                .map(|r| {
+                    json!({
+                        "line": r.line,
+                        "original": r.original,
+                        "fixed": r.fixed,
+                        "kinds": r.kinds.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            if report.has_errors() {
+                if cli.json {
+                    let errors: Vec<_> = report
+                        .diagnostics
+                        .iter()
+                        .filter(|d| d.severity == Severity::Error)
+                        .map(diagnostic_json)
+                        .collect();
+                    let output = json!({ "repairs": repairs, "errors": errors });
+                    println!("{}", serde_json::to_string_pretty(&output)?);
+                    return Ok(false);
+                }
+                eprint!("{}", report.render_repairs());
+                let remaining = ParseReport {
+                    log: report.log,
+                    diagnostics: report.diagnostics,
+                };
+                return report_failure(&report.repaired, &remaining, false);
+            }
+            let output = if *in_place {
+                file.as_deref()
+            } else {
+                output.as_deref()
+            };
+            let format = CabrilloFormat::for_log(&report.log, &TemplateRegistry::default());
+            let repaired = format.format(&report.log);
+            if cli.json {
+                print_json_with_log(json!({ "repairs": repairs }), output, &repaired)?;
+            } else {
+                eprint!("{}", report.render_repairs());
+                write_output(output, &repaired)?;
+            }
+            Ok(true)
+        }
+        Command::Convert { file, to, output } => {
+            let (source, report) = load(file.as_deref())?;
+            if report.has_errors() {
+                return report_failure(&source, &report, cli.json);
+            }
+            let converted = match to {
+                Format::Cabrillo => {
+                    CabrilloFormat::for_log(&report.log, &TemplateRegistry::default())
+                        .format(&report.log)
+                }
+                Format::Adif => Adif::from(&report.log).to_adi(),
+                Format::Adx => Adif::from(&report.log).to_adx(),
+                Format::Csv => to_csv(&report.log),
+                Format::Json => serde_json::to_string_pretty(&report.log)? + "\n",
+            };
+            write_output(output.as_deref(), &converted)?;
+            Ok(true)
+        }
+        Command::Stats { file } => {
+            let (_, report) = load(file.as_deref())?;
+            print_stats(&report.log, cli.json);
+            Ok(true)
+        }
+        Command::Dupes { file, scope } => {
+            let (_, report) = load(file.as_deref())?;
+            print_dupes(&report.log, *scope, cli.json);
+            Ok(true)
+        }
+        Command::Merge {
+            files,
+            output,
+            keep_tx,
+            window,
+        } => {
+            let mut logs = Vec::with_capacity(files.len());
+            for file in files {
+                let (source, report) = load(Some(file))?;
+                if report.has_errors() {
+                    return report_failure(&source, &report, cli.json);
+                }
+                logs.push(report.log);
+            }
+            let options = MergeOptions {
+                assign_tx_ids: !keep_tx,
+                window: TimeDelta::minutes(*window),
+            };
+            let merged = CabrilloLog::merge(&logs, &options);
+            let failed = merged
+                .issues
+                .iter()
+                .any(|i| i.severity() == Severity::Error);
+            let format = CabrilloFormat::for_log(&merged.log, &TemplateRegistry::default());
+            let combined = if failed {
+                String::new()
+            } else {
+                format.format(&merged.log)
+            };
+            if cli.json {
+                let issues: Vec<_> = merged.issues.iter().map(ToString::to_string).collect();
+                print_json_with_log(json!({ "issues": issues }), output.as_deref(), &combined)?;
+            } else {
+                for issue in &merged.issues {
+                    eprintln!("{}: {}", issue.severity(), issue);
+                }
+                if !failed {
+                    write_output(output.as_deref(), &combined)?;
+                }
+            }
+            Ok(!failed)
+        }
+        Command::Diff { old, new } => {
+            let mut logs = Vec::with_capacity(2);
+            for file in [old, new] {
+                let (source, report) = load(Some(file))?;
+                if report.has_errors() {
+                    return report_failure(&source, &report, cli.json);
+                }
+                logs.push(report.log);
+            }
+            let diff = logs[0].diff(&logs[1]);
+            if cli.json {
+                let output = json!({
+                    "headers": diff.headers.iter().map(|h| json!({
+                        "tag": h.tag,
+                        "old": h.old,
+                        "new": h.new,
+                    })).collect::<Vec<_>>(),
+                    "qsos": diff.qsos.iter().map(qso_change_json).collect::<Vec<_>>(),
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                print!("{}", diff);
+            }
+            Ok(diff.is_empty())
+        }
+        Command::Crosscheck {
+            files,
+            tolerance,
+            dir,
+        } => {
+            let mut logs = Vec::with_capacity(files.len());
+            for file in files {
+                let (source, report) = load(Some(file))?;
+                if report.has_errors() {
+                    return report_failure(&source, &report, cli.json);
+                }
+                logs.push(report.log);
+            }
+            let options = CrossCheckOptions {
+                tolerance: TimeDelta::minutes(*tolerance),
+            };
+            let reports = CabrilloLog::cross_check(&logs, &options);
+            let mut written = Vec::with_capacity(reports.len());
+            for ((report, log), file) in reports.iter().zip(&logs).zip(files) {
+                let shared = reports
+                    .iter()
+                    .filter(|r| r.callsign == report.callsign)
+                    .count()
+                    > 1;
+                let name = if report.callsign.is_empty() || shared {
+                    file.file_stem()
+                        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+                } else {
+                    report.callsign.replace('/', "_")
+                };
+                let path = dir.as_ref().map(|dir| dir.join(format!("{}.ubn", name)));
+                if let Some(path) = &path {
+                    std::fs::write(path, report.render(log))?;
+                } else if !cli.json {
+                    println!("{}", report.render(log));
+                }
+                written.push(path);
+            }
+            if cli.json {
+                let output: Vec<_> = reports
+                    .iter()
+                    .zip(&written)
+                    .map(|(report, path)| {
+                        json!({
+                            "callsign": report.callsign,
+                            "file": path.as_ref().map(|p| p.display().to_string()),
+                            "checks": report.checks.iter().map(|c| json!({
+                                "qso": c.qso + 1,
+                                "ok": c.result.is_ok(),
+                                "result": c.result.to_string(),
+                            })).collect::<Vec<_>>(),
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else if dir.is_some() {
+                for (report, path) in reports.iter().zip(&written) {
+                    let problems = report.count(|r| !r.is_ok());
+                    if let Some(path) = path {
+                        println!("{}: {} problem QSOs", path.display(), problems);
+                    }
+                }
+            }
+            Ok(reports.iter().all(|r| r.count(|c| !c.is_ok()) == 0))
+        }
+        Command::Split { file, by, dir } => {
+            let (source, report) = load(file.as_deref())?;
+            if report.has_errors() {
+                return report_failure(&source, &report, cli.json);
+            }
+            let input = file.as_deref().filter(|f| *f != Path::new("-"));
+            let stem = input
+                .and_then(Path::file_stem)
+                .map_or_else(|| "log".into(), |s| s.to_string_lossy());
+            let dir = match (dir, input.and_then(Path::parent)) {
+                (Some(dir), _) => dir.clone(),
+                (None, Some(parent)) => parent.to_path_buf(),
+                (None, None) => PathBuf::from("."),
+            };
+            let mut written = Vec::new();
+            for (part, log) in report.log.split((*by).into()) {
+                let path = dir.join(format!("{}-{}.log", stem, part));
+                let format = CabrilloFormat::for_log(&log, &TemplateRegistry::default());
+                std::fs::write(&path, format.format(&log))?;
+                written.push((part, path, log.qsos.len()));
+            }
+            if cli.json {
+                let parts: Vec<_> = written
+                    .iter()
+                    .map(|(part, path, qsos)| {
+                        json!({ "part": part.to_string(), "file": path.display().to_string(), "qsos": qsos })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&parts)?);
+            } else {
+                for (_, path, qsos) in &written {
+                    println!("{}: {} QSOs", path.display(), qsos);
+                }
+            }
+            Ok(true)
+        }
+    }
+}
+
+fn validate(file: Option<&Path>, period: Option<ContestPeriod>, json: bool) -> Result<bool> {
+    let (source, report) = load(file)?;
+    let validation = report.log.validate().err();
+    let rules = TimingRules {
+        period,
+        ..TimingRules::default()
     };
+    let timing = report.log.check_timing(&rules);
+    let serials = report.log.check_serials();
+    let consistency = report.log.check_consistency(None);
+    let ok = !report.has_errors()
+        && validation.is_none()
+        && timing.iter().all(|t| t.severity() == Severity::Warning)
+        && consistency
+            .iter()
+            .all(|c| c.severity() == Severity::Warning);
+
+    if json {
+        let output = json!({
+            "file": display_name(file),
+            "valid": ok,
+            "qsos": report.log.qsos.len(),
+            "diagnostics": report.diagnostics.iter().map(diagnostic_json).collect::<Vec<_>>(),
+            "validation_error": validation.as_ref().map(ToString::to_string),
+            "timing": timing.iter().map(|t| json!({
+                "qso": t.qso + 1,
+                "severity": t.severity().to_string(),
+                "message": t.to_string(),
+            })).collect::<Vec<_>>(),
+            "consistency": consistency.iter().map(|c| json!({
+                "qso": c.qso.map(|qso| qso + 1),
+                "severity": c.severity().to_string(),
+                "message": c.to_string(),
+            })).collect::<Vec<_>>(),
+            "serials": serials.iter().map(|s| json!({
+                "qso": s.qso + 1,
+                "severity": Severity::Warning.to_string(),
+                "message": s.to_string(),
+            })).collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        if !report.diagnostics.is_empty() {
+            eprint!("{}", report.render(&source));
+        }
+        if let Some(e) = &validation {
+            eprintln!("error: {}", e);
+        }
+        for issue in &timing {
+            eprintln!("{}: {}", issue.severity(), issue);
+        }
+        for issue in &consistency {
+            eprintln!("{}: {}", issue.severity(), issue);
+        }
+        for issue in &serials {
+            eprintln!("{}: {}", Severity::Warning, issue);
+        }
+        if ok {
+            println!("{}: OK, {} QSOs", display_name(file), report.log.qsos.len());
+        }
+    }
+    Ok(ok)
+}
 
-    // Add to log
-    let mut new_log = log.clone();
-    new_log.qsos.push(qso);
+/// Print the parse errors of a log that cannot be processed further.
+fn report_failure(source: &str, report: &ParseReport, json: bool) -> Result<bool> {
+    if json {
+        let errors: Vec<_> = report.errors().map(diagnostic_json).collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({ "errors": errors }))?
+        );
+    } else {
+        eprint!("{}", report.render(source));
+    }
+    Ok(false)
+}
 
-    // Output new log using Display trait
-    println!("New log:\n{}", new_log);
+fn qso_change_json(change: &QsoChange) -> serde_json::Value {
+    let line = |qso: &QSO| CabrilloFormat::default().qso_line(qso);
+    match change {
+        QsoChange::Added { new, qso } => {
+            json!({ "change": "added", "new": new + 1, "line": line(qso) })
+        }
+        QsoChange::Removed { old, qso } => {
+            json!({ "change": "removed", "old": old + 1, "line": line(qso) })
+        }
+        QsoChange::Edited {
+            old,
+            new,
+            qso,
+            fields,
+        } => json!({
+            "change": "edited",
+            "old": old + 1,
+            "new": new + 1,
+            "line": line(qso),
+            "fields": fields.iter().map(|f| json!({
+                "field": f.field,
+                "old": f.old,
+                "new": f.new,
+            })).collect::<Vec<_>>(),
+        }),
+    }
+}
 
+fn diagnostic_json(d: &Diagnostic) -> serde_json::Value {
+    json!({
+        "line": d.line,
+        "column": d.columns.start + 1,
+        "severity": d.severity.to_string(),
+        "message": d.kind.to_string(),
+    })
+}
+
+/// Read and parse the input, accepting ADIF as well as Cabrillo.
+fn load(file: Option<&Path>) -> Result<(String, ParseReport)> {
+    let source = read_input(file)?;
+    let trimmed = source.trim_start();
+    let is_adif = trimmed.starts_with('<') && !trimmed.starts_with("<!")
+        || source.contains("<EOH>")
+        || source.contains("<eoh>");
+    let report = if is_adif {
+        ParseReport {
+            log: Adif::parse(&source)?.to_log(),
+            diagnostics: Vec::new(),
+        }
+    } else {
+        CabrilloLog::parse_with_diagnostics(&source, &TemplateRegistry::default())
+    };
+    Ok((source, report))
+}
+
+fn read_input(file: Option<&Path>) -> io::Result<String> {
+    match file {
+        Some(path) if path != Path::new("-") => std::fs::read_to_string(path),
+        _ => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)?;
+            Ok(content)
+        }
+    }
+}
+
+/// Print a JSON report on stdout. The log goes to `file`, or into the report
+/// as `"log"` if it would go to stdout too; an empty log is not written.
+fn print_json_with_log(
+    mut report: serde_json::Value,
+    file: Option<&Path>,
+    log: &str,
+) -> Result<()> {
+    match file {
+        _ if log.is_empty() => {}
+        Some(path) if path != Path::new("-") => std::fs::write(path, log)?,
+        _ => report["log"] = log.into(),
+    }
+    println!("{}", serde_json::to_string_pretty(&report)?);
     Ok(())
 }
+
+fn write_output(file: Option<&Path>, content: &str) -> io::Result<()> {
+    match file {
+        Some(path) if path != Path::new("-") => std::fs::write(path, content),
+        _ => io::stdout().write_all(content.as_bytes()),
+    }
+}
+
+fn display_name(file: Option<&Path>) -> String {
+    match file {
+        Some(path) if path != Path::new("-") => path.display().to_string(),
+        _ => "<stdin>".to_string(),
+    }
+}
+
+fn to_csv(log: &CabrilloLog) -> String {
+    let mut out =
+        String::from("freq,mode,date,time,sent_call,sent_exch,rcvd_call,rcvd_exch,tx,excluded\n");
+    for qso in &log.qsos {
+        let row = [
+            qso.freq.to_string(),
+            qso.mode.to_string(),
+            qso.date.format("%Y-%m-%d").to_string(),
+            qso.time.format("%H%M").to_string(),
+            qso.sent_call.clone(),
+            qso.sent_exch.to_string(),
+            qso.rcvd_call.clone(),
+            qso.rcvd_exch.to_string(),
+            qso.tx.clone().unwrap_or_default(),
+            qso.excluded.to_string(),
+        ];
+        let row: Vec<String> = row.iter().map(|v| csv_escape(v)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Name of a band, `unknown` for frequencies outside the amateur bands.
+fn band_name(band: Option<Band>) -> &'static str {
+    band.map_or("unknown", |b| b.name())
+}
+
+fn print_stats(log: &CabrilloLog, json: bool) {
+    let counted: Vec<&QSO> = log.counted_qsos().collect();
+    let mut per_band: BTreeMap<Option<Band>, usize> = BTreeMap::new();
+    let mut per_mode: BTreeMap<CabrilloMode, usize> = BTreeMap::new();
+    let mut per_precise_mode: BTreeMap<&str, usize> = BTreeMap::new();
+    for qso in &counted {
+        *per_band.entry(qso.freq.band()).or_default() += 1;
+        *per_mode.entry(qso.mode.category).or_default() += 1;
+        *per_precise_mode
+            .entry(qso.mode.precise_or_category())
+            .or_default() += 1;
+    }
+    let unique: HashSet<&str> = counted.iter().map(|q| q.rcvd_call.as_str()).collect();
+    let excluded = log.excluded_qsos().count();
+
+    if json {
+        let output = json!({
+            "callsign": log.headers.callsign(),
+            "contest": log.headers.contest(),
+            "qsos": counted.len(),
+            "excluded": excluded,
+            "unique_calls": unique.len(),
+            "per_band": per_band
+                .iter()
+                .map(|(band, count)| (band_name(*band), count))
+                .collect::<BTreeMap<_, _>>(),
+            "per_mode": per_mode
+                .iter()
+                .map(|(mode, count)| (mode.as_str(), count))
+                .collect::<BTreeMap<_, _>>(),
+            "per_precise_mode": per_precise_mode,
+        });
+        println!("{}", output);
+        return;
+    }
+
+    println!("QSOs:         {}", counted.len());
+    println!("X-QSOs:       {}", excluded);
+    println!("Unique calls: {}", unique.len());
+    println!("Per band:");
+    for (band, count) in &per_band {
+        println!("  {:<6} {}", band_name(*band), count);
+    }
+    println!("Per mode:");
+    for (mode, count) in &per_mode {
+        println!("  {:<6} {}", mode, count);
+    }
+    if per_precise_mode.len() != per_mode.len()
+        || per_precise_mode
+            .keys()
+            .zip(per_mode.keys())
+            .any(|(p, m)| *p != m.as_str())
+    {
+        println!("Per precise mode:");
+        for (mode, count) in &per_precise_mode {
+            println!("  {:<6} {}", mode, count);
+        }
+    }
+}
+
+fn print_dupes(log: &CabrilloLog, scope: Option<Scope>, json: bool) {
+    let scope = scope.map_or_else(|| log.dupe_scope(), DupeScope::from);
+    let dupes = log.find_dupes(scope);
+
+    if json {
+        let output: Vec<_> = dupes
+            .iter()
+            .map(|d| {
+                json!({
+                    "qso": d.dupe + 1,
+                    "duplicate_of": d.first + 1,
+                    "call": log.qsos[d.dupe].rcvd_call,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::from(output));
+        return;
+    }
+
+    for d in &dupes {
+        println!(
+            "QSO #{} duplicates #{}: {}",
+            d.dupe + 1,
+            d.first + 1,
+            log.qsos[d.dupe]
+        );
+    }
+    println!("{} duplicate(s)", dupes.len());
+}
@@ -0,0 +1,432 @@
+//! Merging the logs of several computers into one multi-transmitter log.
+//!
+//! Multi-op stations often log each transmitter on a computer of its own.
+//! [`CabrilloLog::merge`] combines those logs into the single log the sponsor
+//! expects: QSOs in time order, each tagged with the TX ID of the log it came
+//! from, and one multi-op header reconciled from all of them. QSOs that appear in more
+//! than one log, e.g. after the loggers were networked for a while, are dropped
+//! or reported so they can be fixed by hand.
+//!
+//! ```rust
+//! use cabrillo_log::{CabrilloLog, MergeOptions};
+//!
+//! let run = CabrilloLog::parse("START-OF-LOG: 3.0\nCALLSIGN: K1ABC\nOPERATORS: K1ABC\nQSO: 14005 CW 2009-05-30 0002 K1ABC 599 1 S50A 599 4\nEND-OF-LOG:\n").unwrap();
+//! let mult = CabrilloLog::parse("START-OF-LOG: 3.0\nCALLSIGN: K1ABC\nOPERATORS: W1XYZ\nQSO: 7005 CW 2009-05-30 0001 K1ABC 599 1 EF8M 599 9\nEND-OF-LOG:\n").unwrap();
+//! let merged = CabrilloLog::merge(&[run, mult], &MergeOptions::default());
+//! assert_eq!(merged.log.headers.operators(), vec!["K1ABC", "W1XYZ"]);
+//! assert_eq!(merged.log.qsos[0].rcvd_call, "EF8M");
+//! assert_eq!(merged.log.qsos[0].tx.as_deref(), Some("1"));
+//! assert!(merged.issues.is_empty());
+//! ```
+
+use crate::{CabrilloLog, CategoryOperator, CategoryTransmitter, Header, Headers, QSO, Severity};
+use chrono::TimeDelta;
+use std::fmt;
+
+/// Tags that are not checked for conflicts between the merged logs.
+///
+/// `CLAIMED-SCORE:` of a single computer's log is meaningless for the merged
+/// log and is dropped; the logging programs are allowed to differ.
+const UNCHECKED_TAGS: &[&str] = &["CLAIMED-SCORE", "CREATED-BY"];
+
+/// How [`CabrilloLog::merge`] combines logs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeOptions {
+    /// Give the QSOs of the first log TX ID 0 and those of the second TX ID 1;
+    /// otherwise TX IDs are kept as logged. Cabrillo has no other TX IDs, so
+    /// with more than two logs they are kept as logged and
+    /// [`MergeIssue::TooManyTransmitters`] is reported.
+    pub assign_tx_ids: bool,
+    /// QSOs of different logs with the same station on the same band and mode
+    /// at most this far apart are the same contact.
+    pub window: TimeDelta,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        MergeOptions {
+            assign_tx_ids: true,
+            window: TimeDelta::minutes(2),
+        }
+    }
+}
+
+/// A QSO of one of the merged logs: index of the log and of the QSO in its
+/// [`CabrilloLog::qsos`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QsoSource {
+    pub log: usize,
+    pub qso: usize,
+}
+
+impl fmt::Display for QsoSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "log {} QSO #{}", self.log + 1, self.qso + 1)
+    }
+}
+
+/// A problem found while merging.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MergeIssue {
+    /// A single-valued header differs between logs; the first value is kept.
+    HeaderConflict { tag: String, values: Vec<String> },
+    /// The same QSO is in two logs; the later copy was dropped.
+    DuplicateQso { kept: QsoSource, dropped: QsoSource },
+    /// Two logs have the same contact with different details; both were kept.
+    ConflictingQso { first: QsoSource, second: QsoSource },
+    /// TX IDs were to be assigned to more than two logs; they were kept as logged.
+    TooManyTransmitters { logs: usize },
+}
+
+impl MergeIssue {
+    /// Logs that cannot be told apart by TX ID are an error; everything else
+    /// is a warning.
+    pub fn severity(&self) -> Severity {
+        match self {
+            MergeIssue::TooManyTransmitters { .. } => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for MergeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeIssue::HeaderConflict { tag, values } => write!(
+                f,
+                "{} differs between logs ({}), keeping {}",
+                tag,
+                values.join(" / "),
+                values[0]
+            ),
+            MergeIssue::DuplicateQso { kept, dropped } => {
+                write!(f, "{} repeats {}, dropped", dropped, kept)
+            }
+            MergeIssue::ConflictingQso { first, second } => {
+                write!(
+                    f,
+                    "{} and {} are the same contact but differ",
+                    first, second
+                )
+            }
+            MergeIssue::TooManyTransmitters { logs } => write!(
+                f,
+                "{} logs cannot get TX IDs 0 and 1, keeping TX IDs as logged",
+                logs
+            ),
+        }
+    }
+}
+
+/// Result of [`CabrilloLog::merge`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeReport {
+    pub log: CabrilloLog,
+    /// Header conflicts and TX ID problems first, then QSO issues in time order.
+    pub issues: Vec<MergeIssue>,
+}
+
+impl CabrilloLog {
+    /// Merge logs into one, ordering the QSOs by time.
+    ///
+    /// QSOs logged at the same time keep the order of `logs`. Comments are not
+    /// carried over, since they can no longer be placed next to their lines.
+    pub fn merge(logs: &[CabrilloLog], options: &MergeOptions) -> MergeReport {
+        let mut issues = Vec::new();
+        let headers = merge_headers(logs, &mut issues);
+        let assign_tx_ids = options.assign_tx_ids && logs.len() <= 2;
+        if options.assign_tx_ids && !assign_tx_ids {
+            issues.push(MergeIssue::TooManyTransmitters { logs: logs.len() });
+        }
+
+        let mut entries: Vec<(QsoSource, &QSO)> = logs
+            .iter()
+            .enumerate()
+            .flat_map(|(log, l)| {
+                l.qsos
+                    .iter()
+                    .enumerate()
+                    .map(move |(qso, q)| (QsoSource { log, qso }, q))
+            })
+            .collect();
+        entries.sort_by_key(|(_, qso)| qso.datetime());
+
+        let mut kept: Vec<(QsoSource, &QSO)> = Vec::with_capacity(entries.len());
+        for (source, qso) in entries {
+            let earlier = kept
+                .iter()
+                .rev()
+                .take_while(|(_, k)| qso.datetime() - k.datetime() <= options.window)
+                .find(|(s, k)| s.log != source.log && same_contact(k, qso));
+            match earlier {
+                Some(&(first, k)) if same_details(k, qso, options.window) => {
+                    issues.push(MergeIssue::DuplicateQso {
+                        kept: first,
+                        dropped: source,
+                    });
+                    continue;
+                }
+                Some(&(first, _)) => issues.push(MergeIssue::ConflictingQso {
+                    first,
+                    second: source,
+                }),
+                None => {}
+            }
+            kept.push((source, qso));
+        }
+
+        let qsos = kept
+            .into_iter()
+            .map(|(source, qso)| {
+                let mut qso = qso.clone();
+                if assign_tx_ids {
+                    qso.tx = Some(source.log.to_string());
+                }
+                qso
+            })
+            .collect();
+        MergeReport {
+            log: CabrilloLog {
+                headers,
+                qsos,
+                comments: Vec::new(),
+                end_of_log_version: logs.iter().any(|log| log.end_of_log_version),
+            },
+            issues,
+        }
+    }
+}
+
+/// Header of the merged log: the first log's header, with tags only other logs
+/// have added, `OPERATORS:` and `SOAPBOX:` lines of all logs combined and
+/// `CLAIMED-SCORE:` removed. Merging several logs makes a multi-op,
+/// multi-transmitter entry, so `CATEGORY-OPERATOR:` and `CATEGORY-TRANSMITTER:`
+/// are set accordingly.
+fn merge_headers(logs: &[CabrilloLog], issues: &mut Vec<MergeIssue>) -> Headers {
+    let mut headers = Headers::new();
+    let mut operators: Vec<&str> = Vec::new();
+    let mut soapbox: Vec<String> = Vec::new();
+    // Address lines belong together; take them from one log only
+    let address_log = logs.iter().position(|l| !l.headers.address().is_empty());
+    for (index, log) in logs.iter().enumerate() {
+        for op in log.headers.operators() {
+            if !operators.iter().any(|o| o.eq_ignore_ascii_case(op)) {
+                operators.push(op);
+            }
+        }
+        for header in &log.headers {
+            match header {
+                Header::Operators(_) | Header::ClaimedScore(_) => {}
+                Header::Soapbox(text) if !soapbox.contains(text) => soapbox.push(text.clone()),
+                Header::Soapbox(_) => {}
+                Header::Address(_) if Some(index) == address_log => headers.push(header.clone()),
+                Header::Address(_) => {}
+                _ if !headers.contains(header.tag()) => headers.push(header.clone()),
+                _ => {}
+            }
+        }
+    }
+    if !operators.is_empty() {
+        headers.push(Header::Operators(operators.join(" ")));
+    }
+    for text in soapbox {
+        headers.push(Header::Soapbox(text));
+    }
+    if logs.len() > 1 {
+        headers.set(Header::CategoryOperator(CategoryOperator::MultiOp));
+        headers.set(Header::CategoryTransmitter(if logs.len() == 2 {
+            CategoryTransmitter::Two
+        } else {
+            CategoryTransmitter::Unlimited
+        }));
+    }
+
+    for header in &headers {
+        let tag = header.tag();
+        if matches!(
+            header,
+            Header::Address(_)
+                | Header::Operators(_)
+                | Header::Soapbox(_)
+                | Header::CategoryOperator(_)
+                | Header::CategoryTransmitter(_)
+        ) || UNCHECKED_TAGS.iter().any(|t| t.eq_ignore_ascii_case(tag))
+            || issues
+                .iter()
+                .any(|i| matches!(i, MergeIssue::HeaderConflict { tag: t, .. } if t == tag))
+        {
+            continue;
+        }
+        let mut values: Vec<String> = Vec::new();
+        for value in logs.iter().filter_map(|l| l.headers.get(tag)) {
+            if !values
+                .iter()
+                .any(|v| v.trim().eq_ignore_ascii_case(value.trim()))
+            {
+                values.push(value);
+            }
+        }
+        if values.len() > 1 {
+            issues.push(MergeIssue::HeaderConflict {
+                tag: tag.to_string(),
+                values,
+            });
+        }
+    }
+    headers
+}
+
+/// Whether two QSOs are with the same station on the same band and mode.
+fn same_contact(a: &QSO, b: &QSO) -> bool {
+    a.rcvd_call.eq_ignore_ascii_case(&b.rcvd_call)
+        && a.freq.band() == b.freq.band()
+        && a.mode.category == b.mode.category
+}
+
+/// Whether two QSOs of the same contact were logged identically, apart from
+/// the exact frequency, the TX ID and clocks at most `window` apart.
+fn same_details(a: &QSO, b: &QSO, window: TimeDelta) -> bool {
+    (a.datetime() - b.datetime()).abs() <= window
+        && a.sent_call.eq_ignore_ascii_case(&b.sent_call)
+        && a.sent_exch == b.sent_exch
+        && a.rcvd_exch == b.rcvd_exch
+        && a.excluded == b.excluded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUN: &str = "START-OF-LOG: 3.0
+CONTEST: CQ-WPX-CW
+CALLSIGN: K1ABC
+CLAIMED-SCORE: 10
+QSO: 14005 CW 2009-05-30 0002 K1ABC 599 1 S50A 599 4
+QSO: 14010 CW 2009-05-30 0010 K1ABC 599 2 EF8M 599 9
+END-OF-LOG:
+";
+
+    const MULT: &str = "START-OF-LOG: 3.0
+CONTEST: CQ-WPX-CW
+CALLSIGN: K1ABC
+CATEGORY-OPERATOR: MULTI-OP
+QSO: 7005 CW 2009-05-30 0005 K1ABC 599 1 DL1AA 599 12
+END-OF-LOG:
+";
+
+    const HIGH: &str = "START-OF-LOG: 3.0
+CONTEST: CQ-WPX-CW
+CALLSIGN: K1ABC
+CATEGORY-POWER: HIGH
+QSO: 14005 CW 2009-05-30 0002 K1ABC 599 1 S50A 599 4
+QSO: 14010 CW 2009-05-30 0010 K1ABC 599 2 EF8M 599 9
+END-OF-LOG:
+";
+
+    const LOW: &str = "START-OF-LOG: 3.0
+CONTEST: CQ-WPX-CW
+CALLSIGN: K1ABC
+CATEGORY-POWER: LOW
+QSO: 14006 CW 2009-05-30 0003 K1ABC 599 1 S50A 599 4
+QSO: 14012 CW 2009-05-30 0011 K1ABC 599 2 EF8M 599 19
+END-OF-LOG:
+";
+
+    const THREE: [&str; 3] = [
+        "START-OF-LOG: 3.0
+CALLSIGN: K1ABC
+QSO: 14005 CW 2009-05-30 0002 K1ABC 599 1 S50A 599 4
+END-OF-LOG:
+",
+        "START-OF-LOG: 3.0
+CALLSIGN: K1ABC
+QSO: 7005 CW 2009-05-30 0003 K1ABC 599 1 EF8M 599 9 1
+END-OF-LOG:
+",
+        "START-OF-LOG: 3.0
+CALLSIGN: K1ABC
+QSO: 21005 CW 2009-05-30 0004 K1ABC 599 1 DL1AA 599 12 1
+END-OF-LOG:
+",
+    ];
+
+    #[test]
+    fn test_merge_orders_and_assigns_tx() {
+        let run = CabrilloLog::parse(RUN).unwrap();
+        let mult = CabrilloLog::parse(MULT).unwrap();
+        let merged = CabrilloLog::merge(&[run, mult], &MergeOptions::default());
+        let calls: Vec<_> = merged
+            .log
+            .qsos
+            .iter()
+            .map(|q| (q.rcvd_call.as_str(), q.tx.as_deref()))
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                ("S50A", Some("0")),
+                ("DL1AA", Some("1")),
+                ("EF8M", Some("0"))
+            ]
+        );
+        assert!(merged.log.headers.claimed_score().is_none());
+        assert_eq!(
+            merged.log.headers.category_operator(),
+            Some(CategoryOperator::MultiOp)
+        );
+        assert_eq!(
+            merged.log.headers.category_transmitter(),
+            Some(CategoryTransmitter::Two)
+        );
+        assert!(merged.issues.is_empty());
+    }
+
+    #[test]
+    fn test_merge_issues() {
+        let first = CabrilloLog::parse(HIGH).unwrap();
+        let second = CabrilloLog::parse(LOW).unwrap();
+        let merged = CabrilloLog::merge(&[first, second], &MergeOptions::default());
+        assert_eq!(merged.log.qsos.len(), 3);
+        assert_eq!(
+            merged.issues,
+            vec![
+                MergeIssue::HeaderConflict {
+                    tag: "CATEGORY-POWER".to_string(),
+                    values: vec!["HIGH".to_string(), "LOW".to_string()],
+                },
+                MergeIssue::DuplicateQso {
+                    kept: QsoSource { log: 0, qso: 0 },
+                    dropped: QsoSource { log: 1, qso: 0 },
+                },
+                MergeIssue::ConflictingQso {
+                    first: QsoSource { log: 0, qso: 1 },
+                    second: QsoSource { log: 1, qso: 1 },
+                },
+            ]
+        );
+        assert_eq!(
+            merged.issues[2].to_string(),
+            "log 1 QSO #2 and log 2 QSO #2 are the same contact but differ"
+        );
+    }
+
+    #[test]
+    fn test_merge_three_logs() {
+        let logs = THREE.map(|log| CabrilloLog::parse(log).unwrap());
+        let merged = CabrilloLog::merge(&logs, &MergeOptions::default());
+        assert_eq!(
+            merged.issues,
+            vec![MergeIssue::TooManyTransmitters { logs: 3 }]
+        );
+        assert_eq!(merged.issues[0].severity(), Severity::Error);
+        let txs: Vec<_> = merged.log.qsos.iter().map(|q| q.tx.as_deref()).collect();
+        assert_eq!(txs, vec![None, Some("1"), Some("1")]);
+        assert_eq!(
+            merged.log.headers.category_transmitter(),
+            Some(CategoryTransmitter::Unlimited)
+        );
+        assert!(merged.log.validate().is_ok());
+    }
+}
@@ -0,0 +1,283 @@
+//! QSO modes.
+//!
+//! A Cabrillo QSO line only records the mode category (`CW`, `PH`, `FM`, `RY`
+//! or `DG`), while ADIF and loggers record the precise mode (`FT8`, `PSK31`,
+//! `USB`, ...). [`Mode`] carries the category together with the precise mode
+//! when it is known; [`PRECISE_MODES`] maps precise modes to their category
+//! and ADIF MODE/SUBMODE pair.
+
+use crate::header::CategoryMode;
+use std::fmt;
+use std::str::FromStr;
+
+/// Mode category written in the mode column of a Cabrillo QSO line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum CabrilloMode {
+    #[default]
+    Cw,
+    Ph,
+    Fm,
+    Ry,
+    Dg,
+}
+
+impl CabrilloMode {
+    /// All categories, in specification order.
+    pub const ALL: &'static [CabrilloMode] = &[
+        CabrilloMode::Cw,
+        CabrilloMode::Ph,
+        CabrilloMode::Fm,
+        CabrilloMode::Ry,
+        CabrilloMode::Dg,
+    ];
+
+    /// The category as written in a Cabrillo file.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CabrilloMode::Cw => "CW",
+            CabrilloMode::Ph => "PH",
+            CabrilloMode::Fm => "FM",
+            CabrilloMode::Ry => "RY",
+            CabrilloMode::Dg => "DG",
+        }
+    }
+}
+
+impl FromStr for CabrilloMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        CabrilloMode::ALL
+            .iter()
+            .find(|m| m.as_str().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or(())
+    }
+}
+
+impl fmt::Display for CabrilloMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+serde_as_str!(CabrilloMode);
+
+/// A precise mode and how it is represented in Cabrillo and ADIF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModeDef {
+    /// Precise mode name, e.g. `FT4`.
+    pub name: &'static str,
+    /// ADIF MODE value.
+    pub adif_mode: &'static str,
+    /// ADIF SUBMODE value, when the precise mode is an ADIF submode.
+    pub adif_submode: Option<&'static str>,
+    pub category: CabrilloMode,
+}
+
+const fn def(
+    name: &'static str,
+    adif_mode: &'static str,
+    adif_submode: Option<&'static str>,
+    category: CabrilloMode,
+) -> ModeDef {
+    ModeDef {
+        name,
+        adif_mode,
+        adif_submode,
+        category,
+    }
+}
+
+/// Known precise modes.
+pub static PRECISE_MODES: &[ModeDef] = &[
+    def("CW", "CW", None, CabrilloMode::Cw),
+    def("SSB", "SSB", None, CabrilloMode::Ph),
+    def("USB", "SSB", Some("USB"), CabrilloMode::Ph),
+    def("LSB", "SSB", Some("LSB"), CabrilloMode::Ph),
+    def("AM", "AM", None, CabrilloMode::Ph),
+    def("DIGITALVOICE", "DIGITALVOICE", None, CabrilloMode::Ph),
+    def("FREEDV", "DIGITALVOICE", Some("FREEDV"), CabrilloMode::Ph),
+    def("FM", "FM", None, CabrilloMode::Fm),
+    def("RTTY", "RTTY", None, CabrilloMode::Ry),
+    def("FT8", "FT8", None, CabrilloMode::Dg),
+    def("FT4", "MFSK", Some("FT4"), CabrilloMode::Dg),
+    def("JS8", "MFSK", Some("JS8"), CabrilloMode::Dg),
+    def("Q65", "MFSK", Some("Q65"), CabrilloMode::Dg),
+    def("MFSK16", "MFSK", Some("MFSK16"), CabrilloMode::Dg),
+    def("MFSK", "MFSK", None, CabrilloMode::Dg),
+    def("PSK31", "PSK", Some("PSK31"), CabrilloMode::Dg),
+    def("PSK63", "PSK", Some("PSK63"), CabrilloMode::Dg),
+    def("PSK", "PSK", None, CabrilloMode::Dg),
+    def("JT65", "JT65", None, CabrilloMode::Dg),
+    def("JT9", "JT9", None, CabrilloMode::Dg),
+    def("MSK144", "MSK144", None, CabrilloMode::Dg),
+    def("FSK441", "FSK441", None, CabrilloMode::Dg),
+    def("OLIVIA", "OLIVIA", None, CabrilloMode::Dg),
+    def("CONTESTI", "CONTESTI", None, CabrilloMode::Dg),
+    def("PKT", "PKT", None, CabrilloMode::Dg),
+    def("SSTV", "SSTV", None, CabrilloMode::Dg),
+];
+
+/// Find a precise mode by name.
+fn lookup(name: &str) -> Option<&'static ModeDef> {
+    let name = name.trim();
+    PRECISE_MODES
+        .iter()
+        .find(|m| m.name.eq_ignore_ascii_case(name))
+}
+
+/// Mode of a QSO: the Cabrillo category and, when known, the precise mode.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mode {
+    pub category: CabrilloMode,
+    /// Precise mode in upper case, e.g. `FT8`.
+    pub precise: Option<String>,
+}
+
+impl Mode {
+    /// A mode known only by its Cabrillo category.
+    pub fn new(category: CabrilloMode) -> Self {
+        Mode {
+            category,
+            precise: None,
+        }
+    }
+
+    /// A precise mode, categorized by [`PRECISE_MODES`]. Unknown modes are taken to be digital.
+    pub fn precise(name: &str) -> Self {
+        let name = name.trim().to_ascii_uppercase();
+        let category = lookup(&name).map_or(CabrilloMode::Dg, |m| m.category);
+        Mode {
+            category,
+            precise: Some(name),
+        }
+    }
+
+    /// The mode of an ADIF MODE/SUBMODE pair.
+    pub fn from_adif(mode: &str, submode: Option<&str>) -> Self {
+        match submode.filter(|s| !s.trim().is_empty()) {
+            Some(submode) if lookup(submode).is_some() || lookup(mode).is_none() => {
+                Mode::precise(submode)
+            }
+            _ => Mode::precise(mode),
+        }
+    }
+
+    /// ADIF MODE and SUBMODE for this mode.
+    ///
+    /// ADIF has no generic digital mode, so a bare `DG` is written as `MFSK`.
+    pub fn adif(&self) -> (&str, Option<&str>) {
+        match self.precise.as_deref() {
+            Some(precise) => match lookup(precise) {
+                Some(def) => (def.adif_mode, def.adif_submode),
+                None => (precise, None),
+            },
+            None => match self.category {
+                CabrilloMode::Cw => ("CW", None),
+                CabrilloMode::Ph => ("SSB", None),
+                CabrilloMode::Fm => ("FM", None),
+                CabrilloMode::Ry => ("RTTY", None),
+                CabrilloMode::Dg => ("MFSK", None),
+            },
+        }
+    }
+
+    /// The precise mode if known, otherwise the Cabrillo category.
+    pub fn precise_or_category(&self) -> &str {
+        self.precise
+            .as_deref()
+            .unwrap_or_else(|| self.category.as_str())
+    }
+}
+
+impl From<CabrilloMode> for Mode {
+    fn from(category: CabrilloMode) -> Self {
+        Mode::new(category)
+    }
+}
+
+impl FromStr for Mode {
+    type Err = ();
+
+    /// Parses a Cabrillo category, or a known precise mode as written by some loggers.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(category) = s.parse() {
+            return Ok(Mode::new(category));
+        }
+        lookup(s).map(|def| Mode::precise(def.name)).ok_or(())
+    }
+}
+
+impl fmt::Display for Mode {
+    /// Writes the Cabrillo category, as in the mode column of a QSO line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.category.fmt(f)
+    }
+}
+
+impl PartialEq<CabrilloMode> for Mode {
+    fn eq(&self, other: &CabrilloMode) -> bool {
+        self.category == *other
+    }
+}
+
+impl CategoryMode {
+    /// The narrowest category that allows QSOs in the given mode.
+    pub fn for_mode(mode: CabrilloMode) -> Self {
+        match mode {
+            CabrilloMode::Cw => CategoryMode::Cw,
+            CabrilloMode::Ph => CategoryMode::Ssb,
+            CabrilloMode::Fm => CategoryMode::Fm,
+            CabrilloMode::Ry => CategoryMode::Rtty,
+            CabrilloMode::Dg => CategoryMode::Digi,
+        }
+    }
+
+    /// Whether QSOs in the given mode fit this category.
+    ///
+    /// `SSB` covers all phone QSOs including FM, and `DIGI` covers RTTY as well.
+    pub fn allows(&self, mode: CabrilloMode) -> bool {
+        match self {
+            CategoryMode::Cw => mode == CabrilloMode::Cw,
+            CategoryMode::Ssb => matches!(mode, CabrilloMode::Ph | CabrilloMode::Fm),
+            CategoryMode::Fm => mode == CabrilloMode::Fm,
+            CategoryMode::Rtty => mode == CabrilloMode::Ry,
+            CategoryMode::Digi => matches!(mode, CabrilloMode::Ry | CabrilloMode::Dg),
+            CategoryMode::Mixed => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mode_column() {
+        assert_eq!("CW".parse(), Ok(Mode::new(CabrilloMode::Cw)));
+        assert_eq!("dg".parse(), Ok(Mode::new(CabrilloMode::Dg)));
+        let ft8: Mode = "FT8".parse().unwrap();
+        assert_eq!(ft8.category, CabrilloMode::Dg);
+        assert_eq!(ft8.to_string(), "DG");
+        assert_eq!("USB".parse::<Mode>().unwrap(), CabrilloMode::Ph);
+        assert_eq!("WSPR-ish".parse::<Mode>(), Err(()));
+    }
+
+    #[test]
+    fn test_adif_mapping() {
+        let ft4 = Mode::from_adif("MFSK", Some("FT4"));
+        assert_eq!(ft4.precise.as_deref(), Some("FT4"));
+        assert_eq!(ft4.adif(), ("MFSK", Some("FT4")));
+
+        let usb = Mode::from_adif("SSB", Some("USB"));
+        assert_eq!(usb, CabrilloMode::Ph);
+        assert_eq!(usb.adif(), ("SSB", Some("USB")));
+
+        assert_eq!(Mode::from_adif("RTTY", None).adif(), ("RTTY", None));
+        assert_eq!(Mode::precise("VARA").adif(), ("VARA", None));
+        assert_eq!(Mode::new(CabrilloMode::Ph).adif(), ("SSB", None));
+    }
+}
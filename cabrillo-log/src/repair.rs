@@ -0,0 +1,375 @@
+//! Lenient repair of malformed logs.
+//!
+//! Logs typed by hand or exported by old loggers often have lower case calls,
+//! `MM/DD/YYYY` dates, `12:05` times, tabs, missing TX IDs or junk after the
+//! last column. [`CabrilloLog::repair`] fixes these line by line before parsing
+//! and records every change, so a log is never edited by hand and the submitter
+//! can be told exactly what was changed.
+
+use crate::diagnostic::{Diagnostic, ParseReport};
+use crate::layout::HEADER_ORDER;
+use crate::{CabrilloLog, ContestTemplate, Header, TemplateRegistry};
+use chrono::{NaiveDate, NaiveTime};
+use std::fmt;
+
+/// What was fixed on a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RepairKind {
+    /// Tabs replaced by spaces.
+    Whitespace,
+    /// Tag, mode, calls or exchange put in upper case.
+    Case,
+    /// Date rewritten as `YYYY-MM-DD`.
+    Date,
+    /// Time rewritten as `HHMM`.
+    Time,
+    /// TX ID `0` added to a QSO of a log that has TX IDs elsewhere.
+    TxId,
+    /// Tokens after the last column of the contest template removed.
+    TrailingGarbage,
+    /// Line after `END-OF-LOG:` removed.
+    AfterEndOfLog,
+}
+
+impl fmt::Display for RepairKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RepairKind::Whitespace => "tabs",
+            RepairKind::Case => "upper case",
+            RepairKind::Date => "date format",
+            RepairKind::Time => "time format",
+            RepairKind::TxId => "missing TX ID",
+            RepairKind::TrailingGarbage => "trailing garbage",
+            RepairKind::AfterEndOfLog => "after END-OF-LOG",
+        })
+    }
+}
+
+/// A repaired line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Repair {
+    pub line: usize, // 1-based line number
+    pub original: String,
+    /// Replacement text; empty if the line was removed.
+    pub fixed: String,
+    pub kinds: Vec<RepairKind>,
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kinds: Vec<_> = self.kinds.iter().map(ToString::to_string).collect();
+        write!(
+            f,
+            "line {} ({}):\n  - {}\n  + {}",
+            self.line,
+            kinds.join(", "),
+            self.original,
+            self.fixed
+        )
+    }
+}
+
+/// Result of [`CabrilloLog::repair`]: the log parsed from the repaired text,
+/// what is still wrong with it and every change made.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RepairReport {
+    /// The repaired text the log was parsed from.
+    pub repaired: String,
+    pub log: CabrilloLog,
+    /// Problems left after repair; lines are numbered as in the original text.
+    pub diagnostics: Vec<Diagnostic>,
+    pub repairs: Vec<Repair>,
+}
+
+impl RepairReport {
+    /// Whether problems that could not be repaired are left.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == crate::Severity::Error)
+    }
+
+    /// The audit report: every repaired line with its original and fixed text.
+    pub fn render_repairs(&self) -> String {
+        self.repairs.iter().map(|r| format!("{}\n", r)).collect()
+    }
+}
+
+impl CabrilloLog {
+    /// Repair common formatting problems, then parse the result like
+    /// [`CabrilloLog::parse_with_diagnostics`].
+    ///
+    /// Lines are repaired independently and keep their line numbers. TX IDs and
+    /// trailing garbage are only fixed when the contest template is known, as
+    /// the number of columns is otherwise unknown.
+    pub fn repair(content: &str, templates: &TemplateRegistry) -> RepairReport {
+        let lines: Vec<&str> = content.lines().collect();
+        let template = lines
+            .iter()
+            .take_while(|line| !is_qso_line(line))
+            .filter_map(|line| line.split_once(':'))
+            .find(|(tag, _)| tag.trim().eq_ignore_ascii_case("CONTEST"))
+            .and_then(|(_, contest)| templates.lookup(contest));
+        let uses_tx = template.is_some_and(|template| {
+            lines
+                .iter()
+                .filter(|line| is_qso_line(line))
+                .any(|line| has_tx_id(line, template))
+        });
+
+        let mut repairs = Vec::new();
+        let mut repaired = String::with_capacity(content.len());
+        let mut ended = false;
+        for (index, &line) in lines.iter().enumerate() {
+            let (fixed, kinds) = if ended && !line.trim().is_empty() {
+                (String::new(), vec![RepairKind::AfterEndOfLog])
+            } else if is_qso_line(line) {
+                repair_qso_line(line, template, uses_tx)
+            } else if line.trim_start().starts_with('#') {
+                (line.to_string(), Vec::new())
+            } else {
+                repair_header_line(line)
+            };
+            ended |= line.trim_start().starts_with("END-OF-LOG:");
+            if !kinds.is_empty() {
+                repairs.push(Repair {
+                    line: index + 1,
+                    original: line.to_string(),
+                    fixed: fixed.clone(),
+                    kinds,
+                });
+            }
+            repaired.push_str(&fixed);
+            repaired.push('\n');
+        }
+
+        let ParseReport { log, diagnostics } =
+            CabrilloLog::parse_with_diagnostics(&repaired, templates);
+        RepairReport {
+            repaired,
+            log,
+            diagnostics,
+            repairs,
+        }
+    }
+}
+
+/// Whether a line is a QSO or X-QSO line, in any case.
+fn is_qso_line(line: &str) -> bool {
+    let upper = line.trim_start().to_ascii_uppercase();
+    upper.starts_with("QSO:") || upper.starts_with("X-QSO:")
+}
+
+/// Whether a QSO line ends in a TX ID after all of the template's columns.
+fn has_tx_id(line: &str, template: &ContestTemplate) -> bool {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    tokens.len() == 5 + template.token_count() + 1 && matches!(tokens.last(), Some(&"0" | &"1"))
+}
+
+fn repair_qso_line(
+    line: &str,
+    template: Option<&ContestTemplate>,
+    uses_tx: bool,
+) -> (String, Vec<RepairKind>) {
+    let mut kinds = Vec::new();
+    if line.contains('\t') {
+        kinds.push(RepairKind::Whitespace);
+    }
+    let mut tokens: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+    if tokens.len() < 5 {
+        return (line.replace('\t', " "), kinds);
+    }
+
+    // Cut trailing garbage first so that it does not count as a case repair
+    let mut trailing_garbage = false;
+    let expected = template.map(|template| 5 + template.token_count());
+    if let Some(expected) = expected
+        && tokens.len() > expected
+    {
+        let keep = if tokens[expected] == "0" || tokens[expected] == "1" {
+            expected + 1
+        } else {
+            expected
+        };
+        trailing_garbage = tokens.len() > keep;
+        tokens.truncate(keep);
+    }
+
+    let mut upper_case = false;
+    for (i, token) in tokens.iter_mut().enumerate() {
+        // The frequency and date columns have no letters worth normalizing
+        if i != 1 && i != 3 && token.chars().any(|c| c.is_ascii_lowercase()) {
+            *token = token.to_ascii_uppercase();
+            upper_case = true;
+        }
+    }
+    if upper_case {
+        kinds.push(RepairKind::Case);
+    }
+    if let Some(date) = normalize_date(&tokens[3]) {
+        tokens[3] = date;
+        kinds.push(RepairKind::Date);
+    }
+    if let Some(time) = normalize_time(&tokens[4]) {
+        tokens[4] = time;
+        kinds.push(RepairKind::Time);
+    }
+    if uses_tx && Some(tokens.len()) == expected {
+        tokens.push("0".to_string());
+        kinds.push(RepairKind::TxId);
+    }
+    if trailing_garbage {
+        kinds.push(RepairKind::TrailingGarbage);
+    }
+
+    if kinds.is_empty() {
+        (line.to_string(), kinds)
+    } else {
+        (tokens.join(" "), kinds)
+    }
+}
+
+fn repair_header_line(line: &str) -> (String, Vec<RepairKind>) {
+    let mut kinds = Vec::new();
+    let mut fixed = line.to_string();
+    if fixed.contains('\t') {
+        fixed = fixed.replace('\t', " ");
+        kinds.push(RepairKind::Whitespace);
+    }
+    if let Some((tag, value)) = fixed.split_once(':') {
+        // Unknown and X- tags are looked up as written, so only known tags are upper cased
+        let known = HEADER_ORDER
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(tag.trim()));
+        let upper_tag = if known {
+            tag.to_ascii_uppercase()
+        } else {
+            tag.to_string()
+        };
+        let upper_value = match Header::parse(tag, value) {
+            Header::Callsign(_) => value.to_ascii_uppercase(),
+            _ => value.to_string(),
+        };
+        if upper_tag != tag || upper_value != value {
+            fixed = format!("{}:{}", upper_tag, upper_value);
+            kinds.push(RepairKind::Case);
+        }
+    }
+    (fixed, kinds)
+}
+
+/// `YYYY-MM-DD` for a date in another common format, `None` if it needs no repair.
+fn normalize_date(date: &str) -> Option<String> {
+    if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() {
+        return None;
+    }
+    ["%m/%d/%Y", "%Y/%m/%d", "%Y%m%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// `HHMM` for a time in another common format, `None` if it needs no repair.
+fn normalize_time(time: &str) -> Option<String> {
+    if time.len() == 4 && NaiveTime::parse_from_str(time, "%H%M").is_ok() {
+        return None;
+    }
+    let padded = format!("{:0>4}", time);
+    ["%H:%M", "%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(time, format).ok())
+        .or_else(|| {
+            (time.len() == 3)
+                .then(|| NaiveTime::parse_from_str(&padded, "%H%M").ok())
+                .flatten()
+        })
+        .map(|time| time.format("%H%M").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSY: &str = "START-OF-LOG: 3.0
+callsign: aa1zzz
+CONTEST: CQ-WPX-CW
+QSO: 7005 cw 05/30/2009 00:02 aa1zzz 599 1 s50a 599 4 0
+QSO:\t7006\tCW\t2009-05-30\t0015\tAA1ZZZ\t599\t2\tEF8M\t599\t34
+QSO: 7010 CW 2009-05-30 920 AA1ZZZ 599 3 K1ABC 599 5 0 tnx 73
+END-OF-LOG:
+sent from my phone
+";
+
+    #[test]
+    fn test_repairs_messy_log() {
+        let report = CabrilloLog::repair(MESSY, &TemplateRegistry::default());
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(report.log.headers.callsign(), Some("AA1ZZZ"));
+        assert_eq!(report.log.qsos.len(), 3);
+        assert_eq!(report.log.qsos[0].rcvd_call, "S50A");
+        assert_eq!(
+            report.log.qsos[0].datetime().to_string(),
+            "2009-05-30 00:02:00"
+        );
+        assert_eq!(report.log.qsos[1].tx.as_deref(), Some("0"));
+        assert_eq!(report.log.qsos[2].time.to_string(), "09:20:00");
+
+        let found: Vec<_> = report
+            .repairs
+            .iter()
+            .map(|r| (r.line, r.kinds.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (2, vec![RepairKind::Case]),
+                (
+                    4,
+                    vec![RepairKind::Case, RepairKind::Date, RepairKind::Time]
+                ),
+                (5, vec![RepairKind::Whitespace, RepairKind::TxId]),
+                (6, vec![RepairKind::Time, RepairKind::TrailingGarbage]),
+                (8, vec![RepairKind::AfterEndOfLog]),
+            ]
+        );
+        assert_eq!(
+            report.repairs[1].fixed,
+            "QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4 0"
+        );
+    }
+
+    #[test]
+    fn test_unknown_tags_keep_case() {
+        let content = "START-OF-LOG: 3.0\nContest: CQ-WPX-CW\nx-club-id: 7\nEND-OF-LOG:\n";
+        let report = CabrilloLog::repair(content, &TemplateRegistry::default());
+        assert_eq!(report.repairs.len(), 1);
+        assert_eq!(report.repairs[0].fixed, "CONTEST: CQ-WPX-CW");
+        assert_eq!(report.log.headers.get("x-club-id").as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn test_clean_log_is_untouched() {
+        let clean = "START-OF-LOG: 3.0\nCONTEST: CQ-WPX-CW\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4\nEND-OF-LOG:\n";
+        let report = CabrilloLog::repair(clean, &TemplateRegistry::default());
+        assert!(report.repairs.is_empty());
+        assert_eq!(report.log, CabrilloLog::parse(clean).unwrap());
+    }
+
+    #[test]
+    fn test_render_repairs() {
+        let report = CabrilloLog::repair(
+            "START-OF-LOG: 3.0\nQSO: 14000 CW 2023-10-01 12:05 N1MM 599 001 W1AW 599 001\n",
+            &TemplateRegistry::default(),
+        );
+        assert_eq!(
+            report.render_repairs(),
+            "line 2 (time format):
+  - QSO: 14000 CW 2023-10-01 12:05 N1MM 599 001 W1AW 599 001
+  + QSO: 14000 CW 2023-10-01 1205 N1MM 599 001 W1AW 599 001
+"
+        );
+    }
+}
@@ -0,0 +1,528 @@
+//! Contest scoring.
+//!
+//! Each contest has its own scoring rules: how many points a QSO is worth,
+//! what counts as a multiplier and how points and multipliers make up the
+//! score. [`ContestRules`] describes them and [`CabrilloLog::score`] applies
+//! them to a log, with QSO points per QSO, the multipliers worked and a
+//! breakdown per band.
+//!
+//! Most rules depend on where the stations are: continent, DXCC entity, zone.
+//! That comes from a [`StationLookup`], such as the one of the `enricher`
+//! crate; any function from [`Callsign`] to [`StationInfo`] is one.
+//!
+//! ```rust
+//! use cabrillo_log::{CabrilloLog, Callsign, StationInfo, rules_for_contest};
+//!
+//! let log = CabrilloLog::parse("START-OF-LOG: 3.0\nCALLSIGN: AA1ZZZ\nCONTEST: CQ-WPX-CW\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4\nEND-OF-LOG:\n").unwrap();
+//! let lookup = |call: &Callsign| {
+//!     let (dxcc, continent) = if call.base().starts_with('S') { (499, "EU") } else { (291, "NA") };
+//!     Some(StationInfo { dxcc, continent: continent.to_string(), ..StationInfo::default() })
+//! };
+//! let rules = rules_for_contest("CQ-WPX-CW").unwrap();
+//! let report = log.score(rules, &lookup);
+//! assert_eq!(report.qsos[0].points, 6);
+//! assert_eq!(report.score, 6);
+//! assert!(log.check_consistency(Some(report.score)).is_empty());
+//! ```
+
+use crate::{Band, CabrilloLog, Callsign, DupeScope, FieldKind, QSO};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Where a station is, as far as scoring rules care.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StationInfo {
+    /// DXCC entity number.
+    pub dxcc: u32,
+    pub country: String,
+    /// Two-letter continent code, e.g. `EU` or `NA`.
+    pub continent: String,
+    pub cq_zone: u32,
+    pub itu_zone: u32,
+}
+
+/// Source of [`StationInfo`] for callsigns.
+pub trait StationLookup {
+    fn lookup(&self, call: &Callsign) -> Option<StationInfo>;
+}
+
+impl<F: Fn(&Callsign) -> Option<StationInfo>> StationLookup for F {
+    fn lookup(&self, call: &Callsign) -> Option<StationInfo> {
+        self(call)
+    }
+}
+
+/// A QSO as seen by the scoring rules.
+#[derive(Debug, Clone, Copy)]
+pub struct QsoContext<'a> {
+    pub qso: &'a QSO,
+    /// The station that submitted the log; `None` if its call is unknown.
+    pub station: Option<&'a StationInfo>,
+    /// The station worked; `None` if its call is unknown.
+    pub worked: Option<&'a StationInfo>,
+    /// The received call, if it parses.
+    pub worked_call: Option<&'a Callsign>,
+}
+
+impl QsoContext<'_> {
+    /// Whether both stations are known to be on the same continent.
+    pub fn same_continent(&self) -> Option<bool> {
+        Some(self.station?.continent == self.worked?.continent)
+    }
+
+    /// Whether both stations are known to be in the same DXCC entity.
+    pub fn same_country(&self) -> Option<bool> {
+        Some(self.station?.dxcc == self.worked?.dxcc)
+    }
+}
+
+/// A multiplier: what it counts (`prefix`, `zone`, `country`, ...), its value
+/// and, for multipliers counted once per band, the band.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Multiplier {
+    pub kind: String,
+    pub value: String,
+    pub band: Option<Band>,
+    /// Name shown instead of the value, e.g. the country of a DXCC entity
+    /// number; multipliers with the same value count once whatever their name.
+    pub name: Option<String>,
+}
+
+impl Multiplier {
+    /// A multiplier counted once in the whole contest.
+    pub fn new(kind: &str, value: impl Into<String>) -> Self {
+        Multiplier {
+            kind: kind.to_string(),
+            value: value.into(),
+            band: None,
+            name: None,
+        }
+    }
+
+    /// A multiplier counted once per band.
+    pub fn per_band(kind: &str, value: impl Into<String>, band: Option<Band>) -> Self {
+        Multiplier {
+            band,
+            ..Self::new(kind, value)
+        }
+    }
+
+    /// Show the multiplier by `name`, unless it is empty.
+    pub fn named(self, name: &str) -> Self {
+        Multiplier {
+            name: Some(name.to_string()).filter(|n| !n.is_empty()),
+            ..self
+        }
+    }
+
+    /// What makes two multipliers the same one.
+    fn key(&self) -> (String, String, Option<Band>) {
+        (self.kind.clone(), self.value.clone(), self.band)
+    }
+}
+
+impl fmt::Display for Multiplier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.kind,
+            self.name.as_deref().unwrap_or(&self.value)
+        )?;
+        if let Some(band) = self.band {
+            write!(f, " on {}", band.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// Scoring rules of a contest.
+pub trait ContestRules {
+    /// Which repeat QSOs are dupes; dupes score no points and no multipliers.
+    fn dupe_scope(&self) -> DupeScope;
+
+    /// Points for a QSO that is not a dupe.
+    fn qso_points(&self, qso: &QsoContext) -> u32;
+
+    /// Multipliers a QSO counts towards; each multiplier counts once.
+    fn multipliers(&self, qso: &QsoContext) -> Vec<Multiplier>;
+
+    /// The final score from the QSO point total and the number of multipliers.
+    fn score(&self, points: u64, multipliers: usize) -> u64 {
+        points * multipliers as u64
+    }
+}
+
+/// Whether a band is one of the low bands scoring double in CQ contests.
+fn is_low_band(qso: &QSO) -> bool {
+    matches!(qso.freq.band(), Some(Band::B160M | Band::B80M | Band::B40M))
+}
+
+/// CQ WPX rules: 3 points between continents and 1 point within a continent,
+/// double on 40, 80 and 160 m; 2 points between North American countries;
+/// 1 point within a country. Each prefix is a multiplier once.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CqWpx;
+
+impl ContestRules for CqWpx {
+    fn dupe_scope(&self) -> DupeScope {
+        DupeScope::PerBand
+    }
+
+    fn qso_points(&self, qso: &QsoContext) -> u32 {
+        let (Some(same_continent), Some(same_country)) = (qso.same_continent(), qso.same_country())
+        else {
+            return 0;
+        };
+        let low = if is_low_band(qso.qso) { 2 } else { 1 };
+        if same_country {
+            1
+        } else if !same_continent {
+            3 * low
+        } else if qso.station.is_some_and(|s| s.continent == "NA") {
+            2 * low
+        } else {
+            low
+        }
+    }
+
+    fn multipliers(&self, qso: &QsoContext) -> Vec<Multiplier> {
+        qso.worked_call
+            .map(|call| Multiplier::new("prefix", call.wpx_prefix()))
+            .into_iter()
+            .collect()
+    }
+}
+
+/// CQ World Wide DX rules: 3 points between continents, 1 point within a
+/// continent, 2 points between North American countries and none within a
+/// country. CQ zones and DXCC entities are multipliers once per band; entities
+/// count by their DXCC number.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CqWw;
+
+impl ContestRules for CqWw {
+    fn dupe_scope(&self) -> DupeScope {
+        DupeScope::PerBand
+    }
+
+    fn qso_points(&self, qso: &QsoContext) -> u32 {
+        match (qso.same_continent(), qso.same_country()) {
+            (_, Some(true)) | (None, _) | (_, None) => 0,
+            (Some(false), _) => 3,
+            (Some(true), _) if qso.station.is_some_and(|s| s.continent == "NA") => 2,
+            (Some(true), _) => 1,
+        }
+    }
+
+    fn multipliers(&self, qso: &QsoContext) -> Vec<Multiplier> {
+        let band = qso.qso.freq.band();
+        let mut mults = Vec::new();
+        if let Some(zone) = qso.qso.rcvd_exch.first_of(FieldKind::Zone)
+            && let Ok(zone) = zone.value.parse::<u32>()
+        {
+            mults.push(Multiplier::per_band("zone", zone.to_string(), band));
+        }
+        if let Some(worked) = qso.worked {
+            mults.push(
+                Multiplier::per_band("country", worked.dxcc.to_string(), band)
+                    .named(&worked.country),
+            );
+        }
+        mults
+    }
+}
+
+/// Rules for a `CONTEST:` value, for the contests with built-in rules.
+pub fn rules_for_contest(contest: &str) -> Option<&'static dyn ContestRules> {
+    match contest.trim().to_ascii_uppercase().as_str() {
+        "CQ-WPX-CW" | "CQ-WPX-SSB" | "CQ-WPX-RTTY" => Some(&CqWpx),
+        "CQ-WW-CW" | "CQ-WW-SSB" => Some(&CqWw),
+        _ => None,
+    }
+}
+
+/// Score of a single QSO, with its index in [`CabrilloLog::qsos`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QsoScore {
+    pub qso: usize,
+    pub points: u32,
+    /// Multipliers first worked in this QSO.
+    pub new_multipliers: Vec<Multiplier>,
+    pub dupe: bool,
+}
+
+/// Totals for one band; `band` is `None` for frequencies outside the bands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BandScore {
+    pub band: Option<Band>,
+    pub qsos: usize,
+    pub dupes: usize,
+    pub points: u64,
+    /// Multipliers first worked on this band.
+    pub multipliers: usize,
+}
+
+/// Result of [`CabrilloLog::score`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScoreReport {
+    /// Scores of the counted QSOs in log order; X-QSOs are left out.
+    pub qsos: Vec<QsoScore>,
+    /// All multipliers, in the order they were worked.
+    pub multipliers: Vec<Multiplier>,
+    /// Totals per band, lowest band first.
+    pub bands: Vec<BandScore>,
+    pub points: u64,
+    /// The claimed score, for `CLAIMED-SCORE:`.
+    pub score: u64,
+}
+
+impl fmt::Display for ScoreReport {
+    /// A table of the band totals followed by the score.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<6} {:>6} {:>6} {:>7} {:>6}",
+            "Band", "QSOs", "Dupes", "Points", "Mults"
+        )?;
+        for band in &self.bands {
+            writeln!(
+                f,
+                "{:<6} {:>6} {:>6} {:>7} {:>6}",
+                band.band.map_or("?", |b| b.name()),
+                band.qsos,
+                band.dupes,
+                band.points,
+                band.multipliers
+            )?;
+        }
+        writeln!(
+            f,
+            "{:<6} {:>6} {:>6} {:>7} {:>6}",
+            "Total",
+            self.qsos.len(),
+            self.qsos.iter().filter(|q| q.dupe).count(),
+            self.points,
+            self.multipliers.len()
+        )?;
+        writeln!(f, "Score: {}", self.score)
+    }
+}
+
+impl CabrilloLog {
+    /// Score the log by `rules`, looking up stations with `lookup`.
+    ///
+    /// Dupes by the rules' dupe scope score no points and count for no
+    /// multipliers. QSOs marked zero-point score no points but still count
+    /// for multipliers. X-QSOs are not scored.
+    pub fn score(&self, rules: &dyn ContestRules, lookup: &dyn StationLookup) -> ScoreReport {
+        let own_call = self
+            .headers
+            .callsign()
+            .or_else(|| self.qsos.first().map(|q| q.sent_call.as_str()))
+            .and_then(|call| Callsign::parse(call).ok());
+        let station = own_call.as_ref().and_then(|call| lookup.lookup(call));
+        let dupes: HashSet<usize> = self
+            .find_dupes(rules.dupe_scope())
+            .iter()
+            .map(|d| d.dupe)
+            .collect();
+
+        let mut qsos = Vec::new();
+        let mut worked = HashSet::new();
+        let mut multipliers = Vec::new();
+        let mut bands: BTreeMap<Option<Band>, BandScore> = BTreeMap::new();
+        for (index, qso) in self.qsos.iter().enumerate() {
+            if qso.excluded {
+                continue;
+            }
+            let band = qso.freq.band();
+            let totals = bands.entry(band).or_insert(BandScore {
+                band,
+                qsos: 0,
+                dupes: 0,
+                points: 0,
+                multipliers: 0,
+            });
+            totals.qsos += 1;
+            let dupe = dupes.contains(&index);
+            if dupe {
+                totals.dupes += 1;
+                qsos.push(QsoScore {
+                    qso: index,
+                    points: 0,
+                    new_multipliers: Vec::new(),
+                    dupe,
+                });
+                continue;
+            }
+
+            let worked_call = Callsign::parse(&qso.rcvd_call).ok();
+            let worked_info = worked_call.as_ref().and_then(|call| lookup.lookup(call));
+            let context = QsoContext {
+                qso,
+                station: station.as_ref(),
+                worked: worked_info.as_ref(),
+                worked_call: worked_call.as_ref(),
+            };
+            let points = if qso.zero_point {
+                0
+            } else {
+                rules.qso_points(&context)
+            };
+            let new_multipliers: Vec<_> = rules
+                .multipliers(&context)
+                .into_iter()
+                .filter(|mult| worked.insert(mult.key()))
+                .collect();
+            totals.points += u64::from(points);
+            totals.multipliers += new_multipliers.len();
+            multipliers.extend(new_multipliers.iter().cloned());
+            qsos.push(QsoScore {
+                qso: index,
+                points,
+                new_multipliers,
+                dupe,
+            });
+        }
+
+        let points = qsos.iter().map(|q| u64::from(q.points)).sum();
+        ScoreReport {
+            score: rules.score(points, multipliers.len()),
+            qsos,
+            multipliers,
+            bands: bands.into_values().collect(),
+            points,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WPX: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4
+QSO: 14005 CW 2009-05-30 0010 AA1ZZZ 599 2 S50A 599 9
+QSO: 14010 CW 2009-05-30 0020 AA1ZZZ 599 3 VE3XX 599 5
+QSO: 14012 CW 2009-05-30 0030 AA1ZZZ 599 4 W1AW 599 6
+QSO: 14014 CW 2009-05-30 0040 AA1ZZZ 599 5 JA1AA 599 7
+QSO: 14016 CW 2009-05-30 0050 AA1ZZZ 599 6 JA1AA 599 7
+X-QSO: 21010 CW 2009-05-30 0055 AA1ZZZ 599 7 DL1AA 599 8
+END-OF-LOG:
+";
+
+    const CQ_WW: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WW-CW
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 5 S50A 599 15
+QSO: 14005 CW 2009-05-30 0010 AA1ZZZ 599 5 S50A 599 15
+QSO: 14010 CW 2009-05-30 0020 AA1ZZZ 599 5 DL1AA 599 14
+QSO: 14012 CW 2009-05-30 0030 AA1ZZZ 599 5 W1AW 599 5
+QSO: 14014 CW 2009-05-30 0040 AA1ZZZ 599 5 VE3XX 599 4
+END-OF-LOG:
+";
+
+    /// Stations by the first letter of their call.
+    fn lookup(call: &Callsign) -> Option<StationInfo> {
+        let (dxcc, country, continent, cq_zone) = match call.base().chars().next()? {
+            'K' | 'W' | 'A' => (291, "United States", "NA", 5),
+            'V' => (1, "Canada", "NA", 4),
+            'D' => (230, "Germany", "EU", 14),
+            'S' => (499, "Slovenia", "EU", 15),
+            'J' => (339, "Japan", "AS", 25),
+            _ => return None,
+        };
+        Some(StationInfo {
+            dxcc,
+            country: country.to_string(),
+            continent: continent.to_string(),
+            cq_zone,
+            itu_zone: 0,
+        })
+    }
+
+    #[test]
+    fn test_wpx_score() {
+        let log = CabrilloLog::parse(WPX).unwrap();
+        let report = log.score(rules_for_contest("CQ-WPX-CW").unwrap(), &lookup);
+        let points: Vec<_> = report.qsos.iter().map(|q| q.points).collect();
+        assert_eq!(points, vec![6, 3, 2, 1, 3, 0]);
+        assert!(report.qsos[5].dupe);
+        let mults: Vec<_> = report
+            .multipliers
+            .iter()
+            .map(|m| m.value.as_str())
+            .collect();
+        assert_eq!(mults, vec!["S50", "VE3", "W1", "JA1"]);
+        assert_eq!(report.points, 15);
+        assert_eq!(report.score, 60);
+
+        assert_eq!(report.bands.len(), 2);
+        assert_eq!(
+            report.bands[1],
+            BandScore {
+                band: Some(Band::B20M),
+                qsos: 5,
+                dupes: 1,
+                points: 9,
+                multipliers: 3
+            }
+        );
+        assert!(
+            report
+                .to_string()
+                .ends_with("Total       6      1      15      4\nScore: 60\n")
+        );
+    }
+
+    #[test]
+    fn test_zero_point_keeps_multipliers() {
+        let mut log = CabrilloLog::parse(WPX).unwrap();
+        log.qsos[2].zero_point = true;
+        let report = log.score(rules_for_contest("CQ-WPX-CW").unwrap(), &lookup);
+        let points: Vec<_> = report.qsos.iter().map(|q| q.points).collect();
+        assert_eq!(points, vec![6, 3, 0, 1, 3, 0]);
+        assert!(!report.qsos[2].dupe);
+        assert_eq!(report.qsos[2].new_multipliers[0].value, "VE3");
+        assert_eq!(report.multipliers.len(), 4);
+        assert_eq!(report.score, 52);
+    }
+
+    #[test]
+    fn test_cq_ww_score() {
+        let log = CabrilloLog::parse(CQ_WW).unwrap();
+        let report = log.score(rules_for_contest("cq-ww-cw").unwrap(), &lookup);
+        let points: Vec<_> = report.qsos.iter().map(|q| q.points).collect();
+        assert_eq!(points, vec![3, 3, 3, 0, 2]);
+        // 40m: zone 15, Slovenia; 20m: zones 15, 14, 5, 4 and four countries
+        assert_eq!(report.multipliers.len(), 10);
+        assert_eq!(report.score, 110);
+        assert_eq!(
+            report.qsos[3].new_multipliers[1].to_string(),
+            "country United States on 20m"
+        );
+
+        // Countries count by DXCC entity, also without a country name
+        let unnamed = |call: &Callsign| {
+            lookup(call).map(|info| StationInfo {
+                country: String::new(),
+                ..info
+            })
+        };
+        let report = log.score(&CqWw, &unnamed);
+        assert_eq!(report.multipliers.len(), 10);
+        assert_eq!(report.score, 110);
+        assert_eq!(
+            report.qsos[3].new_multipliers[1].to_string(),
+            "country 291 on 20m"
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Sent serial number checks.
+//!
+//! Loggers number the contacts of each transmitter `1, 2, 3, ...`. Gaps,
+//! repeated numbers and numbers that go backwards usually mean the logging
+//! program was restarted or two computers shared a counter, and a final serial
+//! that does not match the number of QSOs means contacts went missing from the
+//! log. [`CabrilloLog::check_serials`] finds these before the log is submitted.
+
+use crate::CabrilloLog;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Serials sent from one transmitter, in log order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerialSequence {
+    /// TX ID of the transmitter; `None` for QSOs without one.
+    pub tx: Option<String>,
+    /// Index into [`CabrilloLog::qsos`] and serial sent, for every QSO with a serial.
+    pub serials: Vec<(usize, u32)>,
+}
+
+/// Kind of serial number problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SerialIssueKind {
+    /// Serials were skipped: the QSO sent `found` where `expected` was next.
+    Gap { expected: u32, found: u32 },
+    /// The serial was already sent in an earlier QSO.
+    Repeat { previous: usize },
+    /// The serial is lower than one sent before it.
+    OutOfOrder { previous: usize },
+    /// The last serial of the transmitter does not match its number of QSOs.
+    CountMismatch { last: u32, qsos: usize },
+}
+
+/// A serial number problem with the index of the QSO in [`CabrilloLog::qsos`].
+///
+/// For [`SerialIssueKind::CountMismatch`] the QSO is the last one of the transmitter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerialIssue {
+    pub qso: usize,
+    pub tx: Option<String>,
+    pub kind: SerialIssueKind,
+}
+
+impl fmt::Display for SerialIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "QSO #{}: ", self.qso + 1)?;
+        match &self.kind {
+            SerialIssueKind::Gap { expected, found } => {
+                write!(f, "serial {} sent, expected {}", found, expected)?
+            }
+            SerialIssueKind::Repeat { previous } => {
+                write!(f, "serial already sent in QSO #{}", previous + 1)?
+            }
+            SerialIssueKind::OutOfOrder { previous } => {
+                write!(f, "serial lower than in QSO #{}", previous + 1)?
+            }
+            SerialIssueKind::CountMismatch { last, qsos } => {
+                write!(f, "last serial is {} but {} QSOs were logged", last, qsos)?
+            }
+        }
+        if let Some(tx) = &self.tx {
+            write!(f, " (TX {})", tx)?;
+        }
+        Ok(())
+    }
+}
+
+impl CabrilloLog {
+    /// Sent serials grouped by transmitter, ordered by TX ID.
+    ///
+    /// Only QSOs whose sent exchange has a [`FieldKind::Serial`](crate::FieldKind::Serial)
+    /// field are included, so logs of contests without serials give no sequences.
+    pub fn sent_serials(&self) -> Vec<SerialSequence> {
+        let mut by_tx: BTreeMap<Option<&str>, Vec<(usize, u32)>> = BTreeMap::new();
+        for (index, qso) in self.qsos.iter().enumerate() {
+            if let Some(serial) = qso.sent_exch.serial() {
+                by_tx
+                    .entry(qso.tx.as_deref())
+                    .or_default()
+                    .push((index, serial));
+            }
+        }
+        by_tx
+            .into_iter()
+            .map(|(tx, serials)| SerialSequence {
+                tx: tx.map(str::to_string),
+                serials,
+            })
+            .collect()
+    }
+
+    /// Check the sent serials of every transmitter, returning issues ordered by QSO index.
+    ///
+    /// X-QSOs are included: the serial was sent even if the contact does not count.
+    pub fn check_serials(&self) -> Vec<SerialIssue> {
+        let mut issues = Vec::new();
+        for sequence in self.sent_serials() {
+            let mut issue = |qso, kind| {
+                issues.push(SerialIssue {
+                    qso,
+                    tx: sequence.tx.clone(),
+                    kind,
+                })
+            };
+            let mut first_sent: BTreeMap<u32, usize> = BTreeMap::new();
+            let mut highest: Option<(usize, u32)> = None;
+            for &(index, serial) in &sequence.serials {
+                if let Some(&previous) = first_sent.get(&serial) {
+                    issue(index, SerialIssueKind::Repeat { previous });
+                    continue;
+                }
+                first_sent.insert(serial, index);
+                match highest {
+                    Some((previous, high)) if serial < high => {
+                        issue(index, SerialIssueKind::OutOfOrder { previous });
+                    }
+                    _ => {
+                        let expected = highest.map_or(1, |(_, high)| high + 1);
+                        if serial > expected {
+                            issue(
+                                index,
+                                SerialIssueKind::Gap {
+                                    expected,
+                                    found: serial,
+                                },
+                            );
+                        }
+                        highest = Some((index, serial));
+                    }
+                }
+            }
+            if let (Some((last, _)), Some((_, high))) = (sequence.serials.last(), highest)
+                && high as usize != sequence.serials.len()
+            {
+                issue(
+                    *last,
+                    SerialIssueKind::CountMismatch {
+                        last: high,
+                        qsos: sequence.serials.len(),
+                    },
+                );
+            }
+        }
+        issues.sort_by_key(|issue| issue.qso);
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_TX: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4 0
+QSO: 14005 CW 2009-05-30 0003 AA1ZZZ 599 1 EF8M 599 9 1
+QSO: 7006 CW 2009-05-30 0010 AA1ZZZ 599 2 K1ABC 599 17 0
+END-OF-LOG:
+";
+
+    const BROKEN: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4
+QSO: 7005 CW 2009-05-30 0003 AA1ZZZ 599 2 EF8M 599 9
+QSO: 7005 CW 2009-05-30 0004 AA1ZZZ 599 5 K1ABC 599 17
+QSO: 7005 CW 2009-05-30 0005 AA1ZZZ 599 5 W1AW 599 23
+QSO: 7005 CW 2009-05-30 0006 AA1ZZZ 599 3 K1ZZ 599 30
+END-OF-LOG:
+";
+
+    const LATE_START: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 2 S50A 599 4
+QSO: 7005 CW 2009-05-30 0003 AA1ZZZ 599 3 EF8M 599 9
+END-OF-LOG:
+";
+
+    #[test]
+    fn test_clean_sequences_per_transmitter() {
+        let log = CabrilloLog::parse(TWO_TX).unwrap();
+        let sequences = log.sent_serials();
+        assert_eq!(sequences.len(), 2);
+        assert_eq!(sequences[0].tx.as_deref(), Some("0"));
+        assert_eq!(sequences[0].serials, vec![(0, 1), (2, 2)]);
+        assert!(log.check_serials().is_empty());
+    }
+
+    #[test]
+    fn test_gap_repeat_and_out_of_order() {
+        let log = CabrilloLog::parse(BROKEN).unwrap();
+        let kinds: Vec<_> = log
+            .check_serials()
+            .into_iter()
+            .map(|i| (i.qso, i.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    2,
+                    SerialIssueKind::Gap {
+                        expected: 3,
+                        found: 5
+                    }
+                ),
+                (3, SerialIssueKind::Repeat { previous: 2 }),
+                (4, SerialIssueKind::OutOfOrder { previous: 2 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_mismatch() {
+        let log = CabrilloLog::parse(LATE_START).unwrap();
+        let issues = log.check_serials();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0].kind,
+            SerialIssueKind::Gap {
+                expected: 1,
+                found: 2
+            }
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "QSO #2: last serial is 3 but 2 QSOs were logged"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Splitting a log into sub-logs.
+//!
+//! [`CabrilloLog::split`] partitions the QSOs of a log by band, mode, TX ID or
+//! date. Each part is a log of its own with the full header, adjusted to what
+//! the part contains: a part with the 20 m QSOs says `CATEGORY-BAND: 20M`, so
+//! it can be submitted as a single-band entry.
+//!
+//! ```rust
+//! use cabrillo_log::{CabrilloLog, CategoryBand, SplitKey};
+//!
+//! let log = CabrilloLog::parse("START-OF-LOG: 3.0\nCALLSIGN: AA1ZZZ\nCATEGORY-BAND: ALL\nQSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4\nQSO: 14005 CW 2009-05-30 0015 AA1ZZZ 599 2 EF8M 599 34\nEND-OF-LOG:\n").unwrap();
+//! let parts = log.split(SplitKey::Band);
+//! assert_eq!(parts.len(), 2);
+//! assert_eq!(parts[1].0.to_string(), "20m");
+//! assert_eq!(parts[1].1.headers.category_band(), Some(CategoryBand::B20M));
+//! ```
+//!
+//! [`CabrilloLog::split_by`] partitions by any key computed from the QSO; its
+//! parts keep the header as it is.
+
+use crate::header::{CategoryBand, CategoryMode, CategoryTransmitter};
+use crate::{Band, CabrilloLog, CabrilloMode, Header, QSO};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What [`CabrilloLog::split`] partitions QSOs by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitKey {
+    Band,
+    Mode,
+    Transmitter,
+    Date,
+}
+
+/// The QSOs a part of a split log holds.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SplitPart {
+    /// QSOs on a band; `None` for frequencies outside the amateur bands.
+    Band(Option<Band>),
+    Mode(CabrilloMode),
+    /// QSOs with a TX ID; `None` for QSOs without one.
+    Transmitter(Option<String>),
+    Date(NaiveDate),
+}
+
+impl SplitPart {
+    fn of(key: SplitKey, qso: &QSO) -> Self {
+        match key {
+            SplitKey::Band => SplitPart::Band(qso.freq.band()),
+            SplitKey::Mode => SplitPart::Mode(qso.mode.category),
+            SplitKey::Transmitter => SplitPart::Transmitter(qso.tx.clone()),
+            SplitKey::Date => SplitPart::Date(qso.date),
+        }
+    }
+}
+
+impl fmt::Display for SplitPart {
+    /// A short name, usable in a file name, e.g. `20m`, `CW`, `tx0` or `2009-05-30`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitPart::Band(band) => f.write_str(band.map_or("out-of-band", |b| b.name())),
+            SplitPart::Mode(mode) => write!(f, "{}", mode),
+            SplitPart::Transmitter(Some(tx)) => write!(f, "tx{}", tx),
+            SplitPart::Transmitter(None) => f.write_str("no-tx"),
+            SplitPart::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+        }
+    }
+}
+
+impl CabrilloLog {
+    /// Partition the QSOs by `key`, ordered by part.
+    ///
+    /// Band and mode parts get `CATEGORY-BAND:` and `CATEGORY-MODE:` headers
+    /// for their band and mode, where a category for it exists. Transmitter
+    /// parts get `CATEGORY-TRANSMITTER: ONE` and their QSOs lose the TX ID, which
+    /// single-transmitter logs do not have. `CLAIMED-SCORE:` is removed from
+    /// every part.
+    pub fn split(&self, key: SplitKey) -> Vec<(SplitPart, CabrilloLog)> {
+        let mut parts = self.split_by(|qso| SplitPart::of(key, qso));
+        for (part, log) in &mut parts {
+            log.headers.remove("CLAIMED-SCORE");
+            match part {
+                SplitPart::Band(Some(band)) => {
+                    if let Some(category) = CategoryBand::for_band(*band) {
+                        log.headers.set(Header::CategoryBand(category));
+                    }
+                }
+                SplitPart::Mode(mode) => {
+                    log.headers
+                        .set(Header::CategoryMode(CategoryMode::for_mode(*mode)));
+                }
+                SplitPart::Transmitter(_) => {
+                    log.headers
+                        .set(Header::CategoryTransmitter(CategoryTransmitter::One));
+                    for qso in &mut log.qsos {
+                        qso.tx = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+        parts
+    }
+
+    /// Partition the QSOs by a key computed from each QSO, ordered by key.
+    ///
+    /// Every part has the full header of this log and its QSOs in log order.
+    /// Comments are not carried over.
+    pub fn split_by<K: Ord>(&self, mut key: impl FnMut(&QSO) -> K) -> Vec<(K, CabrilloLog)> {
+        let mut parts: BTreeMap<K, Vec<QSO>> = BTreeMap::new();
+        for qso in &self.qsos {
+            parts.entry(key(qso)).or_default().push(qso.clone());
+        }
+        parts
+            .into_iter()
+            .map(|(key, qsos)| {
+                let log = CabrilloLog {
+                    headers: self.headers.clone(),
+                    qsos,
+                    comments: Vec::new(),
+                    end_of_log_version: self.end_of_log_version,
+                };
+                (key, log)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX
+CATEGORY-BAND: ALL
+CATEGORY-MODE: MIXED
+CATEGORY-TRANSMITTER: TWO
+CLAIMED-SCORE: 100
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4 0
+QSO: 14200 PH 2009-05-30 0015 AA1ZZZ 59 2 EF8M 59 34 1
+QSO: 7010 CW 2009-05-31 0020 AA1ZZZ 599 3 DL1AA 599 5 0
+QSO: 10105 CW 2009-05-31 0030 AA1ZZZ 599 4 G3XYZ 599 6 1
+END-OF-LOG:
+";
+
+    #[test]
+    fn test_split_by_band() {
+        let log = CabrilloLog::parse(LOG).unwrap();
+        let parts = log.split(SplitKey::Band);
+        let summary: Vec<_> = parts
+            .iter()
+            .map(|(part, log)| {
+                (
+                    part.to_string(),
+                    log.qsos.len(),
+                    log.headers.category_band(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("40m".to_string(), 2, Some(CategoryBand::B40M)),
+                ("30m".to_string(), 1, Some(CategoryBand::All)),
+                ("20m".to_string(), 1, Some(CategoryBand::B20M)),
+            ]
+        );
+        for (_, part) in &parts {
+            assert!(part.validate().is_ok());
+            assert!(part.check_consistency(None).is_empty());
+            assert!(part.headers.claimed_score().is_none());
+        }
+    }
+
+    #[test]
+    fn test_split_by_mode_tx_and_date() {
+        let log = CabrilloLog::parse(LOG).unwrap();
+        let modes = log.split(SplitKey::Mode);
+        assert_eq!(modes[0].0, SplitPart::Mode(CabrilloMode::Cw));
+        assert_eq!(modes[0].1.qsos.len(), 3);
+        assert_eq!(modes[1].1.headers.category_mode(), Some(CategoryMode::Ssb));
+
+        let parts = log.split(SplitKey::Transmitter);
+        assert!(
+            parts
+                .iter()
+                .all(|(_, log)| log.check_consistency(None).is_empty())
+        );
+        let txs: Vec<_> = parts
+            .into_iter()
+            .map(|(part, log)| {
+                (
+                    part.to_string(),
+                    log.qsos.len(),
+                    log.headers.category_transmitter(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            txs,
+            vec![
+                ("tx0".to_string(), 2, Some(CategoryTransmitter::One)),
+                ("tx1".to_string(), 2, Some(CategoryTransmitter::One)),
+            ]
+        );
+
+        let dates = log.split(SplitKey::Date);
+        assert_eq!(dates[1].0.to_string(), "2009-05-31");
+        assert_eq!(dates[1].1.qsos[0].rcvd_call, "DL1AA");
+
+        let by_call = log.split_by(|qso| qso.rcvd_call.starts_with('S'));
+        assert_eq!(by_call[1].1.qsos.len(), 1);
+        assert_eq!(by_call[1].1.headers, log.headers);
+    }
+}
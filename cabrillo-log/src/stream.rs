@@ -0,0 +1,245 @@
+//! Streaming Cabrillo reader and writer.
+//!
+//! [`CabrilloReader`] reads the header section up front and then yields QSOs one
+//! line at a time, so arbitrarily large logs are processed in constant memory.
+//! [`CabrilloWriter`] is its counterpart and emits each QSO as it arrives.
+
+use crate::{
+    CabrilloError, CabrilloLog, CabrilloVersion, ContestTemplate, Header, Headers, QSO,
+    TemplateRegistry,
+};
+use std::io::{self, BufRead, Write};
+
+/// Incremental reader over any [`BufRead`].
+///
+/// ```rust
+/// use cabrillo_log::CabrilloReader;
+///
+/// let data = "START-OF-LOG: 3.0\nCALLSIGN: N1MM\nQSO: 14000 CW 2023-10-01 1200 N1MM 599 001 W1AW 599 001\nEND-OF-LOG:\n";
+/// let mut reader = CabrilloReader::new(data.as_bytes()).unwrap();
+/// assert_eq!(reader.headers().callsign(), Some("N1MM"));
+/// for qso in &mut reader {
+///     println!("{}", qso.unwrap());
+/// }
+/// ```
+pub struct CabrilloReader<R: BufRead> {
+    reader: R,
+    headers: Headers,
+    template: Option<&'static ContestTemplate>,
+    pending: Option<String>, // First QSO line, read while scanning headers
+    buffer: String,
+    line_number: usize,
+    finished: bool,
+}
+
+impl<R: BufRead> CabrilloReader<R> {
+    /// Create a reader using the built-in contest templates and read the headers.
+    pub fn new(reader: R) -> Result<Self, CabrilloError> {
+        Self::with_templates(reader, &TemplateRegistry::default())
+    }
+
+    /// Create a reader using the given contest templates and read the headers.
+    pub fn with_templates(reader: R, templates: &TemplateRegistry) -> Result<Self, CabrilloError> {
+        let mut stream = CabrilloReader {
+            reader,
+            headers: Headers::new(),
+            template: None,
+            pending: None,
+            buffer: String::new(),
+            line_number: 0,
+            finished: false,
+        };
+
+        let mut version = CabrilloVersion::V3;
+        while stream.read_line()? {
+            let line = stream.buffer.trim();
+            if line.starts_with("QSO:") || line.starts_with("X-QSO:") {
+                stream.pending = Some(line.to_string());
+                break;
+            }
+            if line.starts_with("END-OF-LOG:") {
+                stream.finished = true;
+                break;
+            }
+            if let Some(value) = line.strip_prefix("START-OF-LOG:") {
+                version = CabrilloVersion::from_start_of_log(value);
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                stream.headers.push(Header::parse(key, value));
+            }
+        }
+        if version == CabrilloVersion::V2 {
+            let mut log = CabrilloLog {
+                headers: std::mem::take(&mut stream.headers),
+                ..CabrilloLog::default()
+            };
+            log.upgrade_from_v2();
+            stream.headers = log.headers;
+        }
+        stream.template = stream
+            .headers
+            .contest()
+            .and_then(|contest| templates.lookup(contest));
+
+        Ok(stream)
+    }
+
+    /// Header lines of the log.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Line number of the last line read, 1-based.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Read the next line into the buffer; returns `false` at end of input.
+    fn read_line(&mut self) -> Result<bool, CabrilloError> {
+        self.buffer.clear();
+        let read = self
+            .reader
+            .read_line(&mut self.buffer)
+            .map_err(|e| CabrilloError::ParseError(e.to_string()))?;
+        if read > 0 {
+            self.line_number += 1;
+        }
+        Ok(read > 0)
+    }
+
+    fn parse(&self, line: &str) -> Result<QSO, CabrilloError> {
+        CabrilloLog::parse_qso_line(line, self.template).map_err(|(kind, _)| kind)
+    }
+}
+
+impl<R: BufRead> Iterator for CabrilloReader<R> {
+    type Item = Result<QSO, CabrilloError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(line) = self.pending.take() {
+            return Some(self.parse(&line));
+        }
+        while !self.finished {
+            match self.read_line() {
+                Ok(true) => {}
+                Ok(false) => self.finished = true,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+            let line = self.buffer.trim();
+            if line.starts_with("QSO:") || line.starts_with("X-QSO:") {
+                return Some(self.parse(line));
+            }
+            if line.starts_with("END-OF-LOG:") {
+                self.finished = true;
+            }
+        }
+        None
+    }
+}
+
+/// Incremental writer that emits each line as soon as it is written.
+pub struct CabrilloWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CabrilloWriter<W> {
+    /// Create a writer and emit `START-OF-LOG:` followed by the headers.
+    pub fn new(mut writer: W, headers: &Headers) -> io::Result<Self> {
+        writeln!(writer, "START-OF-LOG: 3.0")?;
+        for header in headers {
+            writeln!(writer, "{}", header)?;
+        }
+        Ok(CabrilloWriter { writer })
+    }
+
+    /// Write a QSO or X-QSO line.
+    pub fn write_qso(&mut self, qso: &QSO) -> io::Result<()> {
+        writeln!(self.writer, "{}", qso)
+    }
+
+    /// Write a `#` comment line.
+    pub fn write_comment(&mut self, text: &str) -> io::Result<()> {
+        writeln!(self.writer, "#{}", text)
+    }
+
+    /// Emit `END-OF-LOG:`, flush and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        writeln!(self.writer, "END-OF-LOG:")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "START-OF-LOG: 3.0
+CONTEST: CQ-WPX-CW
+CALLSIGN: AA1ZZZ
+# comment
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4
+X-QSO: 7006 CW 2009-05-30 0015 AA1ZZZ 599 2 EF8M 599 34
+QSO: 7006 CW 2009-05-30 0016 AA1ZZZ 599 X EF8M 599 34
+END-OF-LOG:
+";
+
+    #[test]
+    fn test_reader_yields_headers_then_qsos() {
+        let mut reader = CabrilloReader::new(CONTENT.as_bytes()).unwrap();
+        assert_eq!(reader.headers().callsign(), Some("AA1ZZZ"));
+        assert_eq!(reader.headers().len(), 2);
+
+        let first = reader.next().unwrap().unwrap();
+        assert_eq!(first.rcvd_exch.serial(), Some(4));
+        assert_eq!(reader.line_number(), 5);
+        assert!(reader.next().unwrap().unwrap().excluded);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_writer_matches_display() {
+        let log = CabrilloLog::parse(CONTENT).unwrap();
+        let mut writer = CabrilloWriter::new(Vec::new(), &log.headers).unwrap();
+        for qso in &log.qsos {
+            writer.write_qso(qso).unwrap();
+        }
+        let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        let mut expected = log.clone();
+        expected.comments.clear();
+        assert_eq!(output, expected.to_string());
+    }
+
+    #[test]
+    fn test_reader_upgrades_v2_headers() {
+        let content = CONTENT.replace("3.0\n", "2.0\nCATEGORY: SINGLE-OP 40M LOW\n");
+        let reader = CabrilloReader::new(content.as_bytes()).unwrap();
+        assert_eq!(reader.headers().len(), 5);
+        assert_eq!(
+            reader.headers().category_power(),
+            Some(crate::CategoryPower::Low)
+        );
+    }
+
+    #[test]
+    fn test_reader_to_writer_pipeline() {
+        let reader = CabrilloReader::new(CONTENT.as_bytes()).unwrap();
+        let mut writer = CabrilloWriter::new(Vec::new(), reader.headers()).unwrap();
+        let mut count = 0;
+        for qso in reader {
+            writer.write_qso(&qso.unwrap()).unwrap();
+            count += 1;
+        }
+        writer.finish().unwrap();
+        assert_eq!(count, 3);
+    }
+}
@@ -0,0 +1,269 @@
+//! QSO timestamp checks.
+//!
+//! [`CabrilloLog::check_timing`] looks at the log as a whole rather than at
+//! single lines: QSOs outside the contest period, QSOs logged out of
+//! chronological order, timestamps in the future and operating time beyond
+//! what the `CATEGORY-TIME:` header allows.
+
+use crate::diagnostic::Severity;
+use crate::header::CategoryTime;
+use crate::{CabrilloLog, QSO};
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use std::fmt;
+
+/// Start and end of a contest, in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContestPeriod {
+    pub start: NaiveDateTime,
+    /// First minute after the contest; QSOs must be logged before it.
+    pub end: NaiveDateTime,
+}
+
+impl ContestPeriod {
+    pub fn new(start: NaiveDateTime, end: NaiveDateTime) -> Self {
+        ContestPeriod { start, end }
+    }
+
+    /// Whether a QSO time falls within the period.
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        (self.start..self.end).contains(&time)
+    }
+}
+
+/// What [`CabrilloLog::check_timing`] checks against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingRules {
+    /// Contest period; QSOs outside it are flagged when set.
+    pub period: Option<ContestPeriod>,
+    /// Reference time for the future-timestamp check; the current time if `None`.
+    pub now: Option<NaiveDateTime>,
+    /// Shortest break that counts as off time for `CATEGORY-TIME:`.
+    pub min_off_time: TimeDelta,
+}
+
+impl Default for TimingRules {
+    fn default() -> Self {
+        TimingRules {
+            period: None,
+            now: None,
+            min_off_time: TimeDelta::minutes(60),
+        }
+    }
+}
+
+impl TimingRules {
+    /// Rules for a contest period, with the default off time.
+    pub fn for_period(period: ContestPeriod) -> Self {
+        TimingRules {
+            period: Some(period),
+            ..Self::default()
+        }
+    }
+}
+
+/// Kind of timing problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimingIssueKind {
+    /// The QSO is before the start or after the end of the contest.
+    OutsidePeriod,
+    /// The QSO is earlier than the one logged before it.
+    OutOfOrder { previous: usize },
+    /// The QSO is later than the reference time.
+    InFuture,
+    /// The QSO was made after the operating time allowed by `CATEGORY-TIME:` was used up.
+    OperatingTimeExceeded { allowed_hours: u32 },
+}
+
+/// A timing problem with the index of the QSO in [`CabrilloLog::qsos`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimingIssue {
+    pub qso: usize,
+    pub kind: TimingIssueKind,
+}
+
+impl TimingIssue {
+    /// Out-of-order QSOs are legitimate in some multi-transmitter logs and are
+    /// only warnings; everything else is an error.
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            TimingIssueKind::OutOfOrder { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for TimingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "QSO #{}: ", self.qso + 1)?;
+        match &self.kind {
+            TimingIssueKind::OutsidePeriod => write!(f, "outside the contest period"),
+            TimingIssueKind::OutOfOrder { previous } => {
+                write!(f, "earlier than QSO #{}", previous + 1)
+            }
+            TimingIssueKind::InFuture => write!(f, "timestamp in the future"),
+            TimingIssueKind::OperatingTimeExceeded { allowed_hours } => {
+                write!(f, "beyond the {} hours of operating time", allowed_hours)
+            }
+        }
+    }
+}
+
+impl CategoryTime {
+    /// Operating time allowed in this category.
+    pub fn hours(&self) -> u32 {
+        match self {
+            CategoryTime::Hours6 => 6,
+            CategoryTime::Hours8 => 8,
+            CategoryTime::Hours12 => 12,
+            CategoryTime::Hours24 => 24,
+        }
+    }
+}
+
+impl QSO {
+    /// Date and time of the QSO.
+    pub fn datetime(&self) -> NaiveDateTime {
+        self.date.and_time(self.time)
+    }
+}
+
+impl CabrilloLog {
+    /// Check QSO timestamps against `rules`, returning issues ordered by QSO index.
+    ///
+    /// Operating time is the time from the first to the last QSO minus every
+    /// break of at least [`TimingRules::min_off_time`]; X-QSOs do not count toward it.
+    pub fn check_timing(&self, rules: &TimingRules) -> Vec<TimingIssue> {
+        let now = rules.now.unwrap_or_else(|| Utc::now().naive_utc());
+        let mut issues = Vec::new();
+        let mut issue = |qso, kind| issues.push(TimingIssue { qso, kind });
+
+        let mut latest: Option<(usize, NaiveDateTime)> = None;
+        for (index, qso) in self.qsos.iter().enumerate() {
+            let time = qso.datetime();
+            if let Some(period) = &rules.period
+                && !period.contains(time)
+            {
+                issue(index, TimingIssueKind::OutsidePeriod);
+            }
+            if time > now {
+                issue(index, TimingIssueKind::InFuture);
+            }
+            match latest {
+                Some((previous, latest_time)) if time < latest_time => {
+                    issue(index, TimingIssueKind::OutOfOrder { previous });
+                }
+                _ => latest = Some((index, time)),
+            }
+        }
+
+        if let Some(category) = self.headers.category_time() {
+            let allowed = TimeDelta::hours(category.hours().into());
+            let mut counted: Vec<(usize, NaiveDateTime)> = self
+                .qsos
+                .iter()
+                .enumerate()
+                .filter(|(_, qso)| !qso.excluded)
+                .map(|(index, qso)| (index, qso.datetime()))
+                .collect();
+            counted.sort_by_key(|&(index, time)| (time, index));
+
+            let mut used = TimeDelta::zero();
+            for pair in counted.windows(2) {
+                let gap = pair[1].1 - pair[0].1;
+                if gap < rules.min_off_time {
+                    used += gap;
+                }
+                if used > allowed {
+                    issue(
+                        pair[1].0,
+                        TimingIssueKind::OperatingTimeExceeded {
+                            allowed_hours: category.hours(),
+                        },
+                    );
+                }
+            }
+        }
+
+        issues.sort_by_key(|issue| issue.qso);
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const LOG: &str = "START-OF-LOG: 3.0
+CALLSIGN: N1MM
+QSO: 14000 CW 2023-10-01 0000 N1MM 599 001 W1AW 599 001
+QSO: 14000 CW 2023-10-01 1200 N1MM 599 002 K1ABC 599 001
+QSO: 14000 CW 2023-10-01 1100 N1MM 599 003 K2ABC 599 001
+QSO: 14000 CW 2023-10-02 0000 N1MM 599 004 K3ABC 599 001
+QSO: 14000 CW 2023-10-03 0000 N1MM 599 005 K4ABC 599 001
+END-OF-LOG:
+";
+
+    // 4 hours on, 3.5 hours off, then 2.5 more hours on: the last QSO is past 6 hours.
+    const SIX_HOURS: &str = "START-OF-LOG: 3.0
+CALLSIGN: N1MM
+CATEGORY-TIME: 6-HOURS
+QSO: 14000 CW 2023-10-01 0000 N1MM 599 001 W1AW 599 001
+QSO: 14000 CW 2023-10-01 0200 N1MM 599 002 K1ABC 599 001
+QSO: 14000 CW 2023-10-01 0400 N1MM 599 003 K2ABC 599 001
+QSO: 14000 CW 2023-10-01 0730 N1MM 599 004 K3ABC 599 001
+QSO: 14000 CW 2023-10-01 0930 N1MM 599 005 K4ABC 599 001
+QSO: 14000 CW 2023-10-01 1000 N1MM 599 006 K5ABC 599 001
+END-OF-LOG:
+";
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_period_order_and_future() {
+        let log = CabrilloLog::parse(LOG).unwrap();
+        let rules = TimingRules {
+            now: Some(at(2, 12, 0)),
+            ..TimingRules::for_period(ContestPeriod::new(at(1, 0, 0), at(2, 0, 0)))
+        };
+        let kinds: Vec<_> = log
+            .check_timing(&rules)
+            .into_iter()
+            .map(|i| (i.qso, i.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (2, TimingIssueKind::OutOfOrder { previous: 1 }),
+                (3, TimingIssueKind::OutsidePeriod),
+                (4, TimingIssueKind::OutsidePeriod),
+                (4, TimingIssueKind::InFuture),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_operating_time_limit() {
+        let log = CabrilloLog::parse(SIX_HOURS).unwrap();
+        let rules = TimingRules {
+            min_off_time: TimeDelta::minutes(150),
+            ..TimingRules::default()
+        };
+        let issues = log.check_timing(&rules);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].qso, 5);
+        assert_eq!(issues[0].severity(), Severity::Error);
+        assert_eq!(
+            issues[0].to_string(),
+            "QSO #6: beyond the 6 hours of operating time"
+        );
+    }
+}
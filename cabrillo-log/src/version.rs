@@ -0,0 +1,273 @@
+//! Cabrillo 2.0 logs.
+//!
+//! Cabrillo 2.0 packs operator, band and power (and sometimes the mode) into a
+//! single `CATEGORY:` line, e.g. `CATEGORY: SINGLE-OP-ASSISTED 20M LOW`, and
+//! calls the location `ARRL-SECTION:`. The QSO lines are the same as in 3.0.
+//!
+//! Logs starting with `START-OF-LOG: 2.0` have their header upgraded while
+//! parsing, so a 2.0 log comes back with the typed 3.0 headers and is written
+//! as a valid 3.0 log:
+//!
+//! ```rust
+//! use cabrillo_log::{CabrilloLog, CategoryAssisted, CategoryBand};
+//!
+//! let log = CabrilloLog::parse("START-OF-LOG: 2.0\nCALLSIGN: N1MM\nCATEGORY: SINGLE-OP-ASSISTED 20M LOW\nARRL-SECTION: CT\nEND-OF-LOG:\n").unwrap();
+//! assert_eq!(log.headers.category_assisted(), Some(CategoryAssisted::Assisted));
+//! assert_eq!(log.headers.category_band(), Some(CategoryBand::B20M));
+//! assert_eq!(log.headers.location(), Some("CT"));
+//! assert!(log.to_string().starts_with("START-OF-LOG: 3.0\n"));
+//! ```
+//!
+//! Nothing is dropped: `CATEGORY:` values that do not map onto 3.0 categories
+//! are kept as `X-CATEGORY:`, and other tags unknown to 3.0 get an `X-` prefix.
+
+use crate::header::{
+    CategoryAssisted, CategoryBand, CategoryMode, CategoryOperator, CategoryPower, CategoryStation,
+    CategoryTransmitter,
+};
+use crate::layout::HEADER_ORDER;
+use crate::{CabrilloLog, Header, Headers};
+use std::fmt;
+
+/// Cabrillo specification version of a log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum CabrilloVersion {
+    V2,
+    #[default]
+    V3,
+}
+
+impl CabrilloVersion {
+    /// Version from the value of `START-OF-LOG:`; anything but 2.x is taken as 3.0.
+    pub fn from_start_of_log(value: &str) -> Self {
+        if value.trim().starts_with('2') {
+            CabrilloVersion::V2
+        } else {
+            CabrilloVersion::V3
+        }
+    }
+
+    /// Version of a log from its `START-OF-LOG:` line, 3.0 if there is none.
+    pub fn detect(content: &str) -> Self {
+        content
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("START-OF-LOG:"))
+            .map_or_else(Self::default, Self::from_start_of_log)
+    }
+}
+
+impl fmt::Display for CabrilloVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CabrilloVersion::V2 => write!(f, "2.0"),
+            CabrilloVersion::V3 => write!(f, "3.0"),
+        }
+    }
+}
+
+/// The 3.0 header lines replacing a 2.0 header line.
+fn upgrade_header(header: Header) -> Vec<Header> {
+    match header {
+        Header::Other { tag, value } if tag.eq_ignore_ascii_case("CATEGORY") => {
+            upgrade_category(&value)
+        }
+        Header::Other { tag, value } if tag.eq_ignore_ascii_case("ARRL-SECTION") => {
+            vec![Header::Location(value)]
+        }
+        Header::Other { tag, value }
+            if !tag.to_ascii_uppercase().starts_with("X-")
+                && !HEADER_ORDER.iter().any(|t| t.eq_ignore_ascii_case(&tag)) =>
+        {
+            vec![Header::Other {
+                tag: format!("X-{}", tag),
+                value,
+            }]
+        }
+        header => vec![header],
+    }
+}
+
+/// Split a 2.0 `CATEGORY:` value into 3.0 category headers.
+fn upgrade_category(value: &str) -> Vec<Header> {
+    let mut tokens = value.split_whitespace();
+    let mut headers = Headers::new();
+    let mut unmapped = false;
+
+    let operator = tokens.next().unwrap_or_default().to_ascii_uppercase();
+    let (op, extra) = match operator.as_str() {
+        "SINGLE-OP" => (CategoryOperator::SingleOp, None),
+        "SINGLE-OP-ASSISTED" => (
+            CategoryOperator::SingleOp,
+            Some(Header::CategoryAssisted(CategoryAssisted::Assisted)),
+        ),
+        "SINGLE-OP-QRP" => (
+            CategoryOperator::SingleOp,
+            Some(Header::CategoryPower(CategoryPower::Qrp)),
+        ),
+        "SINGLE-OP-PORTABLE" => (
+            CategoryOperator::SingleOp,
+            Some(Header::CategoryStation(CategoryStation::Portable)),
+        ),
+        "ROVER" => (
+            CategoryOperator::SingleOp,
+            Some(Header::CategoryStation(CategoryStation::Rover)),
+        ),
+        "MULTI-ONE" => (
+            CategoryOperator::MultiOp,
+            Some(Header::CategoryTransmitter(CategoryTransmitter::One)),
+        ),
+        "MULTI-TWO" => (
+            CategoryOperator::MultiOp,
+            Some(Header::CategoryTransmitter(CategoryTransmitter::Two)),
+        ),
+        "MULTI-LIMITED" => (
+            CategoryOperator::MultiOp,
+            Some(Header::CategoryTransmitter(CategoryTransmitter::Limited)),
+        ),
+        "MULTI-MULTI" | "MULTI-UNLIMITED" => (
+            CategoryOperator::MultiOp,
+            Some(Header::CategoryTransmitter(CategoryTransmitter::Unlimited)),
+        ),
+        "SCHOOL-CLUB" => (
+            CategoryOperator::MultiOp,
+            Some(Header::CategoryStation(CategoryStation::School)),
+        ),
+        "CHECKLOG" => (CategoryOperator::Checklog, None),
+        _ => {
+            unmapped = true;
+            (CategoryOperator::SingleOp, None)
+        }
+    };
+    if !unmapped {
+        headers.set(Header::CategoryOperator(op));
+        if let Some(extra) = extra {
+            headers.push(extra);
+        }
+    }
+
+    for token in tokens {
+        if let Ok(band) = token.parse::<CategoryBand>() {
+            headers.set(Header::CategoryBand(band));
+        } else if let Ok(power) = token.parse::<CategoryPower>() {
+            headers.set(Header::CategoryPower(power));
+        } else if let Ok(mode) = token.parse::<CategoryMode>() {
+            headers.set(Header::CategoryMode(mode));
+        } else {
+            unmapped = true;
+        }
+    }
+    if unmapped {
+        headers.push(Header::Other {
+            tag: "X-CATEGORY".to_string(),
+            value: value.trim().to_string(),
+        });
+    }
+    headers.iter().cloned().collect()
+}
+
+impl CabrilloLog {
+    /// Replace Cabrillo 2.0 header lines by their 3.0 equivalents, keeping
+    /// comments in front of the lines they preceded.
+    pub(crate) fn upgrade_from_v2(&mut self) {
+        let mut headers = Headers::new();
+        // Number of lines added in front of each old line position
+        let mut shift = Vec::with_capacity(self.headers.len() + 1);
+        for header in self.headers.iter().cloned() {
+            shift.push(headers.len() - shift.len());
+            for upgraded in upgrade_header(header) {
+                headers.push(upgraded);
+            }
+        }
+        let total = headers.len() - self.headers.len();
+        for comment in &mut self.comments {
+            comment.position += shift.get(comment.position).copied().unwrap_or(total);
+        }
+        self.headers = headers;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_version() {
+        assert_eq!(
+            CabrilloVersion::detect("START-OF-LOG: 2.0\nEND-OF-LOG:\n"),
+            CabrilloVersion::V2
+        );
+        assert_eq!(
+            CabrilloVersion::detect("\nSTART-OF-LOG: 3.0\n"),
+            CabrilloVersion::V3
+        );
+        assert_eq!(
+            CabrilloVersion::detect("CALLSIGN: N1MM\n"),
+            CabrilloVersion::V3
+        );
+    }
+
+    #[test]
+    fn test_upgrade_category() {
+        let headers = upgrade_category("MULTI-TWO ALL HIGH");
+        let lines: Vec<_> = headers.iter().map(|h| h.to_string()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "CATEGORY-OPERATOR: MULTI-OP",
+                "CATEGORY-TRANSMITTER: TWO",
+                "CATEGORY-BAND: ALL",
+                "CATEGORY-POWER: HIGH",
+            ]
+        );
+
+        let headers = upgrade_category("SINGLE-OP 15M LOW SUPERSTATION");
+        assert_eq!(
+            headers.last().unwrap().to_string(),
+            "X-CATEGORY: SINGLE-OP 15M LOW SUPERSTATION"
+        );
+    }
+
+    #[test]
+    fn test_parse_v2_log() {
+        let content = "START-OF-LOG: 2.0
+CALLSIGN: AA1ZZZ
+# category
+CATEGORY: SINGLE-OP ALL HIGH
+ARRL-SECTION: WMA
+IOTA-ISLAND-NAME: Mainland
+CONTEST: CQ-WPX-CW
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4
+# after QSO
+END-OF-LOG:
+";
+        let log = CabrilloLog::parse(content).unwrap();
+        assert_eq!(
+            log.headers.category_operator(),
+            Some(CategoryOperator::SingleOp)
+        );
+        assert_eq!(log.headers.category_power(), Some(CategoryPower::High));
+        assert_eq!(log.headers.location(), Some("WMA"));
+        assert_eq!(log.headers.get("X-IOTA-ISLAND-NAME").unwrap(), "Mainland");
+        assert_eq!(log.qsos[0].rcvd_exch.serial(), Some(4));
+
+        let upgraded = log.to_string();
+        let header: Vec<_> = upgraded.lines().take(10).collect();
+        assert_eq!(
+            header,
+            vec![
+                "START-OF-LOG: 3.0",
+                "CALLSIGN: AA1ZZZ",
+                "# category",
+                "CATEGORY-OPERATOR: SINGLE-OP",
+                "CATEGORY-BAND: ALL",
+                "CATEGORY-POWER: HIGH",
+                "LOCATION: WMA",
+                "X-IOTA-ISLAND-NAME: Mainland",
+                "CONTEST: CQ-WPX-CW",
+                "QSO:    7005 CW 2009-05-30 0002 AA1ZZZ        599 1      S50A          599 4",
+            ]
+        );
+        assert!(upgraded.ends_with("# after QSO\nEND-OF-LOG:\n"));
+        assert_eq!(CabrilloLog::parse(&upgraded).unwrap(), log);
+    }
+}
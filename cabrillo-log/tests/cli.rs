@@ -0,0 +1,325 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+const LOG: &str = "START-OF-LOG: 3.0
+CALLSIGN: AA1ZZZ
+CONTEST: CQ-WPX-CW
+QSO: 7005 CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4
+QSO: 7006 CW 2009-05-30 0015 AA1ZZZ 599 2 EF8M 599 34
+QSO: 7010 CW 2009-05-30 0020 AA1ZZZ 599 3 S50A 599 5
+END-OF-LOG:
+";
+
+/// Run the binary with `input` on stdin.
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cabrillo-log"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start cabrillo-log");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// A scratch directory for log files, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("cabrillo-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+
+    /// Write a file into the directory and return its path.
+    fn write(&self, name: &str, content: &str) -> String {
+        let path = self.0.join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    fn read(&self, name: &str) -> String {
+        std::fs::read_to_string(self.0.join(name)).unwrap()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_validate_exit_codes() {
+    let ok = run(&["validate"], LOG);
+    assert_eq!(ok.status.code(), Some(0));
+
+    let broken = LOG.replace("2009-05-30 0015", "2009-13-30 0015");
+    let failed = run(&["validate", "--json", "-"], &broken);
+    assert_eq!(failed.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&failed.stdout).unwrap();
+    assert_eq!(report["valid"], false);
+    assert_eq!(report["diagnostics"][0]["line"], 5);
+    assert_eq!(report["diagnostics"][0]["severity"], "error");
+
+    let period = [
+        "validate",
+        "--json",
+        "--start",
+        "2009-05-30T00:00:00",
+        "--end",
+        "2009-05-30T00:10:00",
+    ];
+    let late = run(&period, LOG);
+    assert_eq!(late.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&late.stdout).unwrap();
+    assert_eq!(report["timing"][0]["qso"], 2);
+    assert_eq!(report["timing"][0]["severity"], "error");
+
+    let gap = LOG.replace("599 3 S50A", "599 4 S50A");
+    let warned = run(&["validate", "--json"], &gap);
+    assert_eq!(warned.status.code(), Some(0));
+    let report: serde_json::Value = serde_json::from_slice(&warned.stdout).unwrap();
+    assert_eq!(report["serials"][0]["qso"], 3);
+    assert_eq!(report["serials"][0]["severity"], "warning");
+
+    let wrong_band = LOG.replace("CONTEST:", "CATEGORY-BAND: 20M\nCONTEST:");
+    let report: serde_json::Value =
+        serde_json::from_slice(&run(&["validate", "--json"], &wrong_band).stdout).unwrap();
+    assert_eq!(report["valid"], false);
+    assert_eq!(report["consistency"].as_array().unwrap().len(), 3);
+
+    let missing = run(&["validate", "does-not-exist.log"], "");
+    assert_eq!(missing.status.code(), Some(2));
+}
+
+#[test]
+fn test_fmt_check_and_rewrite() {
+    let unformatted = run(&["fmt", "--check"], LOG);
+    assert_eq!(unformatted.status.code(), Some(1));
+
+    let formatted = run(&["fmt"], LOG);
+    assert_eq!(formatted.status.code(), Some(0));
+    let formatted = String::from_utf8(formatted.stdout).unwrap();
+    assert_eq!(run(&["fmt", "--check"], &formatted).status.code(), Some(0));
+    assert!(formatted.contains(
+        "QSO:    7006 CW 2009-05-30 0015 AA1ZZZ        599 2      EF8M          599 34\n"
+    ));
+
+    let crlf = run(&["fmt", "--crlf", "--sort-headers"], LOG);
+    let crlf = String::from_utf8(crlf.stdout).unwrap();
+    assert!(crlf.starts_with("START-OF-LOG: 3.0\r\nCALLSIGN: AA1ZZZ\r\nCONTEST: CQ-WPX-CW\r\n"));
+    assert_eq!(
+        run(&["fmt", "--check", "--crlf", "--sort-headers"], &crlf)
+            .status
+            .code(),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_convert_formats() {
+    let json = run(&["convert", "--to", "json"], LOG);
+    let log: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(log["qsos"][1]["rcvd_call"], "EF8M");
+
+    let adif = String::from_utf8(run(&["convert", "--to", "adif"], LOG).stdout).unwrap();
+    assert!(adif.contains("<CALL:4>S50A"));
+    let back = String::from_utf8(run(&["convert", "--to", "csv"], &adif).stdout).unwrap();
+    assert_eq!(back.lines().count(), 4);
+    assert!(back.contains("7005,CW,2009-05-30,0002,AA1ZZZ,599 1,S50A,599 4,,false"));
+}
+
+#[test]
+fn test_stats_and_dupes_json() {
+    let stats: serde_json::Value =
+        serde_json::from_slice(&run(&["stats", "--json"], LOG).stdout).unwrap();
+    assert_eq!(stats["qsos"], 3);
+    assert_eq!(stats["unique_calls"], 2);
+    assert_eq!(stats["per_band"]["40m"], 3);
+
+    let dupes: serde_json::Value =
+        serde_json::from_slice(&run(&["dupes", "--json"], LOG).stdout).unwrap();
+    assert_eq!(dupes[0]["qso"], 3);
+    assert_eq!(dupes[0]["duplicate_of"], 1);
+}
+
+#[test]
+fn test_repair_reports_changes() {
+    let messy = LOG
+        .replace("2009-05-30 0015", "05/30/2009 00:15")
+        .replace("S50A 599 4", "s50a 599 4");
+    let repaired = run(&["repair", "--json"], &messy);
+    assert_eq!(repaired.status.code(), Some(0));
+    assert!(repaired.stderr.is_empty());
+    let report: serde_json::Value = serde_json::from_slice(&repaired.stdout).unwrap();
+    let audit = &report["repairs"];
+    assert_eq!(audit[0]["line"], 4);
+    assert_eq!(audit[0]["kinds"][0], "upper case");
+    assert_eq!(
+        audit[1]["kinds"],
+        serde_json::json!(["date format", "time format"])
+    );
+    let fixed = report["log"].as_str().unwrap();
+    assert_eq!(run(&["validate"], fixed).status.code(), Some(0));
+}
+
+#[test]
+fn test_merge_assigns_tx_ids() {
+    let dir = TempDir::new("merge");
+    let run_log = dir.write("run.log", LOG);
+    let mult_log = dir.write(
+        "mult.log",
+        &LOG.replace("7005 CW 2009-05-30 0002", "14005 CW 2009-05-30 0003")
+            .replace(
+                "7006 CW 2009-05-30 0015 AA1ZZZ 599 2 EF8M 599 34",
+                "7006 CW 2009-05-30 0016 AA1ZZZ 599 2 EF8M 599 43",
+            ),
+    );
+    let merged = run(&["merge", "--json", &run_log, &mult_log], "");
+    assert_eq!(merged.status.code(), Some(0));
+    let report: serde_json::Value = serde_json::from_slice(&merged.stdout).unwrap();
+    let log = report["log"].as_str().unwrap();
+    let txs: Vec<_> = log
+        .lines()
+        .filter(|l| l.starts_with("QSO:"))
+        .map(|l| l.split_whitespace().last().unwrap())
+        .collect();
+    assert_eq!(txs, vec!["0", "1", "0", "1", "0"]);
+    assert_eq!(
+        report["issues"],
+        serde_json::json!([
+            "log 1 QSO #2 and log 2 QSO #2 are the same contact but differ",
+            "log 2 QSO #3 repeats log 1 QSO #3, dropped"
+        ])
+    );
+
+    let combined = format!("{}/combined.log", dir.path());
+    let written = run(
+        &["merge", "--json", "-o", &combined, &run_log, &mult_log],
+        "",
+    );
+    let written: serde_json::Value = serde_json::from_slice(&written.stdout).unwrap();
+    assert_eq!(written["issues"], report["issues"]);
+    assert!(written.get("log").is_none());
+    assert_eq!(dir.read("combined.log"), log);
+}
+
+#[test]
+fn test_merge_three_files_needs_keep_tx() {
+    let dir = TempDir::new("merge3");
+    let files: Vec<_> = ["7005", "14005", "21005"]
+        .iter()
+        .map(|freq| {
+            let log = format!(
+                "START-OF-LOG: 3.0\nCALLSIGN: AA1ZZZ\nCONTEST: CQ-WPX-CW\nQSO: {} CW 2009-05-30 0002 AA1ZZZ 599 1 S50A 599 4 1\nEND-OF-LOG:\n",
+                freq
+            );
+            dir.write(&format!("{}.log", freq), &log)
+        })
+        .collect();
+    let mut args = vec!["merge"];
+    args.extend(files.iter().map(String::as_str));
+
+    let rejected = run(&args, "");
+    assert_eq!(rejected.status.code(), Some(1));
+    assert!(rejected.stdout.is_empty());
+    assert!(
+        String::from_utf8(rejected.stderr)
+            .unwrap()
+            .contains("error: 3 logs cannot get TX IDs 0 and 1, keeping TX IDs as logged")
+    );
+
+    args.push("--keep-tx");
+    let kept = run(&args, "");
+    assert_eq!(kept.status.code(), Some(0));
+    let merged = String::from_utf8(kept.stdout).unwrap();
+    assert_eq!(run(&["validate"], &merged).status.code(), Some(0));
+}
+
+#[test]
+fn test_split_writes_parts() {
+    let dir = TempDir::new("split");
+    let input = dir.write("entry.log", &LOG.replace("7006 CW", "14006 CW"));
+    let split = run(&["split", "--json", &input], "");
+    assert_eq!(split.status.code(), Some(0));
+    let parts: serde_json::Value = serde_json::from_slice(&split.stdout).unwrap();
+    assert_eq!(parts[0]["part"], "40m");
+    assert_eq!(parts[0]["qsos"], 2);
+    assert_eq!(parts[1]["part"], "20m");
+    let twenty = dir.read("entry-20m.log");
+    assert!(twenty.contains("CATEGORY-BAND: 20M"));
+    assert_eq!(run(&["validate"], &twenty).status.code(), Some(0));
+}
+
+#[test]
+fn test_diff_reports_changes() {
+    let dir = TempDir::new("diff");
+    let old = dir.write("old.log", LOG);
+    let new = dir.write("new.log", &LOG.replace("EF8M 599 34", "EF8M 599 43"));
+    let same = run(&["diff", &old, &old], "");
+    let changed = run(&["diff", "--json", &old, &new], "");
+    assert_eq!(same.status.code(), Some(0));
+    assert!(same.stdout.is_empty());
+    assert_eq!(changed.status.code(), Some(1));
+    let diff: serde_json::Value = serde_json::from_slice(&changed.stdout).unwrap();
+    assert_eq!(diff["qsos"][0]["change"], "edited");
+    assert_eq!(diff["qsos"][0]["new"], 2);
+    assert_eq!(diff["qsos"][0]["fields"][0]["field"], "rcvd serial");
+    assert_eq!(diff["qsos"][0]["fields"][0]["new"], "43");
+}
+
+#[test]
+fn test_crosscheck_writes_ubn_reports() {
+    let dir = TempDir::new("xcheck");
+    let own = dir.write("aa1zzz.log", LOG);
+    let other = dir.write(
+        "s50a.log",
+        "START-OF-LOG: 3.0\nCALLSIGN: S50A\nCONTEST: CQ-WPX-CW\nQSO: 7005 CW 2009-05-30 0003 S50A 599 4 AA1ZZZ 599 1\nEND-OF-LOG:\n",
+    );
+    let checked = run(
+        &["crosscheck", "--json", "--dir", dir.path(), &own, &other],
+        "",
+    );
+    let ubn = dir.read("AA1ZZZ.ubn");
+    assert_eq!(checked.status.code(), Some(1));
+    let reports: serde_json::Value = serde_json::from_slice(&checked.stdout).unwrap();
+    assert_eq!(reports[0]["callsign"], "AA1ZZZ");
+    assert_eq!(reports[0]["checks"][0]["result"], "matched");
+    assert_eq!(reports[0]["checks"][1]["result"], "unique");
+    assert_eq!(reports[0]["checks"][2]["result"], "not in log");
+    assert!(ubn.starts_with("UBN report for AA1ZZZ\n3 QSOs checked: 1 matched,"));
+
+    // Reports of logs without a call of their own, or sharing one, are named after the file
+    let empty = dir.write("empty.log", "START-OF-LOG: 3.0\nEND-OF-LOG:\n");
+    let copy = dir.write("aa1zzz-copy.log", LOG);
+    let checked = run(
+        &["crosscheck", "--dir", dir.path(), &own, &copy, &empty],
+        "",
+    );
+    assert_eq!(checked.status.code(), Some(0));
+    assert!(
+        dir.read("aa1zzz.ubn")
+            .starts_with("UBN report for AA1ZZZ\n")
+    );
+    assert!(
+        dir.read("aa1zzz-copy.ubn")
+            .starts_with("UBN report for AA1ZZZ\n")
+    );
+    assert!(
+        dir.read("empty.ubn")
+            .starts_with("UBN report for \n0 QSOs checked")
+    );
+}
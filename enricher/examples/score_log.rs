@@ -0,0 +1,22 @@
+use cabrillo_log::{CabrilloLog, rules_for_contest};
+use enricher::station_info;
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: score_log <log file>");
+    let content = std::fs::read_to_string(&path).expect("cannot read log");
+    let log = CabrilloLog::parse(&content).expect("cannot parse log");
+
+    let contest = log.headers.contest().unwrap_or_default();
+    let Some(rules) = rules_for_contest(contest) else {
+        println!("No scoring rules for contest {:?}", contest);
+        return;
+    };
+    let report = log.score(rules, &station_info);
+    print!("{}", report);
+    println!("CLAIMED-SCORE: {}", report.score);
+    for issue in log.check_consistency(Some(report.score)) {
+        println!("{}: {}", issue.severity(), issue);
+    }
+}
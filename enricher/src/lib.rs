@@ -1,3 +1,5 @@
+use cabrillo_log::{Callsign, StationInfo};
+
 #[derive(Debug, Clone)]
 pub struct Entity {
     pub main_prefix: &'static str,
@@ -20,6 +22,30 @@ pub fn enrich_callsign(callsign: &str) -> Option<&'static Entity> {
     prefixes.into_iter().filter_map(|p| ENTITIES.get(&p)).next()
 }
 
+/// Entity of a parsed callsign. A prefix override wins over the base call
+/// (`DL/W1AW` is in Germany), and `/MM` or `/AM` stations are in no entity.
+pub fn enrich(call: &Callsign) -> Option<&'static Entity> {
+    call.entity_call().and_then(enrich_callsign)
+}
+
+impl From<&Entity> for StationInfo {
+    fn from(entity: &Entity) -> Self {
+        StationInfo {
+            dxcc: entity.dxcc,
+            country: entity.country.to_string(),
+            continent: entity.continent.to_string(),
+            cq_zone: entity.cq_zone,
+            itu_zone: entity.itu_zone,
+        }
+    }
+}
+
+/// Station lookup for [`CabrilloLog::score`](cabrillo_log::CabrilloLog::score),
+/// from the entity of the call.
+pub fn station_info(call: &Callsign) -> Option<StationInfo> {
+    enrich(call).map(StationInfo::from)
+}
+
 pub fn enrich_callsign2(callsign: &str) -> Option<Entity> {
     // Find the longest matching prefix
     let mut best_match: Option<&Entity> = None;
@@ -82,4 +108,34 @@ mod tests {
             assert_eq!(entity.dxcc, expected_dxcc, "DXCC mismatch for {}", callsign);
         }
     }
+
+    #[test]
+    fn test_enrich_parsed_callsign() {
+        let enrich_str = |call: &str| enrich(&call.parse().unwrap()).map(|e| e.country);
+        assert_eq!(enrich_str("W1AW"), Some("United States"));
+        assert_eq!(enrich_str("SP/W1AW/P"), Some("Poland"));
+        assert_eq!(enrich_str("W1AW/KH6"), Some("Hawaii"));
+        assert_eq!(enrich_str("SP5TLS/MM"), None);
+    }
+
+    #[test]
+    fn test_score_with_entities() {
+        let log = cabrillo_log::CabrilloLog::parse(
+            "START-OF-LOG: 3.0
+CALLSIGN: W1AW
+CONTEST: CQ-WW-CW
+QSO: 14005 CW 2009-05-30 0002 W1AW 599 5 SP5TLS 599 15
+QSO: 14010 CW 2009-05-30 0010 W1AW 599 5 VE3XX 599 4
+QSO: 7005 CW 2009-05-30 0020 W1AW 599 5 K1ABC 599 5
+END-OF-LOG:
+",
+        )
+        .unwrap();
+        let rules = cabrillo_log::rules_for_contest("CQ-WW-CW").unwrap();
+        let report = log.score(rules, &station_info);
+        let points: Vec<_> = report.qsos.iter().map(|q| q.points).collect();
+        assert_eq!(points, vec![3, 2, 0]);
+        assert_eq!(report.multipliers.len(), 6);
+        assert_eq!(report.score, 30);
+    }
 }
@@ -15,7 +15,7 @@
 //! use stats::QsoStats;
 //!
 //! let log = CabrilloLog::parse("START-OF-LOG: 3.0\nQSO: 14000 CW 2023-10-01 1200 N1MM 599 001 W1AW 599 001\nEND-OF-LOG:").unwrap();
-//! let mut stats = QsoStats::new(log.qsos).unwrap();
+//! let mut stats = QsoStats::new(log.qsos, false).unwrap();
 //!
 //! // Get total QSO count
 //! let total = stats.total_qso_count(None).unwrap();
@@ -26,42 +26,13 @@
 //! println!("QSOs per band: {:?}", per_band);
 //! ```
 
-use cabrillo_log::QSO;
+use cabrillo_log::{Callsign, QSO};
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
-use enricher::enrich_callsign;
+use enricher::enrich;
 use gluesql::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-/// Map frequency string to ham radio band name
-pub fn frequency_to_band(freq_str: &str) -> String {
-    // Parse frequency as float (MHz)
-    let freq_khz = match freq_str.parse::<f64>() {
-        Ok(f) => f,
-        Err(_) => return "Unknown".to_string(),
-    };
-
-    // Convert to Hz for easier comparison
-    let freq_hz = freq_khz * 1_000.0;
-
-    match freq_hz {
-        f if (1_800_000.0..=2_000_000.0).contains(&f) => "160m".to_string(),
-        f if (3_500_000.0..=4_000_000.0).contains(&f) => "80m".to_string(),
-        f if (7_000_000.0..=7_300_000.0).contains(&f) => "40m".to_string(),
-        f if (10_100_000.0..=10_150_000.0).contains(&f) => "30m".to_string(),
-        f if (14_000_000.0..=14_350_000.0).contains(&f) => "20m".to_string(),
-        f if (18_068_000.0..=18_168_000.0).contains(&f) => "17m".to_string(),
-        f if (21_000_000.0..=21_450_000.0).contains(&f) => "15m".to_string(),
-        f if (24_890_000.0..=24_990_000.0).contains(&f) => "12m".to_string(),
-        f if (28_000_000.0..=29_700_000.0).contains(&f) => "10m".to_string(),
-        f if (50_000_000.0..=54_000_000.0).contains(&f) => "6m".to_string(),
-        f if (70_000_000.0..=70_500_000.0).contains(&f) => "4m".to_string(),
-        f if (144_000_000.0..=148_000_000.0).contains(&f) => "2m".to_string(),
-        f if (420_000_000.0..=450_000_000.0).contains(&f) => "70cm".to_string(),
-        _ => "Unknown".to_string(),
-    }
-}
-
 /// Errors that can occur during statistics operations.
 #[derive(Debug, Clone, PartialEq)]
 pub enum StatsError {
@@ -96,7 +67,10 @@ pub struct EnrichedQso {
     pub id: i64,
     pub timestamp: DateTime<Utc>,
     pub freq: String,
+    /// Cabrillo mode category, e.g. `DG`.
     pub mode: String,
+    /// Precise mode if known (e.g. `FT8`), otherwise the Cabrillo category.
+    pub precise_mode: String,
     pub sent_call: String,
     pub rcvd_call: String,
     pub country: Option<String>,
@@ -105,6 +79,7 @@ pub struct EnrichedQso {
     pub continent: Option<String>,
     pub dxcc: Option<u32>,
     pub band_name: String,
+    pub excluded: bool,
 }
 
 /// Filter options for statistics queries.
@@ -115,8 +90,11 @@ pub struct QsoFilter {
     pub cq_zone: Option<u32>,
     pub itu_zone: Option<u32>,
     pub mode: Option<String>,
+    pub precise_mode: Option<String>,
     pub start_date: Option<DateTime<Utc>>,
     pub end_date: Option<DateTime<Utc>>,
+    /// `Some(false)` counts only scored QSOs, `Some(true)` only X-QSOs.
+    pub excluded: Option<bool>,
 }
 
 /// Filter options for statistics queries.
@@ -149,6 +127,53 @@ pub struct TimeSeriesPoint {
     pub count: u32,
 }
 
+/// A country worked in the log, with the callsigns contacted there.
+///
+/// Shared by the web front ends to place one map marker per DXCC entity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapMarker {
+    pub country: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub cq_zone: u32,
+    pub itu_zone: u32,
+    pub dxcc: u32,
+    pub callsigns: Vec<String>,
+}
+
+/// Group the sent and received callsigns of `qsos` by country, sorted by country name.
+///
+/// X-QSOs are left out unless `include_excluded` is set.
+pub fn map_markers(qsos: &[QSO], include_excluded: bool) -> Vec<MapMarker> {
+    let mut countries: HashMap<&str, MapMarker> = HashMap::new();
+
+    for qso in qsos.iter().filter(|q| include_excluded || !q.excluded) {
+        for call in [&qso.sent_call, &qso.rcvd_call] {
+            let Some(entity) = Callsign::parse(call).ok().and_then(|c| enrich(&c)) else {
+                continue;
+            };
+            let marker = countries
+                .entry(entity.country)
+                .or_insert_with(|| MapMarker {
+                    country: entity.country.to_string(),
+                    latitude: entity.latitude,
+                    longitude: entity.longitude,
+                    cq_zone: entity.cq_zone,
+                    itu_zone: entity.itu_zone,
+                    dxcc: entity.dxcc,
+                    callsigns: Vec::new(),
+                });
+            if !marker.callsigns.contains(call) {
+                marker.callsigns.push(call.clone());
+            }
+        }
+    }
+
+    let mut markers: Vec<MapMarker> = countries.into_values().collect();
+    markers.sort_by(|a, b| a.country.cmp(&b.country));
+    markers
+}
+
 /// Main statistics analyzer for QSO data.
 pub struct QsoStats {
     glue: Glue<MemoryStorage>,
@@ -158,14 +183,19 @@ impl QsoStats {
     /// Create a new QsoStats instance from a vector of QSOs.
     ///
     /// This will enrich the QSO data with country/zone information and store
-    /// everything in an in-memory database.
-    pub fn new(qsos: Vec<QSO>) -> Result<Self, StatsError> {
+    /// everything in an in-memory database. X-QSOs are left out unless
+    /// `include_excluded` is set; [`QsoFilter::excluded`] then tells them apart.
+    pub fn new(qsos: Vec<QSO>, include_excluded: bool) -> Result<Self, StatsError> {
         let mut glue = Glue::new(MemoryStorage::default());
 
         // Create tables
         Self::create_tables(&mut glue)?;
 
         // Enrich and insert QSOs
+        let qsos = qsos
+            .into_iter()
+            .filter(|q| include_excluded || !q.excluded)
+            .collect();
         Self::insert_qsos(&mut glue, qsos)?;
 
         Ok(QsoStats { glue })
@@ -180,13 +210,15 @@ impl QsoStats {
                 band TEXT,
                 band_name TEXT,
                 mode TEXT,
+                precise_mode TEXT,
                 sent_call TEXT,
                 rcvd_call TEXT,
                 country TEXT,
                 cq_zone INTEGER,
                 itu_zone INTEGER,
                 continent TEXT,
-                dxcc INTEGER
+                dxcc INTEGER,
+                excluded BOOLEAN
             );
         ";
 
@@ -200,20 +232,22 @@ impl QsoStats {
             let enriched = Self::enrich_qso(qso)?;
             let sql = format!(
                 "INSERT INTO qsos VALUES (
-                    {}, '{}', '{}', '{}', '{}', '{}', '{}', '{}', {}, {}, '{}', {}
+                    {}, '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', {}, {}, '{}', {}, {}
                 )",
                 id,
                 enriched.timestamp.to_rfc3339(),
                 enriched.freq,
                 enriched.band_name,
                 enriched.mode,
+                enriched.precise_mode,
                 enriched.sent_call,
                 enriched.rcvd_call,
                 enriched.country.unwrap_or_default(),
                 enriched.cq_zone.unwrap_or(0),
                 enriched.itu_zone.unwrap_or(0),
                 enriched.continent.unwrap_or_default(),
-                enriched.dxcc.unwrap_or(0)
+                enriched.dxcc.unwrap_or(0),
+                enriched.excluded
             );
             futures::executor::block_on(glue.execute(&sql))?;
         }
@@ -228,17 +262,20 @@ impl QsoStats {
         let timestamp = Utc.from_utc_datetime(&datetime);
 
         // Enrich received callsign
-        let entity = enrich_callsign(&qso.rcvd_call);
+        let entity = Callsign::parse(&qso.rcvd_call)
+            .ok()
+            .and_then(|call| enrich(&call));
 
         // Map frequency to band name
-        let band_name = frequency_to_band(&qso.freq);
+        let band_name = qso.freq.band().map_or("Unknown", |b| b.name()).to_string();
 
         let enriched = EnrichedQso {
             id: 0, // Will be set by database
             timestamp,
-            freq: qso.freq,
+            freq: qso.freq.to_string(),
             band_name,
-            mode: qso.mode,
+            mode: qso.mode.to_string(),
+            precise_mode: qso.mode.precise_or_category().to_string(),
             sent_call: qso.sent_call,
             rcvd_call: qso.rcvd_call,
             country: entity.map(|e| e.country.to_string()),
@@ -246,6 +283,7 @@ impl QsoStats {
             itu_zone: entity.map(|e| e.itu_zone),
             continent: entity.map(|e| e.continent.to_string()),
             dxcc: entity.map(|e| e.dxcc),
+            excluded: qso.excluded,
         };
 
         Ok(enriched)
@@ -368,7 +406,7 @@ impl QsoStats {
         self.group_by_column("continent", filter)
     }
 
-    /// Get QSO count per mode.
+    /// Get QSO count per Cabrillo mode (CW, PH, FM, RY, DG).
     pub fn qso_per_mode(
         &mut self,
         filter: Option<&QsoFilter>,
@@ -376,6 +414,15 @@ impl QsoStats {
         self.group_by_column("mode", filter)
     }
 
+    /// Get QSO count per precise mode (FT8, RTTY, SSB, ...), falling back to the
+    /// Cabrillo mode for QSOs logged without one.
+    pub fn qso_per_precise_mode(
+        &mut self,
+        filter: Option<&QsoFilter>,
+    ) -> Result<Vec<(String, u32)>, StatsError> {
+        self.group_by_column("precise_mode", filter)
+    }
+
     /// Get QSO count per CQ zone.
     pub fn qso_per_cq_zone(
         &mut self,
@@ -584,12 +631,18 @@ impl QsoStats {
             if let Some(ref mode) = filter.mode {
                 conditions.push(format!("mode = '{}'", mode));
             }
+            if let Some(ref precise_mode) = filter.precise_mode {
+                conditions.push(format!("precise_mode = '{}'", precise_mode));
+            }
             if let Some(start_date) = filter.start_date {
                 conditions.push(format!("timestamp >= '{}'", start_date.to_rfc3339()));
             }
             if let Some(end_date) = filter.end_date {
                 conditions.push(format!("timestamp <= '{}'", end_date.to_rfc3339()));
             }
+            if let Some(excluded) = filter.excluded {
+                conditions.push(format!("excluded = {}", excluded));
+            }
 
             if conditions.is_empty() {
                 ("".to_string(), Vec::new())
@@ -605,32 +658,36 @@ impl QsoStats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cabrillo_log::QSO;
+    use cabrillo_log::{Band, CabrilloMode, Exchange, Frequency, Mode, QSO};
     use chrono::{NaiveDate, NaiveTime};
 
     fn create_test_qsos() -> Vec<QSO> {
         vec![
             QSO {
-                freq: "14000".to_string(),
-                mode: "CW".to_string(),
+                freq: Frequency::Khz(14000.0),
+                mode: Mode::new(CabrilloMode::Cw),
                 date: NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
                 time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
                 sent_call: "N1MM".to_string(),
-                sent_rst_exch: "599 001".to_string(),
+                sent_exch: Exchange::from_untyped(["599", "001"]),
                 rcvd_call: "W1AW".to_string(),
-                rcvd_rst_exch: "599 001".to_string(),
+                rcvd_exch: Exchange::from_untyped(["599", "001"]),
                 tx: None,
+                excluded: false,
+                zero_point: false,
             },
             QSO {
-                freq: "7000".to_string(),
-                mode: "PH".to_string(),
+                freq: Frequency::Khz(7000.0),
+                mode: Mode::precise("SSB"),
                 date: NaiveDate::from_ymd_opt(2023, 10, 1).unwrap(),
                 time: NaiveTime::from_hms_opt(12, 30, 0).unwrap(),
                 sent_call: "N1MM".to_string(),
-                sent_rst_exch: "59 001".to_string(),
+                sent_exch: Exchange::from_untyped(["59", "001"]),
                 rcvd_call: "SP5TLS".to_string(),
-                rcvd_rst_exch: "59 001".to_string(),
+                rcvd_exch: Exchange::from_untyped(["59", "001"]),
                 tx: None,
+                excluded: false,
+                zero_point: false,
             },
         ]
     }
@@ -638,14 +695,14 @@ mod tests {
     #[test]
     fn test_new_creates_stats() {
         let qsos = create_test_qsos();
-        let mut stats = QsoStats::new(qsos).unwrap();
+        let mut stats = QsoStats::new(qsos, false).unwrap();
         assert_eq!(stats.total_qso_count(None).unwrap(), 2);
     }
 
     #[test]
     fn test_total_qso_count_with_filter() {
         let qsos = create_test_qsos();
-        let mut stats = QsoStats::new(qsos).unwrap();
+        let mut stats = QsoStats::new(qsos, false).unwrap();
 
         let filter = QsoFilter {
             band: Some("14000".to_string()),
@@ -660,30 +717,97 @@ mod tests {
         assert_eq!(stats.total_qso_count(Some(&filter)).unwrap(), 1);
     }
 
+    #[test]
+    fn test_total_qso_count_excluded_filter() {
+        let mut qsos = create_test_qsos();
+        qsos[1].excluded = true;
+        let mut counted = QsoStats::new(qsos.clone(), false).unwrap();
+        assert_eq!(counted.total_qso_count(None).unwrap(), 1);
+
+        let mut stats = QsoStats::new(qsos, true).unwrap();
+        assert_eq!(stats.total_qso_count(None).unwrap(), 2);
+        let filter = QsoFilter {
+            excluded: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(stats.total_qso_count(Some(&filter)).unwrap(), 1);
+        let filter = QsoFilter {
+            excluded: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(stats.total_qso_count(Some(&filter)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_qso_per_mode_and_precise_mode() {
+        let mut qsos = create_test_qsos();
+        let mut ft8 = qsos[0].clone();
+        ft8.mode = Mode::precise("FT8");
+        qsos.push(ft8);
+        let mut stats = QsoStats::new(qsos, false).unwrap();
+
+        let per_mode = stats.qso_per_mode(None).unwrap();
+        assert!(per_mode.contains(&("CW".to_string(), 1)));
+        assert!(per_mode.contains(&("PH".to_string(), 1)));
+        assert!(per_mode.contains(&("DG".to_string(), 1)));
+
+        let per_precise_mode = stats.qso_per_precise_mode(None).unwrap();
+        assert!(per_precise_mode.contains(&("CW".to_string(), 1)));
+        assert!(per_precise_mode.contains(&("SSB".to_string(), 1)));
+        assert!(per_precise_mode.contains(&("FT8".to_string(), 1)));
+    }
+
     #[test]
     fn test_qso_per_band() {
         let qsos = create_test_qsos();
-        let mut stats = QsoStats::new(qsos).unwrap();
+        let mut stats = QsoStats::new(qsos, false).unwrap();
 
         let per_band = stats.qso_per_band(None).unwrap();
         assert!(per_band.iter().any(|x| x.0 == "20m" && x.1 == 1));
         assert!(per_band.iter().any(|x| x.0 == "40m" && x.1 == 1));
     }
 
+    #[test]
+    fn test_qso_per_band_designator() {
+        let mut qsos = create_test_qsos();
+        qsos[1].freq = Frequency::Band(Band::B6M);
+        let mut stats = QsoStats::new(qsos, false).unwrap();
+
+        let per_band = stats.qso_per_band(None).unwrap();
+        assert!(per_band.iter().any(|x| x.0 == "6m" && x.1 == 1));
+    }
+
     #[test]
     fn test_qso_per_country() {
         let qsos = create_test_qsos();
-        let mut stats = QsoStats::new(qsos).unwrap();
+        let mut stats = QsoStats::new(qsos, false).unwrap();
 
         let per_country = stats.qso_per_country(None).unwrap();
         assert!(per_country.iter().any(|x| x.0 == "United States"));
         assert!(per_country.iter().any(|x| x.0 == "Poland"));
     }
 
+    #[test]
+    fn test_map_markers() {
+        let markers = map_markers(&create_test_qsos(), false);
+        let countries: Vec<_> = markers.iter().map(|m| m.country.as_str()).collect();
+        assert_eq!(countries, vec!["Poland", "United States"]);
+        assert_eq!(markers[1].callsigns, vec!["N1MM", "W1AW"]);
+
+        let mut qsos = create_test_qsos();
+        qsos[1].excluded = true;
+        let countries: Vec<_> = map_markers(&qsos, false)
+            .into_iter()
+            .map(|m| m.country)
+            .collect();
+        assert_eq!(countries, vec!["United States"]);
+        assert_eq!(map_markers(&qsos, true).len(), 2);
+    }
+
     #[test]
     fn test_time_interval_stats() {
         let qsos = create_test_qsos();
-        let mut stats = QsoStats::new(qsos).unwrap();
+        let mut stats = QsoStats::new(qsos, false).unwrap();
 
         let intervals = stats.time_interval_stats(None).unwrap();
         assert_eq!(intervals.count, 1);
@@ -695,7 +819,7 @@ mod tests {
     #[test]
     fn test_time_series_qso_frequency() {
         let qsos = create_test_qsos();
-        let mut stats = QsoStats::new(qsos).unwrap();
+        let mut stats = QsoStats::new(qsos, false).unwrap();
 
         let series = stats.time_series_qso_frequency(None).unwrap();
         assert!(!series.is_empty());
@@ -711,7 +835,7 @@ mod tests {
     #[test]
     fn test_qso_per_country_band() {
         let qsos = create_test_qsos();
-        let mut stats = QsoStats::new(qsos).unwrap();
+        let mut stats = QsoStats::new(qsos, false).unwrap();
 
         let per_country_band = stats.qso_per_country_band(None).unwrap();
         assert_eq!(per_country_band.len(), 2);
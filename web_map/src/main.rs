@@ -5,28 +5,19 @@ use axum::{
     response::{Html, Json},
     routing::{get, post},
 };
-use serde::{Deserialize, Serialize};
+use cabrillo_log::CabrilloLog;
+use stats::MapMarker;
 use std::net::SocketAddr;
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, services::ServeDir};
 
-#[derive(Serialize, Deserialize)]
-struct MapMarker {
-    country: String,
-    latitude: f64,
-    longitude: f64,
-    cq_zone: u32,
-    itu_zone: u32,
-    dxcc: u32,
-    callsigns: Vec<String>,
-}
-
 #[tokio::main]
 async fn main() {
     // Build the application with routes
     let app = Router::new()
         .route("/", get(index))
         .route("/upload", post(upload_log))
+        .route("/parse", post(parse_log))
         .nest_service("/static", ServeDir::new("static"))
         .layer(ServiceBuilder::new().layer(CorsLayer::permissive()));
 
@@ -41,7 +32,8 @@ async fn index() -> Html<&'static str> {
     Html(include_str!("../static/index.html"))
 }
 
-async fn upload_log(mut multipart: Multipart) -> Result<Json<Vec<MapMarker>>, StatusCode> {
+/// Read the uploaded `logfile` field and parse it as a Cabrillo log.
+async fn read_log(mut multipart: Multipart) -> Result<CabrilloLog, StatusCode> {
     let mut file_content = None;
 
     while let Some(field) = multipart.next_field().await.unwrap() {
@@ -56,52 +48,14 @@ async fn upload_log(mut multipart: Multipart) -> Result<Json<Vec<MapMarker>>, St
         None => return Err(StatusCode::BAD_REQUEST),
     };
 
-    // Parse the Cabrillo log
-    let log = cabrillo_log::CabrilloLog::parse(&content).map_err(|_| StatusCode::BAD_REQUEST)?;
-
-    // Process QSOs and collect unique countries with their callsigns
-    let mut country_contacts: std::collections::HashMap<String, (enricher::Entity, Vec<String>)> =
-        std::collections::HashMap::new();
-
-    for qso in &log.qsos {
-        // Try to enrich both sent and received callsigns
-        let sent_entity = enricher::enrich_callsign(&qso.sent_call);
-        let rcvd_entity = enricher::enrich_callsign(&qso.rcvd_call);
-
-        // Add to country contacts for sent callsign
-        if let Some(entity) = sent_entity {
-            let entry = country_contacts
-                .entry(entity.country.to_string())
-                .or_insert((entity.clone(), Vec::new()));
-            if !entry.1.contains(&qso.sent_call) {
-                entry.1.push(qso.sent_call.clone());
-            }
-        }
-
-        // Add to country contacts for received callsign
-        if let Some(entity) = rcvd_entity {
-            let entry = country_contacts
-                .entry(entity.country.to_string())
-                .or_insert((entity.clone(), Vec::new()));
-            if !entry.1.contains(&qso.rcvd_call) {
-                entry.1.push(qso.rcvd_call.clone());
-            }
-        }
-    }
+    CabrilloLog::parse(&content).map_err(|_| StatusCode::BAD_REQUEST)
+}
 
-    // Convert to markers
-    let markers: Vec<MapMarker> = country_contacts
-        .into_iter()
-        .map(|(_, (entity, callsigns))| MapMarker {
-            country: entity.country.to_string(),
-            latitude: entity.latitude,
-            longitude: entity.longitude,
-            cq_zone: entity.cq_zone,
-            itu_zone: entity.itu_zone,
-            dxcc: entity.dxcc,
-            callsigns,
-        })
-        .collect();
+async fn upload_log(multipart: Multipart) -> Result<Json<Vec<MapMarker>>, StatusCode> {
+    let log = read_log(multipart).await?;
+    Ok(Json(stats::map_markers(&log.qsos, false)))
+}
 
-    Ok(Json(markers))
+async fn parse_log(multipart: Multipart) -> Result<Json<CabrilloLog>, StatusCode> {
+    Ok(Json(read_log(multipart).await?))
 }
@@ -1,24 +1,13 @@
+use cabrillo_log::Callsign;
 use chrono::Timelike;
 use js_sys::Promise;
-use serde::{Deserialize, Serialize};
-use stats::{QSOByBand, QsoStats};
+use stats::{MapMarker, QSOByBand, QsoStats};
 use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{File, FileReader};
 use yew::prelude::*;
 
-#[derive(Serialize, Deserialize, Clone, PartialEq)]
-struct MapMarker {
-    country: String,
-    latitude: f64,
-    longitude: f64,
-    cq_zone: u32,
-    itu_zone: u32,
-    dxcc: u32,
-    callsigns: Vec<String>,
-}
-
 #[derive(Clone)]
 struct EnrichedQSO {
     qso: cabrillo_log::QSO,
@@ -309,14 +298,14 @@ fn app() -> Html {
                                     <tr onmouseenter={on_mouse_enter} onmouseleave={on_mouse_leave.clone()}>
                                         <td>{enriched_qso.qso.date.format("%Y-%m-%d").to_string()}</td>
                                         <td>{enriched_qso.qso.time.format("%H%M").to_string()}</td>
-                                        <td>{&enriched_qso.qso.freq}</td>
-                                        <td>{&enriched_qso.qso.mode}</td>
+                                        <td>{enriched_qso.qso.freq.to_string()}</td>
+                                        <td>{enriched_qso.qso.mode.precise_or_category()}</td>
                                         <td>{rcvd_country}</td>
                                         <td>{&enriched_qso.qso.sent_call}</td>
-                                        <td>{&enriched_qso.qso.sent_rst_exch}</td>
+                                        <td>{enriched_qso.qso.sent_exch.to_string()}</td>
                                         <td>{enriched_qso.qso.tx.as_ref().unwrap_or(&"".to_string())}</td>
                                         <td>{&enriched_qso.qso.rcvd_call}</td>
-                                        <td>{&enriched_qso.qso.rcvd_rst_exch}</td>
+                                        <td>{enriched_qso.qso.rcvd_exch.to_string()}</td>
                                     </tr>
                                 }
                             })}
@@ -362,58 +351,20 @@ async fn process_file(
     // Parse the Cabrillo log
     let log = cabrillo_log::CabrilloLog::parse(&content)
         .map_err(|e| JsValue::from_str(&format!("Failed to parse log: {:?}", e)))?;
-    // Process QSOs and collect unique countries with their callsigns
-    let mut country_contacts: HashMap<String, (enricher::Entity, Vec<String>)> = HashMap::new();
-    let mut enriched_qsos: Vec<EnrichedQSO> = Vec::new();
-
-    for qso in &log.qsos {
-        // Try to enrich both sent and received callsigns
-        let sent_entity = enricher::enrich_callsign(&qso.sent_call);
-        let rcvd_entity = enricher::enrich_callsign(&qso.rcvd_call);
-
-        // Add enriched QSO
-        enriched_qsos.push(EnrichedQSO {
+    let enriched_qsos: Vec<EnrichedQSO> = log
+        .counted_qsos()
+        .map(|qso| EnrichedQSO {
             qso: qso.clone(),
-            rcvd_entity: rcvd_entity.cloned(),
-        });
-
-        // Add to country contacts for sent callsign
-        if let Some(entity) = sent_entity {
-            let entry = country_contacts
-                .entry(entity.country.to_string())
-                .or_insert((entity.clone(), Vec::new()));
-            if !entry.1.contains(&qso.sent_call) {
-                entry.1.push(qso.sent_call.clone());
-            }
-        }
-
-        // Add to country contacts for received callsign
-        if let Some(entity) = rcvd_entity {
-            let entry = country_contacts
-                .entry(entity.country.to_string())
-                .or_insert((entity.clone(), Vec::new()));
-            if !entry.1.contains(&qso.rcvd_call) {
-                entry.1.push(qso.rcvd_call.clone());
-            }
-        }
-    }
-
-    // Convert to markers
-    let markers: Vec<MapMarker> = country_contacts
-        .into_iter()
-        .map(|(_, (entity, callsigns))| MapMarker {
-            country: entity.country.to_string(),
-            latitude: entity.latitude,
-            longitude: entity.longitude,
-            cq_zone: entity.cq_zone,
-            itu_zone: entity.itu_zone,
-            dxcc: entity.dxcc,
-            callsigns,
+            rcvd_entity: Callsign::parse(&qso.rcvd_call)
+                .ok()
+                .and_then(|call| enricher::enrich(&call))
+                .cloned(),
         })
         .collect();
+    let markers = stats::map_markers(&log.qsos, false);
 
     // Generate statistics
-    let mut stats_analyzer = QsoStats::new(log.qsos.clone())
+    let mut stats_analyzer = QsoStats::new(log.qsos.clone(), false)
         .map_err(|e| JsValue::from_str(&format!("Failed to create stats: {:?}", e)))?;
 
     let _qso_per_country = stats_analyzer
@@ -453,9 +404,9 @@ async fn process_file(
     // For QSOs per hour and band, we need to extract hour from timestamp
     // Since QsoStats doesn't expose this directly, we'll need to process the QSOs manually
     let mut qso_per_hour_band_real: HashMap<String, HashMap<String, u32>> = HashMap::new();
-    for qso in &log.qsos {
+    for qso in log.counted_qsos() {
         let hour = format!("{:02}", qso.time.hour());
-        let band = stats::frequency_to_band(&qso.freq);
+        let band = qso.freq.band().map_or("Unknown", |b| b.name()).to_string();
         *qso_per_hour_band_real
             .entry(band)
             .or_default()